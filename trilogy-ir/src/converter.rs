@@ -77,12 +77,12 @@ impl<'a> Converter<'a> {
             .take(span.last().line - span.start().line + 1)
             .map(|line| line.to_owned())
             .collect::<Vec<_>>();
+        let last = lines.last_mut().unwrap();
+        *last = last.chars().take(span.end().column).collect::<String>();
         lines[0] = lines[0]
             .chars()
             .skip(span.start().column)
             .collect::<String>();
-        let last = lines.last_mut().unwrap();
-        *last = last.chars().take(span.end().column).collect::<String>();
         lines.join("")
     }
 }
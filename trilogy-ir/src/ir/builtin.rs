@@ -85,6 +85,8 @@ pub enum Builtin {
     Typeof,
     /// ^
     Pin,
+    /// is, in pattern context
+    TypeCheck,
     Yield,
     Resume,
     Cancel,
@@ -84,6 +84,21 @@ impl Definition {
                 };
                 function.overloads.push(Function::convert(converter, *ast))
             }
+            syntax::DefinitionItem::NamedType(ast) => {
+                let symbol = converter.declared(ast.head.name.as_ref()).unwrap();
+                let definition = definitions.get_mut(symbol).unwrap();
+                let DefinitionItem::Function(function) = &mut definition.item else {
+                    let error = Error::DuplicateDefinition {
+                        original: symbol.declaration_span,
+                        duplicate: ast.head.name,
+                    };
+                    converter.error(error);
+                    return;
+                };
+                function
+                    .overloads
+                    .extend(Function::convert_type(converter, *ast))
+            }
             syntax::DefinitionItem::Import(import) => {
                 let module_ident = if let Some(type_as) = import.type_as {
                     let ident = Identifier::declared(converter, &type_as.identifier).unwrap();
@@ -199,18 +214,9 @@ impl Definition {
                     });
                     return vec![];
                 }
-                let name = Identifier::declare(converter, ast.name.clone());
                 let is_mutable = ast.r#mut.is_some();
-                if is_mutable {
-                    converter.error(Error::Unimplemented {
-                        feature: "mutable slots",
-                        span: ast.r#mut.as_ref().unwrap().span,
-                    })
-                }
-                Self::new(
-                    ast.span(),
-                    SlotDefinition::declare(name, ast.r#mut.is_some()),
-                )
+                let name = Identifier::declare_slot(converter, ast.name.clone(), is_mutable);
+                Self::new(ast.span(), SlotDefinition::declare(name, is_mutable))
             }
             syntax::DefinitionItem::Import(import) => {
                 let name = if let Some(type_as) = &import.type_as {
@@ -281,6 +287,18 @@ impl Definition {
                 let name = Identifier::declare(converter, ast.head.name.clone());
                 Self::new(ast.span(), ModuleDefinition::declare(name))
             }
+            syntax::DefinitionItem::NamedType(ast) => {
+                if let Some(original) = converter.declared_no_shadow(ast.head.name.as_ref()) {
+                    let original = original.declaration_span;
+                    converter.error(Error::DuplicateDefinition {
+                        original,
+                        duplicate: ast.head.name.clone(),
+                    });
+                    return vec![];
+                }
+                let name = Identifier::declare(converter, ast.head.name.clone());
+                Self::new(ast.span(), FunctionDefinition::declare(name))
+            }
             syntax::DefinitionItem::Procedure(ast) => {
                 if let Some(original) = converter.declared_no_shadow(ast.head.name.as_ref()) {
                     let original = original.declaration_span;
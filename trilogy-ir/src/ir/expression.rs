@@ -87,6 +87,7 @@ impl Expression {
                 }),
             ),
             Keyword(ast) => Builtin::convert(*ast),
+            Section(ast) => Self::convert_section(converter, *ast),
             Application(ast) => Self::application(
                 ast.span(),
                 Self::convert(converter, ast.function),
@@ -118,6 +119,7 @@ impl Expression {
             }
             Let(ast) => crate::ir::Let::convert(converter, *ast),
             IfElse(ast) => crate::ir::IfElse::convert_expression(converter, *ast),
+            For(ast) => Self::convert_for(converter, *ast),
             Match(ast) => crate::ir::Match::convert_expression(converter, *ast),
             Is(ast) => Self::application(
                 ast.span(),
@@ -255,15 +257,9 @@ impl Expression {
             FunctionAssignment(ast) => crate::ir::Assignment::convert_function(converter, *ast),
             If(ast) => IfElse::convert_expression(converter, *ast),
             Match(ast) => crate::ir::Match::convert_statement(converter, *ast),
-            Defer(ast) => {
-                converter.error(Error::Unimplemented {
-                    feature: "defer statement",
-                    span: ast.span(),
-                });
-                crate::ir::Defer::convert(converter, *ast)
-            }
+            Defer(ast) => crate::ir::Defer::convert(converter, *ast),
             While(ast) => crate::ir::While::convert(converter, *ast),
-            For(ast) => Self::convert_for_statement(converter, *ast),
+            For(ast) => Self::convert_for(converter, *ast),
             Expression(ast) => Self::convert(converter, *ast),
             Assert(ast) => Self::assert(ast.span(), crate::ir::Assert::convert(converter, *ast)),
             Block(ast) => Self::convert_block(converter, *ast),
@@ -302,6 +298,15 @@ impl Expression {
                 .apply_to(ast.span(), Self::convert_pattern(converter, ast.pattern)),
             Typeof(ast) => Self::builtin(ast.type_of.span, Builtin::Typeof)
                 .apply_to(ast.span(), Self::convert_pattern(converter, ast.pattern)),
+            Type(ast) => {
+                // The source of the type is kept alongside it, to describe the type
+                // when a value fails to match.
+                let type_span = ast.r#type.span();
+                let description = converter.get_source(type_span);
+                Self::builtin(ast.is.span, Builtin::TypeCheck)
+                    .apply_to(ast.is.span, Self::string(type_span, description))
+                    .apply_to(ast.span(), Self::convert_type(converter, ast.r#type))
+            }
             Glue(ast) => {
                 let glue_span = ast.glue.span;
                 let lhs_span = ast.lhs.span();
@@ -432,19 +437,52 @@ impl Expression {
         }
     }
 
+    fn convert_type(converter: &mut Converter, ast: syntax::TypeExpression) -> Self {
+        match ast {
+            syntax::TypeExpression::Reference(ast) => Identifier::declared(converter, &ast)
+                .map(|identifier| Self::reference(ast.span(), identifier))
+                .unwrap_or_else(|| {
+                    converter.error(Error::UnboundIdentifier {
+                        name: (*ast).clone(),
+                    });
+                    Self::reference(ast.span(), Identifier::unresolved(converter, *ast))
+                }),
+            syntax::TypeExpression::Application(ast) => {
+                let span = ast.span();
+                let function = Self::convert_type(converter, ast.function);
+                let argument = Self::convert_type(converter, ast.argument);
+                Self::application(span, function, argument)
+            }
+            syntax::TypeExpression::Parenthesized(ast) => Self::convert_type(converter, ast.r#type),
+        }
+    }
+
     pub(super) fn convert_binding(converter: &mut Converter, ast: syntax::BindingPattern) -> Self {
         Self::reference(ast.span(), Identifier::declare_binding(converter, ast))
     }
 
-    fn convert_for_statement(converter: &mut Converter, ast: syntax::ForStatement) -> Self {
+    fn convert_for(converter: &mut Converter, ast: syntax::ForStatement) -> Self {
         let span = ast.span();
+        // The initial value of the accumulator is evaluated before the loop begins, so
+        // cannot see any of the loop's bindings.
+        let initial = ast
+            .accumulator
+            .map(|clause| (clause.identifier, Self::convert(converter, clause.initial)));
         converter.push_scope();
         converter.scope.set_allow_break_continue(false);
         let query = Query::convert(converter, ast.query);
         converter.scope.set_allow_break_continue(true);
+        let accumulator = initial.map(|(identifier, initial)| Accumulator {
+            identifier: Identifier::declare(converter, identifier),
+            initial,
+        });
         let value = Expression::convert_block(converter, ast.body);
         converter.pop_scope();
-        Expression::r#for(span, Iterator::new(query, value))
+        let iterator = match accumulator {
+            Some(accumulator) => Iterator::fold(query, accumulator, value),
+            None => Iterator::new(query, value),
+        };
+        Expression::r#for(span, iterator)
     }
 
     fn convert_iterator(
@@ -459,6 +497,38 @@ impl Expression {
         Iterator::new(query, body)
     }
 
+    /// An operator section is the operator applied to the operand on the side it was
+    /// written, which is straightforward for the left operand. For the right operand, the
+    /// operand is bound first, and the section becomes a function of the left operand.
+    /// In both cases, the operand is evaluated once, when the section is evaluated.
+    fn convert_section(converter: &mut Converter, ast: syntax::OperatorSection) -> Self {
+        let span = ast.span();
+        let operator = Builtin::convert_binary(ast.operator);
+        let operand_span = ast.operand.span();
+        let operand = Self::convert(converter, ast.operand);
+        if ast.side == syntax::SectionSide::Left {
+            return operator.apply_to(span, operand);
+        }
+
+        let bound = Identifier::temporary(converter, operand_span);
+        let parameter = Identifier::temporary(converter, span);
+        let body = operator
+            .apply_to(span, Self::reference(span, parameter.clone()))
+            .apply_to(span, Self::reference(operand_span, bound.clone()));
+        let function = Function {
+            span,
+            head_span: span,
+            parameters: vec![Self::reference(span, parameter)],
+            guard: None,
+            body: Self::builtin(span, Builtin::Return).apply_to(span, body),
+        };
+        let binding = Query::direct(
+            operand_span,
+            Unification::new(Self::reference(operand_span, bound), operand),
+        );
+        Self::function(span, function).in_let(span, binding)
+    }
+
     fn convert_template(converter: &mut Converter, ast: syntax::Template) -> Self {
         let span = ast.span();
         let prefix_str = ast.prefix();
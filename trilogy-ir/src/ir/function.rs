@@ -39,6 +39,58 @@ impl Function {
         }
     }
 
+    /// Converts a named type into the overloads of the predicate function that checks
+    /// for values of that type: the first returns `true` for values that match the
+    /// type's pattern and constraint, and the second returns `false` for everything else.
+    ///
+    /// Parameters of the type become leading parameters of the predicate, so `is List Int`
+    /// calls `List Int value`.
+    pub(super) fn convert_type(
+        converter: &mut Converter,
+        ast: syntax::NamedTypeDefinition,
+    ) -> [Self; 2] {
+        converter.push_scope();
+        let span = ast.span();
+        let head_span = ast.head.span();
+        let mut parameters: Vec<_> = ast
+            .head
+            .parameters
+            .into_iter()
+            .map(|param| Expression::reference(param.span(), Identifier::declare(converter, param)))
+            .collect();
+        parameters.push(Expression::convert_pattern(converter, ast.pattern));
+        let guard = ast.constraint.map(|constraint| {
+            Expression::builtin(constraint.r#where.span, Builtin::Is).apply_to(
+                constraint.span(),
+                Expression::convert_query(converter, constraint.query),
+            )
+        });
+        let matched = Expression::builtin(span, Builtin::Return)
+            .apply_to(span, Expression::boolean(span, true));
+        converter.pop_scope();
+        let unmatched = Self {
+            span,
+            head_span,
+            parameters: parameters
+                .iter()
+                .map(|param| Expression::wildcard(param.span))
+                .collect(),
+            guard: None,
+            body: Expression::builtin(span, Builtin::Return)
+                .apply_to(span, Expression::boolean(span, false)),
+        };
+        [
+            Self {
+                span,
+                head_span,
+                parameters,
+                guard,
+                body: matched,
+            },
+            unmatched,
+        ]
+    }
+
     pub(super) fn convert_fn(converter: &mut Converter, ast: syntax::FnExpression) -> Self {
         converter.push_scope();
         let span = ast.span();
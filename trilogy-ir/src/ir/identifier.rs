@@ -42,12 +42,23 @@ impl Identifier {
     }
 
     pub(crate) fn declare(converter: &mut Converter, identifier: syntax::Identifier) -> Identifier {
+        Self::declare_slot(converter, identifier, false)
+    }
+
+    /// Declares the name of a module-level slot, which may be mutable.
+    pub(crate) fn declare_slot(
+        converter: &mut Converter,
+        identifier: syntax::Identifier,
+        is_mutable: bool,
+    ) -> Identifier {
         let span = identifier.span();
         let id @ Id {
             is_mutable,
             declaration_span,
             ..
-        } = converter.declare(identifier.into(), false, span).clone();
+        } = converter
+            .declare(identifier.into(), is_mutable, span)
+            .clone();
         Self {
             span,
             declaration_span,
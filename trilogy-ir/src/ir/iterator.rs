@@ -4,10 +4,31 @@ use super::*;
 pub struct Iterator {
     pub value: Expression,
     pub query: Query,
+    pub accumulator: Option<Accumulator>,
 }
 
 impl Iterator {
     pub(super) fn new(query: Query, value: Expression) -> Self {
-        Self { value, query }
+        Self {
+            value,
+            query,
+            accumulator: None,
+        }
     }
+
+    pub(super) fn fold(query: Query, accumulator: Accumulator, value: Expression) -> Self {
+        Self {
+            value,
+            query,
+            accumulator: Some(accumulator),
+        }
+    }
+}
+
+/// The accumulator of a `for` expression. It is bound to the initial value for the first
+/// iteration, and then to the value of each previous iteration.
+#[derive(Clone, Debug)]
+pub struct Accumulator {
+    pub identifier: Identifier,
+    pub initial: Expression,
 }
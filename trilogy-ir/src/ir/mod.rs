@@ -48,7 +48,7 @@ pub use handled::Handled;
 pub use handler::Handler;
 pub use identifier::Identifier;
 pub use if_else::IfElse;
-pub use iterator::Iterator;
+pub use iterator::{Accumulator, Iterator};
 pub use r#let::Let;
 pub use lookup::Lookup;
 pub use r#match::{Case, Match};
@@ -22,7 +22,7 @@ impl IrVisitor for IsSingleValuedPattern {
     fn visit_builtin(&mut self, value: &ir::Builtin) {
         match value {
             ir::Builtin::Pin => self.is_pinned = true,
-            ir::Builtin::Typeof => self.result = false,
+            ir::Builtin::Typeof | ir::Builtin::TypeCheck => self.result = false,
             _ => {}
         }
     }
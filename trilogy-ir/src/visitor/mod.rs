@@ -246,6 +246,9 @@ impl IrVisitable for QueryValue {
 
 impl IrVisitable for Iterator {
     fn visit<V: IrVisitor>(&self, visitor: &mut V) {
+        if let Some(accumulator) = &self.accumulator {
+            visitor.visit_expression(&accumulator.initial);
+        }
         visitor.visit_query(&self.query);
         visitor.visit_expression(&self.value);
     }
@@ -36,6 +36,14 @@ impl IrVisitor for References {
                 node.visit(self);
                 self.is_expression = was_expression;
             }
+            // The type of a type pattern is an expression, evaluated to check the value
+            Value::Application(check)
+                if matches!(check.function.value, Value::Builtin(Builtin::TypeCheck)) =>
+            {
+                let was_expression = std::mem::replace(&mut self.is_expression, true);
+                node.visit(self);
+                self.is_expression = was_expression;
+            }
             _ if self.is_expression => node.visit(self),
             _ => {}
         }
@@ -65,6 +73,10 @@ impl IrVisitor for References {
     }
 
     fn visit_iterator(&mut self, node: &Iterator) {
+        if let Some(accumulator) = &node.accumulator {
+            accumulator.initial.visit(self);
+            self.ignore.insert(accumulator.identifier.id.clone());
+        }
         self.ignore.extend(node.query.bindings());
         node.value.visit(self);
     }
@@ -1,4 +1,6 @@
-use std::{fs, path::PathBuf, process::Command};
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+use std::{fs, process::Command};
 
 fn try_command(command: &mut Command) {
     let subprocess = match command.spawn() {
@@ -24,17 +26,67 @@ fn try_command(command: &mut Command) {
     }
 }
 
+fn source_files(dir: &Path, files: &mut Vec<PathBuf>) {
+    for entry in fs::read_dir(dir).unwrap().flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            source_files(&path, files);
+        } else if path.extension().is_some_and(|ext| ext == "rs") {
+            files.push(path);
+        }
+    }
+}
+
+/// Identifies the compiler by a hash of everything that determines the code it generates:
+/// its version, its source and that of the crates it lowers from, and the compiled core.
+fn fingerprint(manifest_dir: &Path, core: &Path) {
+    let mut files = vec![];
+    for dir in [
+        "src",
+        "../trilogy-ir/src",
+        "../trilogy-parser/src",
+        "../trilogy-scanner/src",
+    ] {
+        let dir = manifest_dir.join(dir);
+        if dir.is_dir() {
+            println!("cargo::rerun-if-changed={}", dir.display());
+            source_files(&dir, &mut files);
+        }
+    }
+    files.sort();
+
+    let mut hasher = Sha256::new();
+    hasher.update(std::env::var("CARGO_PKG_VERSION").unwrap());
+    for file in files {
+        let contents = fs::read(&file).unwrap();
+        hasher.update(
+            file.strip_prefix(manifest_dir)
+                .unwrap()
+                .to_string_lossy()
+                .as_bytes(),
+        );
+        hasher.update([0]);
+        hasher.update((contents.len() as u64).to_le_bytes());
+        hasher.update(contents);
+    }
+    hasher.update(fs::read(core.join("trilogy_core.bc")).unwrap());
+    println!(
+        "cargo::rustc-env=TRILOGY_COMPILER_FINGERPRINT={:x}",
+        hasher.finalize()
+    );
+}
+
 fn main() {
     let llvm_prefix = std::env::var("LLVM_SYS_191_PREFIX")
         .ok()
         .and_then(|s| s.parse::<PathBuf>().ok())
         .map(|p| p.join("bin"))
         .unwrap_or_default();
-    let core = std::env::var("CARGO_MANIFEST_DIR")
+    let manifest_dir = std::env::var("CARGO_MANIFEST_DIR")
         .unwrap()
         .parse::<PathBuf>()
-        .unwrap()
-        .join("core");
+        .unwrap();
+    let core = manifest_dir.join("core");
     println!("cargo::rerun-if-env-changed=TRILOGY_CORE_DEFINES");
     let defines = std::env::var("TRILOGY_CORE_DEFINES")
         .map(|s| {
@@ -92,4 +144,6 @@ fn main() {
             .args(["-o", "trilogy_core.bc"])
             .current_dir(&core),
     );
+
+    fingerprint(&manifest_dir, &core);
 }
@@ -69,6 +69,110 @@ void readline(trilogy_value* rv) {
     free(lineptr);
 }
 
+static int program_argc = 0;
+static char** program_argv = NULL;
+
+// The arguments are copied, as an embedding host's `argv` need not outlive the call
+// that provided it, while the program may be called again later. The previous copy is
+// freed when the arguments are next initialized.
+void init_args(int argc, char** argv) {
+    for (int i = 0; i < program_argc; ++i) {
+        free(program_argv[i]);
+    }
+    free(program_argv);
+    program_argv = malloc_safe(sizeof(char*) * (size_t)argc);
+    for (int i = 0; i < argc; ++i) {
+        size_t len = strlen(argv[i]) + 1;
+        program_argv[i] = malloc_safe(len);
+        memcpy(program_argv[i], argv[i], len);
+    }
+    program_argc = argc;
+}
+
+void args(trilogy_value* rv) {
+    // The first argument is the program name, which is not included.
+    size_t count = program_argc > 1 ? (size_t)(program_argc - 1) : 0;
+    trilogy_array_value* arr = trilogy_array_init_cap(rv, count);
+    for (size_t i = 0; i < count; ++i) {
+        trilogy_value arg = trilogy_undefined;
+        trilogy_string_init_from_c(&arg, program_argv[i + 1]);
+        trilogy_array_push(arr, &arg);
+    }
+}
+
+// The deferred blocks of the `defer` statements whose scopes have been entered but not
+// yet exited, innermost last. Each is a continuation which runs the deferred block (if
+// it has not run already) and then continues to the continuation it is called with.
+//
+// Handlers may resume into a scope after registering blocks of their own, so scopes do
+// not always exit in the reverse order they were entered. Each block is therefore
+// registered under a key unique to its scope, by which the scope removes only its own.
+typedef struct deferred_block {
+    uint64_t key;
+    trilogy_value block;
+} deferred_block;
+
+static deferred_block* deferred_blocks = NULL;
+static size_t deferred_len = 0;
+static size_t deferred_cap = 0;
+static uint64_t deferred_next_key = 0;
+
+void defer_reset() {
+    for (size_t i = 0; i < deferred_len; ++i) {
+        trilogy_value_destroy(&deferred_blocks[i].block);
+    }
+    free(deferred_blocks);
+    deferred_blocks = NULL;
+    deferred_len = 0;
+    deferred_cap = 0;
+}
+
+void defer_depth(trilogy_value* rv) {
+    trilogy_number_init_u64(rv, deferred_len);
+}
+
+void defer_key(trilogy_value* rv) {
+    trilogy_number_init_u64(rv, deferred_next_key++);
+}
+
+void defer_push(trilogy_value* key, trilogy_value* deferred) {
+    if (deferred_len == deferred_cap) {
+        deferred_cap = deferred_cap == 0 ? 8 : deferred_cap * 2;
+        deferred_blocks = realloc_safe(
+            deferred_blocks, sizeof(deferred_block) * deferred_cap
+        );
+    }
+    deferred_blocks[deferred_len].key =
+        trilogy_number_to_u64(trilogy_number_untag(key));
+    deferred_blocks[deferred_len].block = *deferred;
+    *deferred = trilogy_undefined;
+    ++deferred_len;
+}
+
+void defer_remove(trilogy_value* key) {
+    uint64_t target = trilogy_number_to_u64(trilogy_number_untag(key));
+    for (size_t i = deferred_len; i > 0; --i) {
+        if (deferred_blocks[i - 1].key != target) continue;
+        trilogy_value_destroy(&deferred_blocks[i - 1].block);
+        memmove(
+            &deferred_blocks[i - 1], &deferred_blocks[i],
+            sizeof(deferred_block) * (deferred_len - i)
+        );
+        --deferred_len;
+        return;
+    }
+}
+
+bool defer_unwind(trilogy_value* rv, trilogy_value* depth) {
+    uint64_t target =
+        depth == NULL ? 0 : trilogy_number_to_u64(trilogy_number_untag(depth));
+    if (deferred_len <= target) return false;
+    --deferred_len;
+    *rv = deferred_blocks[deferred_len].block;
+    deferred_blocks[deferred_len].block = trilogy_undefined;
+    return true;
+}
+
 void readchar(trilogy_value* rv) {
     int ch = getc(stdin);
     if (ch == EOF) {
@@ -7,6 +7,15 @@ void eprint(trilogy_value* rv, trilogy_value* str);
 void callable_backtrace(trilogy_value* rv, trilogy_value* callable);
 void readline(trilogy_value* rv);
 void readchar(trilogy_value* rv);
+void init_args(int argc, char** argv);
+void args(trilogy_value* rv);
+
+void defer_reset();
+void defer_depth(trilogy_value* rv);
+void defer_key(trilogy_value* rv);
+void defer_push(trilogy_value* key, trilogy_value* deferred);
+void defer_remove(trilogy_value* key);
+bool defer_unwind(trilogy_value* rv, trilogy_value* depth);
 
 void boolean_not(trilogy_value* rv, trilogy_value* v);
 void boolean_and(trilogy_value* rv, trilogy_value* lhs, trilogy_value* rhs);
@@ -1,12 +1,34 @@
+#include "core.h"
 #include "internal.h"
+#include "trilogy_array.h"
+#include "trilogy_atom.h"
+#include "trilogy_module.h"
 #include "trilogy_number.h"
+#include "trilogy_value.h"
 #include "types.h"
+#include <setjmp.h>
+#include <stdbool.h>
 #include <stdio.h>
 #include <stdlib.h>
 
+/**
+ * When running embedded, exiting returns control to the host rather than
+ * ending the process.
+ */
+static jmp_buf* exit_handler = NULL;
+static int exit_status = 0;
+
+[[noreturn]] static void trilogy_exit(int status) {
+    if (exit_handler != NULL) {
+        exit_status = status;
+        longjmp(*exit_handler, 1);
+    }
+    exit(status);
+}
+
 [[noreturn]] void internal_panic(char* msg) {
     fprintf(stderr, "%s", msg);
-    exit(255);
+    trilogy_exit(255);
 }
 
 [[noreturn]] void rte(char* expected, unsigned char tag) {
@@ -14,20 +36,50 @@
         stderr, "runtime type error: expected %s but received %s\n", expected,
         type_name(tag)
     );
-    exit(255);
+    trilogy_exit(255);
 }
 
 [[noreturn]] void exit_(trilogy_value* val) {
     switch (val->tag) {
     case TAG_UNIT:
-        exit(0);
+        trilogy_exit(0);
     case TAG_NUMBER:
-        exit(trilogy_number_to_u64(trilogy_number_assume(val)));
+        trilogy_exit((int)trilogy_number_to_u64(trilogy_number_assume(val)));
     default:
         rte("number", val->tag);
     }
 }
 
+bool run_embedded(
+    void (*main)(int, char**), int argc, char** argv, trilogy_value* output,
+    int* status
+) {
+    jmp_buf handler;
+    jmp_buf* previous = exit_handler;
+    exit_handler = &handler;
+    bool exited = true;
+    if (previous == NULL) defer_reset();
+    if (setjmp(handler) == 0) {
+        main(argc, argv);
+        switch (output->tag) {
+        case TAG_UNIT:
+            exit_status = 0;
+            break;
+        case TAG_NUMBER:
+            exit_status =
+                (int)trilogy_number_to_u64(trilogy_number_assume(output));
+            break;
+        default:
+            exit_status = -1;
+        }
+        exited = false;
+    }
+    exit_handler = previous;
+    if (exited && previous == NULL) defer_reset();
+    *status = exit_status;
+    return exited;
+}
+
 void* malloc_safe(size_t size) {
     if (size == 0) return NULL;
     void* ptr = malloc(size);
@@ -53,3 +105,35 @@ void* realloc_safe(void* ptr, size_t size) {
 }
 
 int debug_print(const char* str) { return fprintf(stderr, "%s", str); }
+
+bool run_embedded_entry(
+    void (*entry)(trilogy_value*), trilogy_value* arguments, int* status
+) {
+    jmp_buf handler;
+    jmp_buf* previous = exit_handler;
+    exit_handler = &handler;
+    bool exited = true;
+    if (previous == NULL) defer_reset();
+    if (setjmp(handler) == 0) {
+        entry(arguments);
+        exited = false;
+    }
+    exit_handler = previous;
+    if (exited && previous == NULL) defer_reset();
+    if (exited) *status = exit_status;
+    return exited;
+}
+
+void embedded_find(trilogy_value* arguments) {
+    trilogy_array_value* path = trilogy_array_untag(&arguments[1]);
+    for (size_t i = 0; i < path->len; ++i) {
+        trilogy_value member = trilogy_undefined;
+        trilogy_module_find(
+            &member, trilogy_module_untag(&arguments[0]),
+            trilogy_atom_untag(&path->contents[i])
+        );
+        trilogy_value_destroy(&arguments[0]);
+        arguments[0] = member;
+    }
+    trilogy_value_destroy(&arguments[1]);
+}
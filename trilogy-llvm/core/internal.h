@@ -1,10 +1,47 @@
 #pragma once
 #include "types.h"
+#include <stdbool.h>
 #include <stdlib.h>
 
 [[noreturn]] void internal_panic(char* msg);
 [[noreturn]] void rte(char* expected, unsigned char tag);
 [[noreturn]] void exit_(trilogy_value* code);
+/**
+ * Runs the main function of an embedded program, returning to the caller even
+ * if the program exits or panics.
+ *
+ * Returns true if the program exited, or false if main returned its value into
+ * `output`. Either way, `status` is set to the exit status of the program, where
+ * a returned value is treated as if it were passed to `exit`, or -1 if the
+ * returned value is not a valid exit status.
+ *
+ * Unless called from within another embedded run, the deferred blocks left
+ * registered by a previous run are discarded before the program starts, and
+ * again if it exits without reaching the end of its scopes.
+ */
+bool run_embedded(
+    void (*main)(int, char**), int argc, char** argv, trilogy_value* output,
+    int* status
+);
+/**
+ * Runs one of the entrypoints by which an embedding host calls into a program,
+ * returning to the caller even if the program exits or panics.
+ *
+ * Returns true if the program exited, in which case `status` is set to its exit
+ * status. Deferred blocks are discarded as they are by `run_embedded`.
+ */
+bool run_embedded_entry(
+    void (*entry)(trilogy_value*), trilogy_value* arguments, int* status
+);
+/**
+ * An entrypoint by which an embedding host finds a member of a module, to be run
+ * with `run_embedded_entry` so that lookups which fail are reported as exits.
+ *
+ * The arguments are the module to start from, followed by an array of the atoms
+ * naming each member along the path. The first argument is replaced with the
+ * member that is found, and the second is consumed.
+ */
+void embedded_find(trilogy_value* arguments);
 void* malloc_safe(size_t size);
 void* calloc_safe(size_t num, size_t size);
 void* realloc_safe(void* ptr, size_t size);
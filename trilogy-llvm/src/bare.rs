@@ -1335,6 +1335,109 @@ impl<'ctx> Codegen<'ctx> {
             .unwrap();
     }
 
+    /// Provides the program's command line arguments to the runtime, to be retrieved
+    /// later by the `args` core procedure.
+    pub(crate) fn init_args(&self, argc: IntValue<'ctx>, argv: PointerValue<'ctx>) {
+        let f = self.declare_bare(
+            "init_args",
+            self.context.void_type().fn_type(
+                &[
+                    self.context.i32_type().into(),
+                    self.context.ptr_type(AddressSpace::default()).into(),
+                ],
+                false,
+            ),
+        );
+        self.builder
+            .build_call(f, &[argc.into(), argv.into()], "")
+            .unwrap();
+    }
+
+    /// Gets the number of deferred blocks that are currently registered with the runtime.
+    pub(crate) fn defer_depth(&self, rv: PointerValue<'ctx>) {
+        let f = self.declare_bare(
+            "defer_depth",
+            self.context.void_type().fn_type(
+                &[self.context.ptr_type(AddressSpace::default()).into()],
+                false,
+            ),
+        );
+        self.builder.build_call(f, &[rv.into()], "").unwrap();
+    }
+
+    /// Creates a key, unique to the scope of one `defer` statement, under which that
+    /// scope's deferred block is registered with the runtime.
+    pub(crate) fn defer_key(&self, rv: PointerValue<'ctx>) {
+        let f = self.declare_bare(
+            "defer_key",
+            self.context.void_type().fn_type(
+                &[self.context.ptr_type(AddressSpace::default()).into()],
+                false,
+            ),
+        );
+        self.builder.build_call(f, &[rv.into()], "").unwrap();
+    }
+
+    /// Registers a deferred block with the runtime under the given key, so that it may be
+    /// run if its scope is cancelled or exited. The deferred block is moved.
+    pub(crate) fn defer_push(&self, key: PointerValue<'ctx>, deferred: PointerValue<'ctx>) {
+        let f = self.declare_bare(
+            "defer_push",
+            self.context.void_type().fn_type(
+                &[
+                    self.context.ptr_type(AddressSpace::default()).into(),
+                    self.context.ptr_type(AddressSpace::default()).into(),
+                ],
+                false,
+            ),
+        );
+        self.builder
+            .build_call(f, &[key.into(), deferred.into()], "")
+            .unwrap();
+    }
+
+    /// Discards the deferred block registered under the given key without running it, if
+    /// it is still registered. Blocks registered by other scopes are left in place.
+    pub(crate) fn defer_remove(&self, key: PointerValue<'ctx>) {
+        let f = self.declare_bare(
+            "defer_remove",
+            self.context.void_type().fn_type(
+                &[self.context.ptr_type(AddressSpace::default()).into()],
+                false,
+            ),
+        );
+        self.builder.build_call(f, &[key.into()], "").unwrap();
+    }
+
+    /// Takes the innermost registered deferred block into `rv`, if there are any above the
+    /// given depth. A depth of `None` takes every deferred block. The return value is of type
+    /// `i1`, and indicates whether a deferred block was taken.
+    pub(crate) fn defer_unwind(
+        &self,
+        rv: PointerValue<'ctx>,
+        depth: Option<PointerValue<'ctx>>,
+        name: &str,
+    ) -> IntValue<'ctx> {
+        let f = self.declare_bare(
+            "defer_unwind",
+            self.context.bool_type().fn_type(
+                &[
+                    self.context.ptr_type(AddressSpace::default()).into(),
+                    self.context.ptr_type(AddressSpace::default()).into(),
+                ],
+                false,
+            ),
+        );
+        let depth =
+            depth.unwrap_or_else(|| self.context.ptr_type(AddressSpace::default()).const_null());
+        self.builder
+            .build_call(f, &[rv.into(), depth.into()], name)
+            .unwrap()
+            .try_as_basic_value()
+            .unwrap_basic()
+            .into_int_value()
+    }
+
     pub(crate) fn exit(&self, t: PointerValue<'ctx>) -> NeverValue {
         let f = self.declare_bare(
             "exit_",
@@ -1354,6 +1457,33 @@ impl<'ctx> Codegen<'ctx> {
         NeverValue
     }
 
+    /// Offers an unhandled effect to the embedding host, which may write a response into `rv`.
+    ///
+    /// Returns 0 if the host did not handle the effect, 1 to resume with the response, or
+    /// 2 to cancel with the response.
+    pub(crate) fn trilogy_host_effect(
+        &self,
+        rv: PointerValue<'ctx>,
+        effect: PointerValue<'ctx>,
+    ) -> IntValue<'ctx> {
+        let f = self.declare_bare(
+            "trilogy_host_effect",
+            self.context.i8_type().fn_type(
+                &[
+                    self.context.ptr_type(AddressSpace::default()).into(),
+                    self.context.ptr_type(AddressSpace::default()).into(),
+                ],
+                false,
+            ),
+        );
+        self.builder
+            .build_call(f, &[rv.into(), effect.into()], "host_effect")
+            .unwrap()
+            .try_as_basic_value()
+            .unwrap_basic()
+            .into_int_value()
+    }
+
     pub(crate) fn trilogy_reference_close(&self, t: PointerValue<'ctx>) {
         self.trilogy_reference_close_in(&self.builder, t);
     }
@@ -0,0 +1,127 @@
+/// A module as it was compiled, saved so that it may be linked into a later program
+/// without being compiled again.
+///
+/// Along with the module's bitcode, this records the atoms the module refers to and the
+/// ids they were assigned, which a program must agree with in order to reuse the module.
+#[derive(Clone, Debug)]
+pub struct CachedModule {
+    pub(crate) bitcode: Vec<u8>,
+    pub(crate) atoms: Vec<(String, u64)>,
+    pub(crate) tests: Vec<String>,
+}
+
+/// Storage for compiled modules, keyed by module location.
+///
+/// Implementors are responsible for only returning a module from `load` while it is still
+/// valid, that is, while neither the module's source, the sources of the modules it imports,
+/// nor the compiler have changed since it was saved.
+pub trait ModuleCache {
+    /// Loads the compiled module at `location`, if it is cached and still valid.
+    fn load(&self, location: &str) -> Option<CachedModule>;
+
+    /// Saves the compiled module at `location`, for use in later compilations.
+    fn save(&self, location: &str, module: &CachedModule);
+}
+
+const MAGIC: &[u8] = b"TRILOGY-MODULE-1";
+
+impl CachedModule {
+    /// Serializes this module so it can be stored.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend((self.atoms.len() as u64).to_le_bytes());
+        for (atom, id) in &self.atoms {
+            write_bytes(&mut bytes, atom.as_bytes());
+            bytes.extend(id.to_le_bytes());
+        }
+        bytes.extend((self.tests.len() as u64).to_le_bytes());
+        for test in &self.tests {
+            write_bytes(&mut bytes, test.as_bytes());
+        }
+        write_bytes(&mut bytes, &self.bitcode);
+        bytes
+    }
+
+    /// Deserializes a module previously serialized with [`to_bytes`][CachedModule::to_bytes].
+    ///
+    /// Returns `None` if the bytes are not a valid module.
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        let mut reader = bytes.strip_prefix(MAGIC)?;
+        let atom_count = read_u64(&mut reader)?;
+        let atoms = (0..atom_count)
+            .map(|_| {
+                let atom = read_string(&mut reader)?;
+                let id = read_u64(&mut reader)?;
+                Some((atom, id))
+            })
+            .collect::<Option<_>>()?;
+        let test_count = read_u64(&mut reader)?;
+        let tests = (0..test_count)
+            .map(|_| read_string(&mut reader))
+            .collect::<Option<_>>()?;
+        let bitcode = read_bytes(&mut reader)?.to_vec();
+        if !reader.is_empty() {
+            return None;
+        }
+        Some(Self {
+            bitcode,
+            atoms,
+            tests,
+        })
+    }
+}
+
+fn write_bytes(output: &mut Vec<u8>, bytes: &[u8]) {
+    output.extend((bytes.len() as u64).to_le_bytes());
+    output.extend(bytes);
+}
+
+fn read_u64(input: &mut &[u8]) -> Option<u64> {
+    let (value, rest) = input.split_first_chunk::<8>()?;
+    *input = rest;
+    Some(u64::from_le_bytes(*value))
+}
+
+fn read_bytes<'a>(input: &mut &'a [u8]) -> Option<&'a [u8]> {
+    let len = usize::try_from(read_u64(input)?).ok()?;
+    if input.len() < len {
+        return None;
+    }
+    let (bytes, rest) = input.split_at(len);
+    *input = rest;
+    Some(bytes)
+}
+
+fn read_string(input: &mut &[u8]) -> Option<String> {
+    String::from_utf8(read_bytes(input)?.to_vec()).ok()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn module() -> CachedModule {
+        CachedModule {
+            bitcode: vec![0x42, 0x43, 0xc0, 0xde, 0, 1, 2],
+            atoms: vec![("hello".to_owned(), 22), ("world".to_owned(), 40)],
+            tests: vec!["test_one".to_owned(), "test_two".to_owned()],
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        let module = module();
+        let restored = CachedModule::from_bytes(&module.to_bytes()).unwrap();
+        assert_eq!(restored.bitcode, module.bitcode);
+        assert_eq!(restored.atoms, module.atoms);
+        assert_eq!(restored.tests, module.tests);
+    }
+
+    #[test]
+    fn rejects_invalid_bytes() {
+        let bytes = module().to_bytes();
+        assert!(CachedModule::from_bytes(&bytes[..bytes.len() - 1]).is_none());
+        assert!(CachedModule::from_bytes(&[bytes.as_slice(), &[0]].concat()).is_none());
+        assert!(CachedModule::from_bytes(&bytes[1..]).is_none());
+    }
+}
@@ -478,16 +478,20 @@ impl<'ctx> Codegen<'ctx> {
         self.get_continuation(name)
     }
 
-    /// Calls the `main` function as the Trilogy program entrypoint.
+    /// Calls the program's main procedure as the Trilogy program entrypoint, returning a
+    /// pointer to its return value. This is similar to a standard procedure call, but
+    /// because this is the first call in a program, we have to create the initial
+    /// `return_to`, `yield_to`, and `end_to` continuations from scratch.
     ///
-    /// This is similar to a standard procedure call, but because this is the first call
-    /// in a program, we have to create the initial `return_to`, `yield_to`, and `end_to`
-    /// continuations from scratch.
+    /// When `host_output` is provided, the program is being run by an embedding host, and
+    /// effects that are not handled by the program are offered to the host, which may
+    /// cancel the program by writing a value to the output.
     pub(crate) fn call_main(
         &self,
         value: PointerValue<'ctx>,
         arguments: &[BasicMetadataValueEnum<'ctx>],
         call_conv: u32,
+        host_output: Option<PointerValue<'ctx>>,
     ) -> PointerValue<'ctx> {
         let chain_function = self.add_continuation("return");
         let yield_function = self.add_continuation("unhandled_effect");
@@ -537,6 +541,9 @@ impl<'ctx> Codegen<'ctx> {
         self.begin_next_function(yield_function);
         let effect = self.get_continuation("effect");
         let resume = self.get_provided_resume();
+        if let Some(output) = host_output {
+            self.offer_effect_to_host(effect, resume, output);
+        }
         let effect = self.to_string(effect, "effect_string", Span::default());
         self.debug_print("unhandled effect: ");
         self.call_procedure(self.eprint(), &[effect], "", Span::default());
@@ -567,4 +574,54 @@ impl<'ctx> Codegen<'ctx> {
         self.trilogy_value_clone_into(result, self.get_continuation("ret_val"));
         result
     }
+
+    /// Offers an effect that the program did not handle to the embedding host. When the host
+    /// resumes or cancels, that branch is ended here, and code generation continues in the
+    /// branch where the host did not handle the effect.
+    fn offer_effect_to_host(
+        &self,
+        effect: PointerValue<'ctx>,
+        resume: PointerValue<'ctx>,
+        output: PointerValue<'ctx>,
+    ) {
+        let offered = self.allocate_value("offered");
+        self.trilogy_value_clone_into(offered, effect);
+        let response = self.allocate_value("response");
+        let handling = self.trilogy_host_effect(response, offered);
+
+        let function = self.get_function();
+        let snapshot = self.snapshot_function_context();
+        let resume_block = self.context.append_basic_block(function, "host.resume");
+        let cancel_block = self.context.append_basic_block(function, "host.cancel");
+        let unhandled_block = self.context.append_basic_block(function, "host.unhandled");
+        self.builder
+            .build_switch(
+                handling,
+                unhandled_block,
+                &[
+                    (self.context.i8_type().const_int(1, false), resume_block),
+                    (self.context.i8_type().const_int(2, false), cancel_block),
+                ],
+            )
+            .unwrap();
+        let cancel_cp = self.branch_continuation_point();
+        let unhandled_cp = self.branch_continuation_point();
+
+        self.builder.position_at_end(resume_block);
+        let continuation = self.allocate_value("resume");
+        self.trilogy_value_clone_into(continuation, resume);
+        self.call_known_continuation(continuation, response);
+
+        self.become_continuation_point(cancel_cp);
+        self.builder.position_at_end(cancel_block);
+        self.restore_function_context(snapshot.clone());
+        let cancelled = self.load_value(response, "");
+        let store = self.builder.build_store(output, cancelled).unwrap();
+        self.end_continuation_point_as_clean(store);
+        self.builder.build_return(None).unwrap();
+
+        self.become_continuation_point(unhandled_cp);
+        self.builder.position_at_end(unhandled_block);
+        self.restore_function_context(snapshot);
+    }
 }
@@ -1,14 +1,16 @@
 //! The core code generation tool.
+use crate::cache::CachedModule;
 use crate::types;
 use inkwell::builder::Builder;
 use inkwell::context::Context;
 use inkwell::execution_engine::ExecutionEngine;
+use inkwell::memory_buffer::MemoryBuffer;
 use inkwell::module::Module;
 use inkwell::values::{GlobalValue, PointerValue};
 use inkwell::{OptimizationLevel, values::FunctionValue};
 use source_span::Span;
 use std::cell::{Cell, RefCell};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::rc::Rc;
 use trilogy_ir::{Id, ir};
 
@@ -41,6 +43,11 @@ pub(crate) struct CurrentDefinition<'ctx> {
 
 pub(crate) struct Codegen<'ctx> {
     pub(crate) atoms: Rc<RefCell<HashMap<String, u64>>>,
+    /// The id the next new atom is assigned, which is always past every id in `atoms`.
+    pub(crate) next_atom: Rc<Cell<u64>>,
+    /// The atoms referred to by the code generated by this `Codegen`, which must be
+    /// assigned the same ids if the module is to be reused from a cache.
+    pub(crate) used_atoms: RefCell<BTreeMap<String, u64>>,
     pub(crate) context: &'ctx Context,
     pub(crate) module: Rc<Module<'ctx>>,
     pub(crate) builder: Builder<'ctx>,
@@ -62,10 +69,14 @@ pub(crate) struct Codegen<'ctx> {
     current_definition: RefCell<Option<CurrentDefinition<'ctx>>>,
     closure_array: Cell<Option<PointerValue<'ctx>>>,
     pub(crate) function_params: RefCell<Vec<PointerValue<'ctx>>>,
+    /// Whether the pattern being compiled has no alternative to fall back on if it fails,
+    /// in which case a failed type pattern yields a `'type_error`.
+    pub(crate) final_pattern: Cell<bool>,
     pub(crate) current_break: RefCell<Vec<PointerValue<'ctx>>>,
     pub(crate) current_continue: RefCell<Vec<PointerValue<'ctx>>>,
     pub(crate) current_cancel: RefCell<Vec<PointerValue<'ctx>>>,
     pub(crate) current_resume: RefCell<Vec<PointerValue<'ctx>>>,
+    pub(crate) current_return: RefCell<Vec<PointerValue<'ctx>>>,
 }
 
 impl<'ctx> Codegen<'ctx> {
@@ -106,7 +117,9 @@ impl<'ctx> Codegen<'ctx> {
         let codegen = Codegen {
             path: vec![],
             tests: vec![],
+            next_atom: Rc::new(Cell::new(ATOM_ASSERTION_FAILED + 1)),
             atoms: Rc::new(RefCell::new(atoms)),
+            used_atoms: RefCell::default(),
             builder: context.create_builder(),
             di,
             context,
@@ -119,10 +132,12 @@ impl<'ctx> Codegen<'ctx> {
             current_definition: RefCell::default(),
             closure_array: Cell::default(),
             function_params: RefCell::default(),
+            final_pattern: Cell::default(),
             current_break: RefCell::default(),
             current_continue: RefCell::default(),
             current_cancel: RefCell::default(),
             current_resume: RefCell::default(),
+            current_return: RefCell::default(),
         };
         codegen.write_current_backtrace();
         codegen.write_backtrace_of();
@@ -175,6 +190,8 @@ impl<'ctx> Codegen<'ctx> {
             path: vec![],
             tests: vec![],
             atoms: self.atoms.clone(),
+            next_atom: self.next_atom.clone(),
+            used_atoms: RefCell::default(),
             context: self.context,
             builder: self.context.create_builder(),
             di,
@@ -187,10 +204,12 @@ impl<'ctx> Codegen<'ctx> {
             current_definition: RefCell::default(),
             closure_array: Cell::default(),
             function_params: RefCell::default(),
+            final_pattern: Cell::default(),
             current_break: RefCell::default(),
             current_continue: RefCell::default(),
             current_cancel: RefCell::default(),
             current_resume: RefCell::default(),
+            current_return: RefCell::default(),
         }
     }
 
@@ -290,6 +309,7 @@ impl<'ctx> Codegen<'ctx> {
         self.current_continue.borrow_mut().clear();
         self.current_cancel.borrow_mut().clear();
         self.current_resume.borrow_mut().clear();
+        self.current_return.borrow_mut().clear();
         *self.function_params.borrow_mut() = self.bind_arguments(function);
     }
 
@@ -349,11 +369,65 @@ impl<'ctx> Codegen<'ctx> {
         self.current_resume.borrow_mut().pop();
     }
 
+    pub(crate) fn push_defer_scope(&self, return_to: PointerValue<'ctx>) {
+        self.current_return.borrow_mut().push(return_to);
+    }
+
+    pub(crate) fn pop_defer_scope(&self) {
+        self.current_return.borrow_mut().pop();
+    }
+
     pub(crate) fn consume(&mut self, submodule: Self) {
-        submodule.di.builder.finalize();
         self.module
             .link_in_module(Rc::into_inner(submodule.module).unwrap())
             .unwrap();
         self.tests.extend(submodule.tests);
     }
+
+    /// Captures a compiled submodule in the form in which it is cached.
+    pub(crate) fn to_cached(&self) -> CachedModule {
+        CachedModule {
+            bitcode: self.module.write_bitcode_to_memory().as_slice().to_vec(),
+            atoms: self.used_atoms.borrow().clone().into_iter().collect(),
+            tests: self.tests.clone(),
+        }
+    }
+
+    /// Links in a module that was previously compiled and cached, instead of compiling it.
+    ///
+    /// Returns `false` if the cached module cannot be used, in which case it must be
+    /// compiled as usual.
+    pub(crate) fn consume_cached(&mut self, cached: CachedModule) -> bool {
+        if !self.claim_atoms(&cached.atoms) {
+            return false;
+        }
+        let buffer = MemoryBuffer::create_from_memory_range_copy(&cached.bitcode, "cached");
+        let Ok(module) = Module::parse_bitcode_from_buffer(&buffer, self.context) else {
+            return false;
+        };
+        self.module.link_in_module(module).unwrap();
+        self.tests.extend(cached.tests);
+        true
+    }
+
+    /// Assigns atoms the ids a cached module was compiled with, unless any of those atoms
+    /// or ids have already been assigned differently.
+    fn claim_atoms(&self, claims: &[(String, u64)]) -> bool {
+        let mut atoms = self.atoms.borrow_mut();
+        let taken: HashSet<u64> = atoms.values().copied().collect();
+        let conflict = claims.iter().any(|(atom, id)| match atoms.get(atom) {
+            Some(existing) => existing != id,
+            None => taken.contains(id),
+        });
+        if conflict {
+            return false;
+        }
+        atoms.extend(claims.iter().cloned());
+        // Atoms claimed by cached modules may leave gaps, so new atoms go after the
+        // highest id rather than filling them.
+        if let Some(max) = claims.iter().map(|(_, id)| *id).max() {
+            self.next_atom.set(self.next_atom.get().max(max + 1));
+        }
+        true
+    }
 }
@@ -13,6 +13,7 @@ pub(crate) struct Snapshot<'ctx> {
     current_continue: Vec<PointerValue<'ctx>>,
     current_resume: Vec<PointerValue<'ctx>>,
     current_cancel: Vec<PointerValue<'ctx>>,
+    current_return: Vec<PointerValue<'ctx>>,
 }
 
 impl<'ctx> Codegen<'ctx> {
@@ -26,6 +27,7 @@ impl<'ctx> Codegen<'ctx> {
             current_continue: self.current_continue.borrow().clone(),
             current_resume: self.current_resume.borrow().clone(),
             current_cancel: self.current_cancel.borrow().clone(),
+            current_return: self.current_return.borrow().clone(),
         }
     }
 
@@ -39,5 +41,6 @@ impl<'ctx> Codegen<'ctx> {
         *self.current_continue.borrow_mut() = snapshot.current_continue;
         *self.current_resume.borrow_mut() = snapshot.current_resume;
         *self.current_cancel.borrow_mut() = snapshot.current_cancel;
+        *self.current_return.borrow_mut() = snapshot.current_return;
     }
 }
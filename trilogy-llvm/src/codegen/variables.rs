@@ -94,6 +94,16 @@ impl<'ctx> Codegen<'ctx> {
         container
     }
 
+    /// Gets the continuation that `return` calls, which first runs the deferred blocks
+    /// of any `defer` statements that are in scope.
+    pub(crate) fn get_return_from_scope(&self, name: &str) -> PointerValue<'ctx> {
+        let current_return = self.current_return.borrow().last().copied();
+        match current_return {
+            Some(return_to) => self.use_temporary_clone(return_to).unwrap(),
+            None => self.get_return(name),
+        }
+    }
+
     pub(crate) fn get_return_temporary(&self) -> PointerValue<'ctx> {
         self.function_params.borrow()[0]
     }
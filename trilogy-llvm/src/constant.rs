@@ -1,7 +1,9 @@
 use crate::Codegen;
+use crate::codegen::{Global, Variable};
 use inkwell::debug_info::AsDIScope;
 use inkwell::llvm_sys::debuginfo::LLVMDIFlagPublic;
 use inkwell::module::Linkage;
+use inkwell::values::PointerValue;
 use trilogy_ir::{Id, ir};
 
 impl<'ctx> Codegen<'ctx> {
@@ -33,7 +35,7 @@ impl<'ctx> Codegen<'ctx> {
             self.build_callable_data(&self.module_path(), &name, 0, definition.span(), None);
         self.set_current_definition(
             name.clone(),
-            accessor_name,
+            accessor_name.clone(),
             definition.value.span,
             metadata,
             module_context,
@@ -41,13 +43,15 @@ impl<'ctx> Codegen<'ctx> {
         self.begin_constant(accessor, definition.span());
 
         let has_context = accessor.count_params() == 2;
-        let storage = if has_context {
-            self.allocate_value("")
+        // Within a parameterized module, the slot is held in the module instance's closure,
+        // so each instance has its own, shared by all of that instance's members.
+        let (storage, upvalue) = if has_context {
+            match self.get_variable(&definition.name.id).unwrap() {
+                Variable::Closed { location, upvalue } => (location, Some(upvalue)),
+                variable => (variable.ptr(), None),
+            }
         } else {
-            let global = self.module.add_global(self.value_type(), None, &name);
-            global.set_linkage(Linkage::Private);
-            global.set_initializer(&self.value_type().const_zero());
-            global.as_pointer_value()
+            (self.slot_storage(&accessor_name), None)
         };
 
         let initialize = self.context.append_basic_block(accessor, "initialize");
@@ -57,6 +61,9 @@ impl<'ctx> Codegen<'ctx> {
         self.builder.position_at_end(initialized);
         let sret = accessor.get_first_param().unwrap().into_pointer_value();
         self.trilogy_value_clone_into(sret, storage);
+        if let Some(upvalue) = upvalue {
+            self.trilogy_value_destroy(upvalue);
+        }
         self.builder.build_return(None).unwrap();
 
         self.builder.position_at_end(initialize);
@@ -78,4 +85,37 @@ impl<'ctx> Codegen<'ctx> {
 
         self.end_function();
     }
+
+    /// The storage of a slot in a module without parameters. Such a slot has exactly one
+    /// instance, which is stored in a global that is initialized on first access.
+    fn slot_storage(&self, accessor_name: &str) -> PointerValue<'ctx> {
+        let storage_name = format!("{accessor_name}.slot");
+        if let Some(global) = self.module.get_global(&storage_name) {
+            return global.as_pointer_value();
+        }
+        let global = self
+            .module
+            .add_global(self.value_type(), None, &storage_name);
+        global.set_linkage(Linkage::Private);
+        global.set_initializer(&self.value_type().const_zero());
+        global.as_pointer_value()
+    }
+
+    /// Assigns a value to a mutable slot in a module without parameters, ensuring that the
+    /// slot has been initialized first so that its initializer does not later overwrite the
+    /// assigned value.
+    pub(crate) fn assign_slot(&self, global: &Global, value: PointerValue<'ctx>) {
+        let accessor_name = format!("{}::{}", global.module_path(&self.location), global.id);
+        let accessor = self
+            .module
+            .get_function(&accessor_name)
+            .expect("function was not defined");
+        let current = self.allocate_value("");
+        self.call_internal(current, accessor, &[]);
+        self.trilogy_value_destroy(current);
+
+        let storage = self.slot_storage(&accessor_name);
+        self.trilogy_value_destroy(storage);
+        self.trilogy_value_clone_into(storage, value);
+    }
 }
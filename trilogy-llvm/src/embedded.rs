@@ -0,0 +1,189 @@
+use crate::Value;
+use std::borrow::Borrow;
+use std::collections::{BTreeSet, HashMap};
+use std::fmt::{self, Display};
+use trilogy_ir::ir::{self, DefinitionItem};
+
+/// A call made into a Trilogy program from its embedding host.
+#[derive(Clone, Debug)]
+pub enum Call {
+    /// Call a procedure with the given arguments.
+    Procedure(Vec<Value>),
+    /// Apply a function to the given argument.
+    Function(Value),
+    /// Query a rule for all of its solutions. Arguments that are `None` are left unbound.
+    Query(Vec<Option<Value>>),
+}
+
+impl Call {
+    fn kind(&self) -> &'static str {
+        match self {
+            Self::Procedure(..) => "procedure",
+            Self::Function(..) => "function",
+            Self::Query(..) => "rule",
+        }
+    }
+
+    fn arity(&self) -> usize {
+        match self {
+            Self::Procedure(arguments) => arguments.len(),
+            Self::Function(..) => 1,
+            Self::Query(arguments) => arguments.len(),
+        }
+    }
+}
+
+/// The reasons a call into a Trilogy program may fail.
+#[derive(Clone, Debug)]
+pub enum CallError {
+    /// There is no exported definition at the requested path.
+    NotFound(String),
+    /// The definition at the requested path is not of the kind that was called.
+    WrongKind {
+        path: String,
+        expected: &'static str,
+    },
+    /// The definition at the requested path expects a different number of arguments.
+    WrongArity {
+        path: String,
+        expected: usize,
+        actual: usize,
+    },
+    /// An argument cannot be represented in the program, such as a callable, or an
+    /// atom that the program never mentions.
+    InvalidArgument(Box<Value>),
+    /// A command line argument contains a NUL character, so cannot be provided to the
+    /// program.
+    InvalidParameter(String),
+    /// The program exited before the call returned, either by calling `exit` or by a
+    /// runtime error.
+    Exited(i32),
+    /// The host's effect handler cancelled the call with the given value before it
+    /// returned, or before all of a query's solutions were found.
+    Cancelled(Box<Value>),
+}
+
+impl Display for CallError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NotFound(path) => write!(f, "no exported definition `{path}`"),
+            Self::WrongKind { path, expected } => write!(f, "`{path}` is not a {expected}"),
+            Self::WrongArity {
+                path,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "`{path}` expects {expected} arguments, but {actual} were provided"
+            ),
+            Self::InvalidArgument(value) => {
+                write!(f, "{value:?} cannot be passed to a Trilogy program")
+            }
+            Self::InvalidParameter(parameter) => {
+                write!(
+                    f,
+                    "command line argument {parameter:?} contains a NUL character"
+                )
+            }
+            Self::Exited(code) => write!(f, "the program exited with code {code}"),
+            Self::Cancelled(value) => write!(f, "the call was cancelled with {value:?}"),
+        }
+    }
+}
+
+impl std::error::Error for CallError {}
+
+/// Checks that the path refers to an exported definition that may be called as requested.
+///
+/// Every module along the path must be exported from its parent, and must not require
+/// parameters, as modules are looked up at runtime the same way a Trilogy program would.
+pub(crate) fn resolve<M: Borrow<ir::Module>>(
+    modules: &HashMap<String, M>,
+    entrymodule: &str,
+    path: &[&str],
+    call: &Call,
+) -> Result<(), CallError> {
+    let display = path.join("::");
+    let not_found = || CallError::NotFound(display.clone());
+    let (name, parents) = path.split_last().ok_or_else(not_found)?;
+    let mut module = modules.get(entrymodule).ok_or_else(not_found)?.borrow();
+    for segment in parents {
+        let definition = exported(module, segment).ok_or_else(not_found)?;
+        let DefinitionItem::Module(submodule) = &definition.item else {
+            return Err(not_found());
+        };
+        module = match submodule.module.as_external() {
+            Some(location) => modules.get(location).ok_or_else(not_found)?.borrow(),
+            None => submodule.module.as_module().ok_or_else(not_found)?,
+        };
+        if !module.parameters.is_empty() {
+            return Err(not_found());
+        }
+    }
+
+    let definition = exported(module, name).ok_or_else(not_found)?;
+    // `main!()` is compiled with a different calling convention, so may only be run
+    // as the entrypoint of a program.
+    if *name == "main" {
+        return Err(not_found());
+    }
+    let expected = match (&definition.item, call) {
+        (DefinitionItem::Procedure(procedure), Call::Procedure(..)) => procedure.arity,
+        (DefinitionItem::Function(..), Call::Function(..)) => 1,
+        (DefinitionItem::Rule(rule), Call::Query(..)) => rule.overloads[0].parameters.len(),
+        _ => {
+            return Err(CallError::WrongKind {
+                path: display,
+                expected: call.kind(),
+            });
+        }
+    };
+    if expected != call.arity() {
+        return Err(CallError::WrongArity {
+            path: display,
+            expected,
+            actual: call.arity(),
+        });
+    }
+    Ok(())
+}
+
+fn exported<'a>(module: &'a ir::Module, name: &str) -> Option<&'a ir::Definition> {
+    module
+        .definitions()
+        .iter()
+        .find(|def| def.is_exported && def.name().map(|id| id.name()) == Some(name))
+}
+
+/// The arities of the calls and queries that may be made into a program, for which
+/// embedded entrypoints must be compiled.
+pub(crate) fn arities<'a>(
+    modules: impl IntoIterator<Item = &'a ir::Module>,
+) -> (BTreeSet<usize>, BTreeSet<usize>) {
+    let mut calls = BTreeSet::new();
+    let mut queries = BTreeSet::new();
+    let mut pending: Vec<&ir::Module> = modules.into_iter().collect();
+    while let Some(module) = pending.pop() {
+        for definition in module.definitions() {
+            if !definition.is_exported {
+                continue;
+            }
+            match &definition.item {
+                DefinitionItem::Procedure(procedure) => {
+                    calls.insert(procedure.arity);
+                }
+                DefinitionItem::Function(..) => {
+                    calls.insert(1);
+                }
+                DefinitionItem::Rule(rule) => {
+                    queries.insert(rule.overloads[0].parameters.len());
+                }
+                DefinitionItem::Module(submodule) => {
+                    pending.extend(submodule.module.as_module());
+                }
+                DefinitionItem::Constant(..) | DefinitionItem::Test(..) => {}
+            }
+        }
+    }
+    (calls, queries)
+}
@@ -1,11 +1,14 @@
-use crate::TAIL_CALL_CONV;
+use crate::{IMPLICIT_PARAMS, TAIL_CALL_CONV};
 use crate::{TrilogyValue, codegen::Codegen};
+use inkwell::AddressSpace;
 use inkwell::debug_info::AsDIScope;
 use inkwell::execution_engine::ExecutionEngine;
 use inkwell::llvm_sys::LLVMCallConv;
 use inkwell::llvm_sys::debuginfo::LLVMDIFlagPublic;
 use inkwell::memory_buffer::MemoryBuffer;
 use inkwell::module::Module;
+use inkwell::values::{BasicMetadataValueEnum, FunctionValue, GlobalValue, PointerValue};
+use source_span::Span;
 use std::rc::Rc;
 
 impl<'ctx> Codegen<'ctx> {
@@ -20,9 +23,7 @@ impl<'ctx> Codegen<'ctx> {
             .map(|def| def.span)
             .unwrap_or_default();
 
-        let main_wrapper =
-            self.module
-                .add_function("main", self.context.void_type().fn_type(&[], false), None);
+        let main_wrapper = self.add_main_wrapper();
         let main_scope = self.di.builder.create_function(
             self.di.unit.get_file().as_debug_info_scope(),
             "main",
@@ -45,6 +46,10 @@ impl<'ctx> Codegen<'ctx> {
         let basic_block = self.context.append_basic_block(main_wrapper, "entry");
 
         self.builder.position_at_end(basic_block);
+        self.init_args(
+            main_wrapper.get_nth_param(0).unwrap().into_int_value(),
+            main_wrapper.get_nth_param(1).unwrap().into_pointer_value(),
+        );
 
         // Reference main
         let main_accessor = self
@@ -55,7 +60,7 @@ impl<'ctx> Codegen<'ctx> {
         self.call_internal(main, main_accessor, &[]);
 
         // Call main
-        let output = self.call_main(main, &[], LLVMCallConv::LLVMFastCallConv as u32);
+        let output = self.call_main(main, &[], LLVMCallConv::LLVMFastCallConv as u32, None);
         _ = self.exit(output);
         self.close_continuation();
         self.di.pop_scope();
@@ -77,13 +82,9 @@ impl<'ctx> Codegen<'ctx> {
             .find(|def| def.name().map(|id| id.name()) == Some(entrypoint))
             .map(|def| def.span)
             .unwrap_or_default();
-        let output_ptr = self.module.add_global(self.value_type(), None, "output");
-        self.execution_engine
-            .add_global_mapping(&output_ptr, output as usize);
+        let output_ptr = self.embedded_output(output);
 
-        let main_wrapper =
-            self.module
-                .add_function("main", self.context.void_type().fn_type(&[], false), None);
+        let main_wrapper = self.add_main_wrapper();
         let main_scope = self.di.builder.create_function(
             self.di.unit.get_file().as_debug_info_scope(),
             "main",
@@ -106,6 +107,10 @@ impl<'ctx> Codegen<'ctx> {
         let basic_block = self.context.append_basic_block(main_wrapper, "entry");
 
         self.builder.position_at_end(basic_block);
+        self.init_args(
+            main_wrapper.get_nth_param(0).unwrap().into_int_value(),
+            main_wrapper.get_nth_param(1).unwrap().into_pointer_value(),
+        );
         // Reference main
         let main_accessor = self
             .module
@@ -115,7 +120,12 @@ impl<'ctx> Codegen<'ctx> {
         self.call_internal(main, main_accessor, &[]);
 
         // Call main
-        let return_pointer = self.call_main(main, &[], LLVMCallConv::LLVMFastCallConv as u32);
+        let return_pointer = self.call_main(
+            main,
+            &[],
+            LLVMCallConv::LLVMFastCallConv as u32,
+            Some(output_ptr.as_pointer_value()),
+        );
         let return_value = self
             .builder
             .build_load(self.value_type(), return_pointer, "")
@@ -131,9 +141,7 @@ impl<'ctx> Codegen<'ctx> {
 
     pub(crate) fn compile_test_entrypoint(&self, test_accessor_names: &[&str]) {
         let span = source_span::Span::default();
-        let main_wrapper =
-            self.module
-                .add_function("main", self.context.void_type().fn_type(&[], false), None);
+        let main_wrapper = self.add_main_wrapper();
         let main_scope = self.di.builder.create_function(
             self.di.unit.get_file().as_debug_info_scope(),
             "main",
@@ -155,6 +163,10 @@ impl<'ctx> Codegen<'ctx> {
         self.set_span(span);
         let basic_block = self.context.append_basic_block(main_wrapper, "entry");
         self.builder.position_at_end(basic_block);
+        self.init_args(
+            main_wrapper.get_nth_param(0).unwrap().into_int_value(),
+            main_wrapper.get_nth_param(1).unwrap().into_pointer_value(),
+        );
 
         let test_manifest = self.allocate_value("tests");
         let test_array =
@@ -179,6 +191,7 @@ impl<'ctx> Codegen<'ctx> {
                 .unwrap()
                 .into()],
             TAIL_CALL_CONV,
+            None,
         );
         self.builder.build_return(None).unwrap();
         self.close_continuation();
@@ -186,10 +199,230 @@ impl<'ctx> Codegen<'ctx> {
         self.di.pop_scope();
     }
 
+    /// Compiles the entrypoint through which an embedding host calls a procedure of the
+    /// given arity. Functions are called the same way, as procedures of arity 1.
+    ///
+    /// The entrypoint receives an array of values: the procedure followed by its arguments.
+    /// The procedure's return value is written to the output.
+    pub(crate) fn compile_embedded_call(&self, arity: usize, output: *mut TrilogyValue) {
+        let name = format!("#embedded::call/{arity}");
+        if self.module.get_function(&name).is_some() {
+            return;
+        }
+        let output_ptr = self.embedded_output(output);
+        let arguments = self.begin_embedded_entry(&name);
+
+        let target = self.allocate_value("target");
+        self.builder
+            .build_store(target, self.embedded_argument(arguments, 0))
+            .unwrap();
+        let args: Vec<BasicMetadataValueEnum> = (1..=arity)
+            .map(|i| self.embedded_argument(arguments, i).into())
+            .collect();
+        let return_pointer = self.call_main(
+            target,
+            &args,
+            TAIL_CALL_CONV,
+            Some(output_ptr.as_pointer_value()),
+        );
+        let return_value = self.load_value(return_pointer, "");
+        self.builder
+            .build_store(output_ptr.as_pointer_value(), return_value)
+            .unwrap();
+        self.builder.build_return(None).unwrap();
+        self.close_continuation();
+        self.di.pop_scope();
+        self.di.pop_scope();
+    }
+
+    /// Compiles the entrypoint through which an embedding host queries a rule of the given
+    /// arity, finding all of its solutions.
+    ///
+    /// The entrypoint receives an array of values: the rule followed by its arguments, where
+    /// `undefined` marks an unbound argument. An array of solutions, each an array of the
+    /// rule's bound arguments, is written to the output.
+    pub(crate) fn compile_embedded_query(&self, arity: usize, output: *mut TrilogyValue) {
+        let name = format!("#embedded::query/{arity}");
+        if self.module.get_function(&name).is_some() {
+            return;
+        }
+        let (query, metadata) = self.compile_query_procedure(arity);
+        let output_ptr = self.embedded_output(output);
+        let arguments = self.begin_embedded_entry(&name);
+
+        let target = self.allocate_value("query");
+        self.trilogy_callable_init_proc(target, arity + 1, query, metadata);
+        let args: Vec<BasicMetadataValueEnum> = (0..=arity)
+            .map(|i| self.embedded_argument(arguments, i).into())
+            .collect();
+        let return_pointer = self.call_main(
+            target,
+            &args,
+            TAIL_CALL_CONV,
+            Some(output_ptr.as_pointer_value()),
+        );
+        let return_value = self.load_value(return_pointer, "");
+        self.builder
+            .build_store(output_ptr.as_pointer_value(), return_value)
+            .unwrap();
+        self.builder.build_return(None).unwrap();
+        self.close_continuation();
+        self.di.pop_scope();
+        self.di.pop_scope();
+    }
+
+    /// A procedure that collects every solution of a rule into an array, as if by the
+    /// comprehension `[[a, b, ...] for rule(a, b, ...)]`.
+    fn compile_query_procedure(&self, arity: usize) -> (FunctionValue<'ctx>, GlobalValue<'ctx>) {
+        let name = format!("#embedded::query/{arity}");
+        let span = Span::default();
+        let function = self.add_procedure(&name, arity + 1, &name, span, false);
+        let metadata = self.build_callable_data("trilogy", &name, arity as u32 + 1, span, None);
+        self.set_current_definition(name.clone(), name, span, metadata, None);
+        self.begin_function(function, span);
+
+        let params =
+            self.function_params.borrow()[IMPLICIT_PARAMS..=IMPLICIT_PARAMS + arity].to_vec();
+        let rule = self.allocate_value("rule");
+        self.trilogy_value_clone_into(rule, params[0]);
+        let inputs: Vec<_> = params[1..]
+            .iter()
+            .map(|param| {
+                let input = self.allocate_value("in_arg");
+                self.trilogy_value_clone_into(input, *param);
+                self.bind_temporary(input);
+                input
+            })
+            .collect();
+
+        let solutions = self.allocate_value("solutions");
+        self.trilogy_array_init_cap(solutions, 8, "");
+        self.bind_temporary(solutions);
+
+        let done_function = self.add_continuation("done");
+        let (done_continuation, done_continuation_point) =
+            self.capture_current_continuation_full(done_function, "query_done", span);
+        let (next_iteration, outputs) = self.call_rule(
+            rule,
+            &inputs,
+            self.use_temporary(done_continuation).unwrap(),
+            "query_next",
+            span,
+        );
+        self.bind_temporary(next_iteration);
+        let solution = self.allocate_value("solution");
+        let solution_array = self.trilogy_array_init_cap(solution, arity, "");
+        for output in outputs {
+            self.trilogy_array_push(solution_array, output);
+        }
+        let solutions_val = self.use_temporary_clone(solutions).unwrap();
+        let solutions_arr = self.trilogy_array_assume(solutions_val, "");
+        self.trilogy_array_push(solutions_arr, solution);
+        let next_iteration = self.use_temporary_clone(next_iteration).unwrap();
+        self.void_call_continuation(next_iteration);
+
+        self.become_continuation_point(done_continuation_point);
+        self.begin_next_function(done_function);
+        let solutions = self.use_temporary_clone(solutions).unwrap();
+        self.call_known_continuation(self.get_return(""), solutions);
+        self.end_function();
+        self.close_continuation();
+        (function, metadata)
+    }
+
+    /// Begins an entrypoint by which an embedding host calls into the program. Entrypoints use
+    /// the C calling convention, receiving a pointer to an array of arguments.
+    fn begin_embedded_entry(&self, name: &str) -> PointerValue<'ctx> {
+        let span = Span::default();
+        let entry = self.module.add_function(
+            name,
+            self.context.void_type().fn_type(
+                &[self.context.ptr_type(AddressSpace::default()).into()],
+                false,
+            ),
+            None,
+        );
+        let scope = self.di.builder.create_function(
+            self.di.unit.get_file().as_debug_info_scope(),
+            name,
+            None,
+            self.di.unit.get_file(),
+            span.start().line as u32 + 1,
+            self.di.continuation_di_type(),
+            true,
+            true,
+            span.start().line as u32 + 1,
+            LLVMDIFlagPublic,
+            false,
+        );
+        entry.set_subprogram(scope);
+        let metadata = self.build_callable_data("trilogy", "#embedded", 0, span, None);
+        self.set_current_definition(name.to_owned(), name.to_owned(), span, metadata, None);
+        self.di.push_subprogram(scope);
+        self.di.push_block_scope(span);
+        self.set_span(span);
+        let basic_block = self.context.append_basic_block(entry, "entry");
+        self.builder.position_at_end(basic_block);
+        entry.get_nth_param(0).unwrap().into_pointer_value()
+    }
+
+    /// Loads the argument at the given index of an embedded entrypoint's arguments.
+    fn embedded_argument(
+        &self,
+        arguments: PointerValue<'ctx>,
+        index: usize,
+    ) -> inkwell::values::BasicValueEnum<'ctx> {
+        let pointer = unsafe {
+            self.builder
+                .build_gep(
+                    self.value_type(),
+                    arguments,
+                    &[self.context.i64_type().const_int(index as u64, false)],
+                    "",
+                )
+                .unwrap()
+        };
+        self.builder
+            .build_load(self.value_type(), pointer, "")
+            .unwrap()
+    }
+
+    /// The global into which embedded entrypoints write their output, which is mapped to
+    /// memory owned by the host.
+    fn embedded_output(&self, output: *mut TrilogyValue) -> GlobalValue<'ctx> {
+        if let Some(global) = self.module.get_global("output") {
+            return global;
+        }
+        let global = self.module.add_global(self.value_type(), None, "output");
+        self.execution_engine
+            .add_global_mapping(&global, output as usize);
+        global
+    }
+
+    /// The `main` function of the compiled program, which receives the command line
+    /// arguments the same way as a C `main` function.
+    fn add_main_wrapper(&self) -> FunctionValue<'ctx> {
+        self.module.add_function(
+            "main",
+            self.context.void_type().fn_type(
+                &[
+                    self.context.i32_type().into(),
+                    self.context.ptr_type(AddressSpace::default()).into(),
+                ],
+                false,
+            ),
+            None,
+        )
+    }
+
     fn build_atom_registry(&self) {
         let atoms = self.atoms.borrow();
-        let mut atoms_vec: Vec<_> = atoms.iter().collect();
-        atoms_vec.sort_by_key(|(_, s)| **s);
+        // Atoms claimed by cached modules may leave gaps in the ids, which are filled
+        // with empty names so that the registry can still be indexed by id.
+        let mut atoms_vec = vec![""; atoms.values().max().map_or(0, |max| *max as usize + 1)];
+        for (atom, id) in atoms.iter() {
+            atoms_vec[*id as usize] = atom;
+        }
         let atom_registry_sz =
             self.module
                 .add_global(self.context.i64_type(), None, "atom_registry_sz");
@@ -206,7 +439,7 @@ impl<'ctx> Codegen<'ctx> {
         );
         let atom_table: Vec<_> = atoms_vec
             .into_iter()
-            .map(|(atom, _)| {
+            .map(|atom| {
                 let string = self.global_c_string(atom, true);
                 self.string_value_type().const_named_struct(&[
                     self.context
@@ -15,13 +15,13 @@ impl<'ctx> Codegen<'ctx> {
         match builtin {
             Builtin::Return => {
                 let result = self.compile_expression(expression, "retval")?;
-                let return_cont = self.get_return("return");
+                let return_cont = self.get_return_from_scope("return");
                 self.call_known_continuation(return_cont, result);
                 None
             }
             Builtin::Exit => {
                 let result = self.compile_expression(expression, "exit_code")?;
-                _ = self.exit(result);
+                self.compile_exit(result, span);
                 None
             }
             Builtin::Typeof => {
@@ -88,6 +88,7 @@ impl<'ctx> Codegen<'ctx> {
             // Non-unary operators
             Builtin::Is => unreachable!(),
             Builtin::Pin => unreachable!(),
+            Builtin::TypeCheck => unreachable!(),
             Builtin::Remainder => unreachable!(),
             Builtin::Power => unreachable!(),
             Builtin::IntDivide => unreachable!(),
@@ -426,6 +427,7 @@ impl<'ctx> Codegen<'ctx> {
             Builtin::Is => unreachable!(),
             Builtin::Typeof => unreachable!(),
             Builtin::Pin => unreachable!(),
+            Builtin::TypeCheck => unreachable!(),
             Builtin::Yield => unreachable!(),
             Builtin::Exit => unreachable!(),
             Builtin::Resume => unreachable!(),
@@ -663,6 +665,7 @@ impl<'ctx> Codegen<'ctx> {
             Builtin::Is => unreachable!(),
             Builtin::Typeof => unreachable!(),
             Builtin::Pin => unreachable!(),
+            Builtin::TypeCheck => unreachable!(),
             Builtin::Yield => unreachable!(),
             Builtin::Exit => unreachable!(),
         }
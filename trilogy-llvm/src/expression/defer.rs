@@ -0,0 +1,180 @@
+use crate::codegen::Codegen;
+use inkwell::values::PointerValue;
+use source_span::Span;
+use trilogy_ir::ir;
+
+impl<'ctx> Codegen<'ctx> {
+    /// Compiles a `defer` statement, where `rest` is the remainder of the block in which
+    /// it appears, which is the scope that the deferred block is run at the end of.
+    ///
+    /// The deferred block runs exactly once, whether the scope completes normally or is
+    /// left early, guarded by a flag that is cleared once it has run:
+    /// * `return`, `break`, and `continue` are lexical, so within the scope they are
+    ///   replaced by continuations that first run the deferred block.
+    /// * `cancel` and `exit` may leave the scope from anywhere, so the deferred block is
+    ///   also registered with the runtime, to be run by `with` when its handled section is
+    ///   cancelled, or before the program exits.
+    pub(super) fn compile_defer(
+        &self,
+        defer: &ir::Defer,
+        rest: &[ir::Expression],
+        name: &str,
+        span: Span,
+    ) -> Option<PointerValue<'ctx>> {
+        if rest.is_empty() {
+            let result = self.compile_expression(&defer.body, "defer.body")?;
+            self.trilogy_value_destroy(result);
+            return Some(self.allocate_const(self.unit_const(), name));
+        }
+
+        let armed = self.allocate_const(self.bool_const(true), "defer.armed");
+        self.bind_temporary(armed);
+        let key = self.allocate_value("defer.key");
+        self.defer_key(key);
+        self.bind_temporary(key);
+
+        // When unwinding, the deferred block is called with the continuation that
+        // continues the unwinding.
+        let unwind_to = self.defer_continuation(defer, armed, key, "defer.unwind", span, |next| {
+            self.void_call_continuation(next);
+        });
+        self.defer_push(self.use_temporary(key).unwrap(), unwind_to);
+
+        let return_to = self.defer_continuation(defer, armed, key, "defer.return", span, |value| {
+            self.call_known_continuation(self.get_return_from_scope(""), value);
+        });
+        let in_loop = !self.current_break.borrow().is_empty();
+        let loop_scope = in_loop.then(|| {
+            let break_to =
+                self.defer_continuation(defer, armed, key, "defer.break", span, |value| {
+                    self.call_known_continuation(self.get_break(), value);
+                });
+            let continue_to =
+                self.defer_continuation(defer, armed, key, "defer.continue", span, |value| {
+                    self.call_known_continuation(self.get_continue(), value);
+                });
+            self.push_loop_scope(break_to, continue_to);
+            (break_to, continue_to)
+        });
+        self.push_defer_scope(return_to);
+        let result = self.compile_sequence(rest, name);
+        self.pop_defer_scope();
+        if loop_scope.is_some() {
+            self.pop_loop_scope();
+        }
+        let result = result?;
+        self.bind_temporary(result);
+
+        // The scope completed normally, so the deferred block runs here instead.
+        self.disarm(armed, key);
+        self.destroy_owned_temporary(return_to);
+        if let Some((break_to, continue_to)) = loop_scope {
+            self.destroy_owned_temporary(break_to);
+            self.destroy_owned_temporary(continue_to);
+        }
+        let body = self.compile_expression(&defer.body, "defer.body")?;
+        self.trilogy_value_destroy(body);
+        Some(self.use_temporary_clone(result).unwrap())
+    }
+
+    /// Builds a continuation which runs the deferred block, if it has not run yet, and
+    /// then continues by calling `then` with the value that the continuation was called with.
+    fn defer_continuation(
+        &self,
+        defer: &ir::Defer,
+        armed: PointerValue<'ctx>,
+        key: PointerValue<'ctx>,
+        name: &str,
+        span: Span,
+        then: impl Fn(PointerValue<'ctx>),
+    ) -> PointerValue<'ctx> {
+        let function = self.add_continuation(name);
+        let (continuation, continuation_point) =
+            self.capture_current_continuation_full(function, name, span);
+        let here = self.builder.get_insert_block().unwrap();
+        let snapshot = self.snapshot_function_context();
+        let outer_cp = self.shadow_continuation_point();
+
+        self.become_continuation_point(continuation_point);
+        self.begin_next_function(function);
+        let value = self.get_continuation("defer.value");
+        self.bind_temporary(value);
+        let is_armed = self.trilogy_boolean_untag(self.use_temporary(armed).unwrap(), "");
+        let run = self
+            .context
+            .append_basic_block(self.get_function(), "defer.run");
+        let skip = self
+            .context
+            .append_basic_block(self.get_function(), "defer.skip");
+        let run_cp = self.branch_continuation_point();
+        self.builder
+            .build_conditional_branch(is_armed, run, skip)
+            .unwrap();
+        let run_snapshot = self.snapshot_function_context();
+
+        self.builder.position_at_end(skip);
+        then(self.use_temporary_clone(value).unwrap());
+
+        self.builder.position_at_end(run);
+        self.restore_function_context(run_snapshot);
+        self.become_continuation_point(run_cp);
+        self.disarm(armed, key);
+        if let Some(body) = self.compile_expression(&defer.body, "defer.body") {
+            self.trilogy_value_destroy(body);
+            then(self.use_temporary_clone(value).unwrap());
+        }
+
+        self.builder.position_at_end(here);
+        self.restore_function_context(snapshot);
+        self.become_continuation_point(outer_cp);
+        continuation
+    }
+
+    /// Marks a deferred block as run, and removes it from the runtime's deferred blocks.
+    fn disarm(&self, armed: PointerValue<'ctx>, key: PointerValue<'ctx>) {
+        let armed = self.use_temporary(armed).unwrap();
+        self.builder
+            .build_store(armed, self.bool_const(false))
+            .unwrap();
+        self.defer_remove(self.use_temporary(key).unwrap());
+    }
+
+    /// Runs the deferred blocks registered with the runtime, innermost first, until only
+    /// `depth` of them remain, or all of them if `depth` is `None`.
+    pub(crate) fn unwind_deferred(&self, depth: Option<PointerValue<'ctx>>, span: Span) {
+        let unwind_function = self.add_continuation("defer.unwind");
+        let unwind = self.continue_in_loop(unwind_function, span);
+        self.begin_next_function(unwind_function);
+
+        let deferred = self.allocate_value("deferred");
+        let depth = depth.map(|depth| self.use_temporary(depth).unwrap());
+        let has_deferred = self.defer_unwind(deferred, depth, "");
+        let run = self
+            .context
+            .append_basic_block(self.get_function(), "defer.unwind.run");
+        let done = self
+            .context
+            .append_basic_block(self.get_function(), "defer.unwind.done");
+        let done_cp = self.branch_continuation_point();
+        self.builder
+            .build_conditional_branch(has_deferred, run, done)
+            .unwrap();
+        let snapshot = self.snapshot_function_context();
+
+        self.builder.position_at_end(run);
+        let unwind = self.use_temporary_clone(unwind).unwrap();
+        self.call_known_continuation(deferred, unwind);
+
+        self.builder.position_at_end(done);
+        self.restore_function_context(snapshot);
+        self.become_continuation_point(done_cp);
+    }
+
+    /// Exits the program with the given value, first running every deferred block that
+    /// is registered with the runtime.
+    pub(super) fn compile_exit(&self, value: PointerValue<'ctx>, span: Span) {
+        self.bind_temporary(value);
+        self.unwind_deferred(None, span);
+        _ = self.exit(self.use_temporary_clone(value).unwrap());
+    }
+}
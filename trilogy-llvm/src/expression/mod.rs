@@ -6,6 +6,7 @@ use trilogy_ir::visitor::{Bindings, HasBindings};
 use trilogy_parser::syntax;
 
 mod builtin;
+mod defer;
 
 impl<'ctx> Codegen<'ctx> {
     #[must_use = "must acknowldge continuation of control flow"]
@@ -73,7 +74,7 @@ impl<'ctx> Codegen<'ctx> {
                 self.compile_end();
                 None
             }
-            Value::Defer(..) => todo!("implement defer"),
+            Value::Defer(defer) => self.compile_defer(defer, &[], name, expression.span),
             Value::Pack(..) => panic!("loose packs are not permitted"),
             Value::Mapping(..) => panic!("loose mappings are not permitted"),
             Value::Conjunction(..) => panic!("conjunction not permitted in expression context"),
@@ -154,6 +155,9 @@ impl<'ctx> Codegen<'ctx> {
         name: &str,
         span: Span,
     ) -> Option<PointerValue<'ctx>> {
+        if let Some(accumulator) = &expr.accumulator {
+            return self.compile_fold(expr, accumulator, name, span);
+        }
         let done_function = self.add_continuation("done");
         let (done_continuation, done_continuation_point) =
             self.capture_current_continuation_full(done_function, "for_break", span);
@@ -175,6 +179,59 @@ impl<'ctx> Codegen<'ctx> {
         Some(self.allocate_const(self.unit_const(), ""))
     }
 
+    /// Compiles the expression form of `for`, which folds the values of each iteration
+    /// into its accumulator. Reaching the end of the body is the same as `continue` with
+    /// the body's value, which becomes the accumulator for the next iteration, while `break`
+    /// ends the loop early, evaluating to the accumulator as it is.
+    fn compile_fold(
+        &self,
+        expr: &ir::Iterator,
+        accumulator: &ir::Accumulator,
+        name: &str,
+        span: Span,
+    ) -> Option<PointerValue<'ctx>> {
+        let initial = self.compile_expression(&accumulator.initial, "fold.initial")?;
+        let variable = self.variable(&accumulator.identifier.id);
+        self.trilogy_value_clone_into(variable, initial);
+        self.trilogy_value_destroy(initial);
+
+        let done_function = self.add_continuation("done");
+        let (done_continuation, done_continuation_point) =
+            self.capture_current_continuation_full(done_function, "for_break", span);
+        let done_to_clone = self.allocate_value("break");
+        self.trilogy_value_clone_into(done_to_clone, done_continuation);
+        self.bind_temporary(done_to_clone);
+        let next_iteration = self.compile_query_iteration(&expr.query, done_continuation);
+        self.bind_temporary(next_iteration);
+
+        let continue_function = self.add_continuation("fold.continue");
+        let (continue_to, continue_continuation_point) =
+            self.capture_current_continuation_full(continue_function, "fold.continue", span);
+        let here = self.builder.get_insert_block().unwrap();
+        let snapshot = self.snapshot_function_context();
+        let outer_cp = self.shadow_continuation_point();
+        self.become_continuation_point(continue_continuation_point);
+        self.begin_next_function(continue_function);
+        let variable = self.get_variable(&accumulator.identifier.id).unwrap().ptr();
+        self.trilogy_value_destroy(variable);
+        self.trilogy_value_clone_into(variable, self.get_continuation_temporary());
+        self.void_call_continuation(self.use_temporary_clone(next_iteration).unwrap());
+        self.builder.position_at_end(here);
+        self.restore_function_context(snapshot);
+        self.become_continuation_point(outer_cp);
+        self.bind_temporary(continue_to);
+
+        self.push_loop_scope(done_to_clone, continue_to);
+        if let Some(value) = self.compile_expression(&expr.value, name) {
+            self.call_known_continuation(self.get_continue(), value);
+        }
+        self.pop_loop_scope();
+
+        self.become_continuation_point(done_continuation_point);
+        self.begin_next_function(done_function);
+        Some(self.compile_reference(&accumulator.identifier, name))
+    }
+
     fn compile_array_comprehension(
         &self,
         expr: &ir::Iterator,
@@ -274,6 +331,12 @@ impl<'ctx> Codegen<'ctx> {
         let body_function = self.add_continuation("when.handler");
         let handler_function = self.add_yield();
 
+        // Deferred blocks registered from within the handled section must be run if it
+        // is cancelled.
+        let defer_depth = self.allocate_value("defer.depth");
+        self.defer_depth(defer_depth);
+        self.bind_temporary(defer_depth);
+
         // Prepare cancel continuation for after the handled section is complete.
         let cancel_to_function = self.add_continuation("when.cancel");
         let (cancel_to, cancel_to_continuation_point) =
@@ -306,7 +369,10 @@ impl<'ctx> Codegen<'ctx> {
         self.pop_with_scope();
         self.become_continuation_point(cancel_to_continuation_point);
         self.begin_next_function(cancel_to_function);
-        Some(self.get_continuation(name))
+        let result = self.get_continuation(name);
+        self.bind_temporary(result);
+        self.unwind_deferred(Some(defer_depth), span);
+        Some(self.use_temporary_clone(result).unwrap())
     }
 
     fn compile_handlers(&self, handlers: &[ir::Handler], span: Span) {
@@ -429,13 +495,18 @@ impl<'ctx> Codegen<'ctx> {
     }
 
     fn compile_sequence(&self, seq: &[ir::Expression], name: &str) -> Option<PointerValue<'ctx>> {
-        let mut exprs = seq.iter();
-        let mut value = self.compile_expression(exprs.next().unwrap(), "")?;
-        for expr in exprs {
-            self.trilogy_value_destroy(value);
-            value = self.compile_expression(expr, name)?;
+        let mut value = None;
+        for (i, expr) in seq.iter().enumerate() {
+            if let Some(value) = value {
+                self.trilogy_value_destroy(value);
+            }
+            if let Value::Defer(defer) = &expr.value {
+                // The rest of the sequence is the scope of the `defer`.
+                return self.compile_defer(defer, &seq[i + 1..], name, expr.span);
+            }
+            value = Some(self.compile_expression(expr, name)?);
         }
-        Some(value)
+        value
     }
 
     fn compile_array(&self, pack: &ir::Pack, name: &str) -> Option<PointerValue<'ctx>> {
@@ -559,7 +630,7 @@ impl<'ctx> Codegen<'ctx> {
                     let var = self.get_variable(&id).unwrap().ptr();
                     self.trilogy_value_destroy(var);
                 }
-                self.compile_pattern_match(&unif.pattern, value, self.get_end_temporary())?;
+                self.compile_final_pattern_match(&unif.pattern, value, self.get_end_temporary())?;
                 self.destroy_owned_temporary(value);
                 self.compile_expression(&decl.body, name)
             }
@@ -581,7 +652,7 @@ impl<'ctx> Codegen<'ctx> {
         let continuation = self.add_continuation("");
         let mut merger = Merger::default();
         let mut returns = false;
-        for case in &expr.cases {
+        for (i, case) in expr.cases.iter().enumerate() {
             // An unmatchable case can be skipped; rare this would occur, but easy to handle
             // since we're handling true specially anyway
             if matches!(
@@ -594,7 +665,11 @@ impl<'ctx> Codegen<'ctx> {
             let next_case_function = self.add_continuation("match.next");
             let (go_to_next_case, next_case_cp) =
                 self.capture_current_continuation(next_case_function, "match.next", case.span);
-            self.compile_pattern_match(&case.pattern, discriminant, go_to_next_case)?;
+            if i == expr.cases.len() - 1 {
+                self.compile_final_pattern_match(&case.pattern, discriminant, go_to_next_case)?;
+            } else {
+                self.compile_pattern_match(&case.pattern, discriminant, go_to_next_case)?;
+            }
 
             if let Some(guard) = &case.guard
                 && !matches!(guard.value, Value::Boolean(true))
@@ -784,9 +859,16 @@ impl<'ctx> Codegen<'ctx> {
         match &assign.lhs.value {
             Value::Reference(variable) => {
                 let value = self.compile_expression(&assign.rhs, name)?;
-                let variable = self.get_variable(&variable.id).unwrap();
-                self.trilogy_value_destroy(variable.ptr());
-                self.trilogy_value_clone_into(variable.ptr(), value);
+                match self.get_variable(&variable.id) {
+                    Some(variable) => {
+                        self.trilogy_value_destroy(variable.ptr());
+                        self.trilogy_value_clone_into(variable.ptr(), value);
+                    }
+                    None => {
+                        let global = self.globals.get(&variable.id).expect("unresolved variable");
+                        self.assign_slot(global, value);
+                    }
+                }
                 Some(value)
             }
             Value::Application(app) => match &app.function.value {
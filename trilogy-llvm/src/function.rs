@@ -97,6 +97,7 @@ impl<'ctx> Codegen<'ctx> {
         'outer: for (i, overload) in overloads.iter().enumerate() {
             let overload = overload.borrow();
             assert_eq!(overload.parameters.len(), arity);
+            let is_last = i == overloads.len() - 1;
             if matches!(
                 overload.guard.as_ref().map(|g| &g.value),
                 Some(Value::Boolean(false))
@@ -106,11 +107,7 @@ impl<'ctx> Codegen<'ctx> {
 
             self.set_span(overload.head_span);
 
-            let next_overload_function = self.add_continuation(if i == overloads.len() - 1 {
-                "failed"
-            } else {
-                ""
-            });
+            let next_overload_function = self.add_continuation(if is_last { "failed" } else { "" });
             let (go_to_next_overload, next_overload_cp) = self.capture_current_continuation(
                 next_overload_function,
                 "next_overload",
@@ -118,10 +115,12 @@ impl<'ctx> Codegen<'ctx> {
             );
 
             for (pattern, param) in overload.parameters.iter().zip(&params) {
-                if self
-                    .compile_pattern_match(pattern, *param, go_to_next_overload)
-                    .is_none()
-                {
+                let matched = if is_last {
+                    self.compile_final_pattern_match(pattern, *param, go_to_next_overload)
+                } else {
+                    self.compile_pattern_match(pattern, *param, go_to_next_overload)
+                };
+                if matched.is_none() {
                     break 'outer;
                 }
             }
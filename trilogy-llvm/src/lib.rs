@@ -5,30 +5,65 @@
 //! I guess in theory this means we can swap out the core library, but that's kind of weird. It
 //! would probably be more reliable to include the core module from the trilogy-llvm crate directly,
 //! but this is not convenient due to the compilation requirements, so it is not done.
+pub use cache::{CachedModule, ModuleCache};
 use codegen::Codegen;
+use inkwell::OptimizationLevel;
 use inkwell::context::Context;
-use std::{collections::HashMap, ffi::c_void};
+use inkwell::execution_engine::ExecutionEngine;
+use inkwell::module::Module;
+use inkwell::targets::{
+    CodeModel, FileType, InitializationConfig, RelocMode, Target, TargetMachine,
+};
+use num::{ToPrimitive, Zero};
+use std::collections::HashMap;
+use std::ffi::{CString, c_char, c_int, c_void};
+use std::path::Path;
 use trilogy_ir::ir;
 
 mod bare;
+mod cache;
 mod call;
 mod codegen;
 mod constant;
 mod continue_in_scope;
 mod core;
 mod current_continuation;
+mod embedded;
 mod entrypoint;
 mod expression;
 mod function;
 mod module;
+mod native;
 mod pattern_match;
 mod procedure;
+mod program;
 mod query;
 mod rule;
 mod test;
 mod types;
+mod value;
 
-type Entrypoint = unsafe extern "C" fn() -> c_void;
+pub use embedded::{Call, CallError};
+
+/// Identifies this build of the compiler, changing whenever the code it generates may
+/// change, so that code compiled by one build is never mistaken for another's.
+pub const COMPILER_FINGERPRINT: &str = env!("TRILOGY_COMPILER_FINGERPRINT");
+pub use native::{Continuation, EffectHandler, EffectResponse, NativeProcedure, Outcome};
+pub use program::CompiledProgram;
+pub use value::{Callable, Value};
+
+type Entrypoint = unsafe extern "C" fn(c_int, *const *const c_char) -> c_void;
+type RunEmbedded = unsafe extern "C" fn(
+    Entrypoint,
+    c_int,
+    *const *const c_char,
+    *mut TrilogyValue,
+    *mut c_int,
+) -> bool;
+type InitArgs = unsafe extern "C" fn(c_int, *const *const c_char);
+type EmbeddedEntry = unsafe extern "C" fn(*mut TrilogyValue);
+type RunEmbeddedEntry = unsafe extern "C" fn(EmbeddedEntry, *mut TrilogyValue, *mut c_int) -> bool;
+type ValueDestroy = unsafe extern "C" fn(*mut TrilogyValue);
 
 /// Parameters to rules/procedures/functions start after the implicit parameters: return, yield, and end
 const IMPLICIT_PARAMS: usize = 3;
@@ -43,41 +78,148 @@ pub struct TrilogyValue {
     pub payload: u64,
 }
 
-fn compile<'a>(context: &'a Context, modules: &'a HashMap<String, &ir::Module>) -> Codegen<'a> {
+/// How an embedded program finished running.
+#[derive(Clone, Debug)]
+pub enum Termination {
+    /// The entrypoint returned a value.
+    ///
+    /// The exit code is the one that value would produce if passed to `exit`,
+    /// or `None` if it is not a valid exit code.
+    Returned {
+        value: Value,
+        exit_code: Option<i32>,
+    },
+    /// The program exited before the entrypoint returned, either by calling
+    /// `exit` or by a runtime error.
+    Exited(i32),
+    /// The host's effect handler cancelled the program with a value.
+    ///
+    /// The exit code is the one that value would produce if passed to `exit`,
+    /// or `None` if it is not a valid exit code.
+    Cancelled {
+        value: Value,
+        exit_code: Option<i32>,
+    },
+}
+
+impl Termination {
+    /// The exit code that a value produces if passed to `exit`, if it is a valid one.
+    fn exit_code_of(value: &Value) -> Option<i32> {
+        match value {
+            Value::Unit => Some(0),
+            Value::Number(number) if number.im.is_zero() && number.re.is_integer() => {
+                number.re.to_integer().to_i32().filter(|code| *code >= 0)
+            }
+            _ => None,
+        }
+    }
+
+    /// The exit code for the process running this program. A returned value that is
+    /// not a valid exit code is treated as a runtime error.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Self::Returned { exit_code, .. } | Self::Cancelled { exit_code, .. } => {
+                exit_code.unwrap_or(255)
+            }
+            Self::Exited(code) => *code,
+        }
+    }
+}
+
+fn compile<'a>(
+    context: &'a Context,
+    modules: &'a HashMap<String, &ir::Module>,
+    cache: Option<&dyn ModuleCache>,
+) -> Codegen<'a> {
     let mut codegen = Codegen::new(context, modules);
 
+    // Modules are compiled in a consistent order, libraries first, so that atoms tend to
+    // be assigned the same ids each time, and cached modules can be reused.
+    let mut ordered: Vec<_> = modules.iter().collect();
+    ordered.sort_by_key(|(file, _)| (!file.starts_with("trilogy:"), *file));
+
     log::debug!("beginning trilogy compilation");
-    for (file, module) in modules {
+    let mut uncached = vec![];
+    for (file, module) in ordered {
+        if let Some(cached) = cache.and_then(|cache| cache.load(file))
+            && codegen.consume_cached(cached)
+        {
+            log::debug!("reusing cached module {file}");
+            continue;
+        }
+        uncached.push((file, module));
+    }
+    for (file, module) in uncached {
         log::debug!("compiling module {file}");
         let submodule = codegen.compile_module(file, module, false);
+        if let Some(cache) = cache {
+            cache.save(file, &submodule.to_cached());
+        }
         codegen.consume(submodule)
     }
     log::debug!("trilogy compilation finished");
     codegen
 }
 
-pub fn evaluate(
-    modules: HashMap<String, &ir::Module>,
-    entrymodule: &str,
-    entrypoint: &str,
-    _parameters: Vec<String>,
-) -> TrilogyValue {
-    let context = Context::create();
-    let codegen = compile(&context, &modules);
+/// Collects a program's command line arguments, where the program name is the first
+/// argument, as it would be for a native executable.
+///
+/// Fails with the first argument that contains a NUL character, which cannot be passed
+/// as a C string.
+fn program_args(program: &str, parameters: &[String]) -> Result<Vec<CString>, CallError> {
+    let program = CString::new(program).expect("module locations never contain NUL characters");
+    let parameters = parameters.iter().map(|arg| {
+        CString::new(arg.as_str()).map_err(|_| CallError::InvalidParameter(arg.clone()))
+    });
+    std::iter::once(Ok(program)).chain(parameters).collect()
+}
 
-    let mut output = TrilogyValue::default();
-    codegen.compile_embedded(entrymodule, entrypoint, &mut output as *mut TrilogyValue);
-    let (_module, ee) = codegen.finish();
+/// Provides a JIT compiled program with command line arguments, as its `main` function
+/// would, for entrypoints that are not run through `main`.
+unsafe fn init_args(
+    ee: &ExecutionEngine,
+    program: &str,
+    parameters: &[String],
+) -> Result<(), CallError> {
+    let args = program_args(program, parameters)?;
+    let argv: Vec<*const c_char> = args.iter().map(|arg| arg.as_ptr()).collect();
+    unsafe {
+        ee.get_function::<InitArgs>("init_args")
+            .unwrap()
+            .call(argv.len() as c_int, argv.as_ptr());
+    }
+    Ok(())
+}
 
+/// Calls the `main` function of a JIT compiled program with command line arguments, where
+/// the program name is the first argument, as it would be for a native executable.
+///
+/// The program is run such that exiting returns control here rather than ending the
+/// process. Returns the exit status if the program exited before `main` returned.
+unsafe fn call_main(
+    ee: &ExecutionEngine,
+    program: &str,
+    parameters: &[String],
+    output: *mut TrilogyValue,
+) -> Result<(bool, i32), CallError> {
+    let args = program_args(program, parameters)?;
+    let argv: Vec<*const c_char> = args.iter().map(|arg| arg.as_ptr()).collect();
+    let mut status = 0;
     unsafe {
         log::debug!("locating main (compiling llvm)");
-        let tri_main = ee.get_function::<Entrypoint>("main").unwrap();
+        let tri_main: Entrypoint = std::mem::transmute(ee.get_function_address("main").unwrap());
+        let run_embedded = ee.get_function::<RunEmbedded>("run_embedded").unwrap();
         log::debug!("calling main");
-        tri_main.call();
+        let exited = run_embedded.call(
+            tri_main,
+            argv.len() as c_int,
+            argv.as_ptr(),
+            output,
+            &mut status,
+        );
         log::debug!("called main");
-    };
-
-    output
+        Ok((exited, status))
+    }
 }
 
 pub fn compile_to_llvm(
@@ -86,12 +228,52 @@ pub fn compile_to_llvm(
     entrypoint: &str,
 ) -> String {
     let context = Context::create();
-    let codegen = compile(&context, &modules);
+    let codegen = compile(&context, &modules, None);
     codegen.compile_standalone(entrymodule, entrypoint);
     let (module, _) = codegen.finish();
     module.to_string()
 }
 
+/// Compiles a Trilogy program to a native object file, which must then be linked
+/// into an executable.
+///
+/// Modules found in the `cache` are linked in without being compiled again, and those
+/// that are compiled are saved to it.
+pub fn compile_to_object(
+    modules: HashMap<String, &ir::Module>,
+    entrymodule: &str,
+    entrypoint: &str,
+    output: &Path,
+    cache: Option<&dyn ModuleCache>,
+) -> Result<(), String> {
+    let context = Context::create();
+    let codegen = compile(&context, &modules, cache);
+    codegen.compile_standalone(entrymodule, entrypoint);
+    let (module, _) = codegen.finish();
+    write_object(&module, output)
+}
+
+fn write_object(module: &Module, output: &Path) -> Result<(), String> {
+    Target::initialize_native(&InitializationConfig::default())?;
+    let triple = TargetMachine::get_default_triple();
+    let target = Target::from_triple(&triple).map_err(|error| error.to_string())?;
+    let machine = target
+        .create_target_machine(
+            &triple,
+            &TargetMachine::get_host_cpu_name().to_string(),
+            &TargetMachine::get_host_cpu_features().to_string(),
+            OptimizationLevel::Default,
+            RelocMode::PIC,
+            CodeModel::Default,
+        )
+        .ok_or_else(|| format!("no target machine available for {triple}"))?;
+    module.set_triple(&triple);
+    module.set_data_layout(&machine.get_target_data().get_data_layout());
+    machine
+        .write_to_file(module, FileType::Object, output)
+        .map_err(|error| error.to_string())
+}
+
 fn compile_tests<'a>(
     context: &'a Context,
     modules: &'a HashMap<String, &ir::Module>,
@@ -122,14 +304,20 @@ fn compile_tests<'a>(
     codegen
 }
 
-pub fn evaluate_tests(modules: HashMap<String, &ir::Module>, filter_prefix: &[impl AsRef<str>]) {
-    let context = Context::create();
-    let codegen = compile_tests(&context, &modules, filter_prefix);
-    let (_module, ee) = codegen.finish();
-    unsafe {
-        let tri_main = ee.get_function::<Entrypoint>("main").unwrap();
-        tri_main.call();
-    };
+/// Runs a testsuite, returning the exit code that the test runner finished with.
+///
+/// Native procedures and the effect handler are provided to the tests as they are to
+/// a [`CompiledProgram`][].
+pub fn evaluate_tests(
+    modules: HashMap<String, &ir::Module>,
+    filter_prefix: &[impl AsRef<str>],
+    natives: Vec<NativeProcedure>,
+    effect_handler: Option<EffectHandler>,
+) -> i32 {
+    CompiledProgram::tests(modules, filter_prefix, natives, effect_handler)
+        .run(&[])
+        .expect("tests are run without arguments")
+        .exit_code()
 }
 
 pub fn compile_tests_to_llvm(
@@ -141,3 +329,16 @@ pub fn compile_tests_to_llvm(
     let (module, _) = codegen.finish();
     module.to_string()
 }
+
+/// Compiles a Trilogy testsuite to a native object file, which must then be linked
+/// into an executable.
+pub fn compile_tests_to_object(
+    modules: HashMap<String, &ir::Module>,
+    filter_prefix: &[impl AsRef<str>],
+    output: &Path,
+) -> Result<(), String> {
+    let context = Context::create();
+    let codegen = compile_tests(&context, &modules, filter_prefix);
+    let (module, _) = codegen.finish();
+    write_object(&module, output)
+}
@@ -17,6 +17,7 @@ impl<'ctx> Codegen<'ctx> {
     ) -> Codegen<'ctx> {
         let mut subcontext = self.for_file(file);
         subcontext.compile_module_contents(module, None, true, compile_tests);
+        subcontext.di.builder.finalize();
         subcontext
     }
 
@@ -515,6 +516,11 @@ impl<'ctx> Codegen<'ctx> {
         global: &Global,
         closure: PointerValue<'ctx>,
     ) {
+        // A slot is left undefined in the closure, to be initialized by its accessor the
+        // first time it is accessed, as for a module without parameters.
+        if let Head::Constant = global.head {
+            return;
+        }
         let global_name = format!("{}::{}", global.module_path(&self.location), global.id);
         let function = self
             .module
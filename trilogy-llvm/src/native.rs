@@ -0,0 +1,349 @@
+//! Native procedures, written in Rust, which Trilogy programs call through the
+//! `trilogy_native_call` and `trilogy_native_resume` extern procedures.
+//!
+//! A native procedure cannot take over the control flow of the program the way a
+//! Trilogy procedure can, so it instead describes what it needs the program to do as an
+//! [`Outcome`][], with the rest of its work in a continuation. The Trilogy side of the
+//! native call fulfills each request, resuming the native procedure with the result,
+//! until the native procedure eventually returns.
+//!
+//! Effects that the program does not handle itself may similarly be offered to the
+//! embedding host's [`EffectHandler`][] through `trilogy_host_effect`.
+use crate::TrilogyValue;
+use crate::program::CompiledProgram;
+use crate::value::Value;
+use num::{BigRational, Complex, ToPrimitive, Zero};
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::fmt::{self, Debug};
+use std::rc::Rc;
+
+/// The rest of a native procedure, to be continued with the value produced by the
+/// request it made.
+pub type Continuation = Box<dyn FnOnce(Value) -> Outcome>;
+
+/// What a native procedure requires of the program that called it.
+pub enum Outcome {
+    /// Return a value to the caller, ending the native procedure.
+    Return(Value),
+    /// Yield an effect, continuing with the value it is resumed with.
+    ///
+    /// The continuation may only be resumed once; a handler that resumes it a second
+    /// time is a runtime error.
+    Yield(Value, Continuation),
+    /// Call a procedure with the given arguments, continuing with the value it returns.
+    /// Procedures of up to 8 arguments may be called this way.
+    Call(Value, Vec<Value>, Continuation),
+    /// Apply a function to an argument, continuing with the value it returns.
+    Apply(Value, Value, Continuation),
+}
+
+impl Outcome {
+    /// The equivalent of the `yield` operator.
+    pub fn r#yield(effect: Value, then: impl FnOnce(Value) -> Outcome + 'static) -> Self {
+        Self::Yield(effect, Box::new(then))
+    }
+
+    /// The equivalent of calling `procedure!(arguments...)`.
+    pub fn call(
+        procedure: Value,
+        arguments: Vec<Value>,
+        then: impl FnOnce(Value) -> Outcome + 'static,
+    ) -> Self {
+        Self::Call(procedure, arguments, Box::new(then))
+    }
+
+    /// The equivalent of applying `function argument`.
+    pub fn apply(
+        function: Value,
+        argument: Value,
+        then: impl FnOnce(Value) -> Outcome + 'static,
+    ) -> Self {
+        Self::Apply(function, argument, Box::new(then))
+    }
+}
+
+/// A procedure implemented in Rust, which receives the arguments it was called with.
+#[derive(Clone)]
+pub struct NativeProcedure(Rc<dyn Fn(Vec<Value>) -> Outcome>);
+
+impl NativeProcedure {
+    pub fn new(procedure: impl Fn(Vec<Value>) -> Outcome + 'static) -> Self {
+        Self(Rc::new(procedure))
+    }
+}
+
+impl Debug for NativeProcedure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("NativeProcedure").finish_non_exhaustive()
+    }
+}
+
+/// How the embedding host responds to an effect that the program did not handle.
+pub enum EffectResponse {
+    /// Resume the program from where the effect was yielded, with this value.
+    Resume(Value),
+    /// Cancel the rest of the program, ending it with this value. This is reported as
+    /// [`Termination::Cancelled`][crate::Termination::Cancelled] by runs, and as
+    /// [`CallError::Cancelled`][crate::CallError::Cancelled] by calls.
+    Cancel(Value),
+    /// Leave the effect unhandled, ending the program with an error.
+    Unhandled,
+}
+
+/// A handler for effects that reach the top of a program without being handled, which
+/// receives the effect that was yielded.
+#[derive(Clone)]
+pub struct EffectHandler(Rc<dyn Fn(Value) -> EffectResponse>);
+
+impl EffectHandler {
+    pub fn new(handler: impl Fn(Value) -> EffectResponse + 'static) -> Self {
+        Self(Rc::new(handler))
+    }
+}
+
+impl Debug for EffectHandler {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("EffectHandler").finish_non_exhaustive()
+    }
+}
+
+/// The native procedures of a program, indexed by the ids the program calls them by,
+/// along with the continuations of those which are waiting on the program.
+#[derive(Default)]
+pub(crate) struct Natives {
+    procedures: Vec<NativeProcedure>,
+    effect_handler: Option<EffectHandler>,
+    continuations: RefCell<HashMap<u64, Continuation>>,
+    next_continuation: Cell<u64>,
+    /// Whether the effect handler cancelled the program since this was last checked.
+    cancelled: Cell<bool>,
+}
+
+impl Natives {
+    pub(crate) fn new(
+        procedures: Vec<NativeProcedure>,
+        effect_handler: Option<EffectHandler>,
+    ) -> Self {
+        Self {
+            procedures,
+            effect_handler,
+            ..Self::default()
+        }
+    }
+
+    /// Whether the effect handler cancelled the program since this was last checked, in
+    /// which case the value in the program's output is the value it was cancelled with.
+    pub(crate) fn take_cancelled(&self) -> bool {
+        self.cancelled.replace(false)
+    }
+
+    /// Converts an outcome into the request the program is to fulfill.
+    fn request(&self, outcome: Outcome) -> Value {
+        match outcome {
+            Outcome::Return(value) => Value::Struct("return".to_owned(), Box::new(value)),
+            Outcome::Yield(effect, continuation) => Value::Struct(
+                "yield".to_owned(),
+                Box::new(Value::Tuple(
+                    Box::new(effect),
+                    Box::new(self.suspend(continuation)),
+                )),
+            ),
+            Outcome::Call(procedure, arguments, continuation) => Value::Struct(
+                "call".to_owned(),
+                Box::new(Value::Tuple(
+                    Box::new(procedure),
+                    Box::new(Value::Tuple(
+                        Box::new(Value::Array(arguments)),
+                        Box::new(self.suspend(continuation)),
+                    )),
+                )),
+            ),
+            Outcome::Apply(function, argument, continuation) => Value::Struct(
+                "apply".to_owned(),
+                Box::new(Value::Tuple(
+                    Box::new(function),
+                    Box::new(Value::Tuple(
+                        Box::new(argument),
+                        Box::new(self.suspend(continuation)),
+                    )),
+                )),
+            ),
+        }
+    }
+
+    fn suspend(&self, continuation: Continuation) -> Value {
+        let id = self.next_continuation.get();
+        self.next_continuation.set(id + 1);
+        self.continuations.borrow_mut().insert(id, continuation);
+        Value::Number(Complex::from(BigRational::from_integer(id.into())))
+    }
+}
+
+thread_local! {
+    static CURRENT: Cell<*const CompiledProgram> = const { Cell::new(std::ptr::null()) };
+}
+
+/// Marks a program as running on this thread, so that native procedures it calls can
+/// be found, until this is dropped.
+///
+/// When the outermost call into a program ends, the continuations of native procedures
+/// it never resumed are dropped, as nothing remains that could resume them.
+pub(crate) struct Running {
+    program: *const CompiledProgram,
+    previous: *const CompiledProgram,
+}
+
+impl Running {
+    pub(crate) fn new(program: &CompiledProgram) -> Self {
+        Self {
+            program,
+            previous: CURRENT.replace(program),
+        }
+    }
+}
+
+impl Drop for Running {
+    fn drop(&mut self) {
+        CURRENT.set(self.previous);
+        if self.previous != self.program {
+            let program = unsafe { &*self.program };
+            program.natives().continuations.borrow_mut().clear();
+        }
+    }
+}
+
+/// The addresses of the extern procedures through which programs call native procedures.
+pub(crate) fn symbols() -> [(&'static str, usize); 3] {
+    [
+        ("trilogy_native_call", native_call as *const () as usize),
+        ("trilogy_native_resume", native_resume as *const () as usize),
+        ("trilogy_host_effect", host_effect as *const () as usize),
+    ]
+}
+
+/// The program that is currently running on this thread, if any.
+///
+/// Programs are only ever run while marked [`Running`][], so this is missing only if
+/// a program was run some other way.
+fn current<'a>() -> Option<&'a CompiledProgram> {
+    let program = CURRENT.get();
+    if program.is_null() {
+        log::error!("a program that is not running called into its host");
+        return None;
+    }
+    Some(unsafe { &*program })
+}
+
+/// The request by which a native procedure ends the program with a runtime error.
+fn panic(message: String) -> Value {
+    Value::Struct("panic".to_owned(), Box::new(Value::String(message)))
+}
+
+/// Reads the id of a native procedure or continuation, which the program passes as a
+/// natural number.
+fn id(value: Value) -> Result<u64, String> {
+    match value {
+        Value::Number(number) if number.im.is_zero() && number.re.is_integer() => number
+            .re
+            .to_integer()
+            .to_u64()
+            .ok_or_else(|| format!("{number} is not a valid native id")),
+        value => Err(format!("{value:?} is not a valid native id")),
+    }
+}
+
+/// Handles a request from the program, writing the next request into `rv`. A request
+/// that cannot be handled ends the program with a runtime error.
+///
+/// If no program is running, `rv` is left undefined, which the program does not
+/// recognize as a request, so still fails with a runtime error.
+unsafe fn respond(
+    rv: *mut TrilogyValue,
+    f: impl FnOnce(&CompiledProgram) -> Result<Outcome, String>,
+) {
+    let Some(program) = current() else {
+        return;
+    };
+    let request = match f(program) {
+        Ok(outcome) => program.natives().request(outcome),
+        Err(message) => panic(message),
+    };
+    let written = program.write(&request).unwrap_or_else(|value| {
+        program
+            .write(&panic(format!(
+                "{value:?} cannot be passed to a Trilogy program"
+            )))
+            .expect("panic messages are always valid")
+    });
+    unsafe { *rv = written };
+}
+
+extern "C" fn native_call(
+    rv: *mut TrilogyValue,
+    id_value: *mut TrilogyValue,
+    arguments: *mut TrilogyValue,
+) {
+    unsafe {
+        respond(rv, |program| {
+            let id = id(program.take(id_value))?;
+            let Value::Array(arguments) = program.take(arguments) else {
+                return Err("native procedures must be called with an array of arguments".into());
+            };
+            let procedure = usize::try_from(id)
+                .ok()
+                .and_then(|id| program.natives().procedures.get(id))
+                .ok_or_else(|| format!("there is no native procedure with id {id}"))?
+                .clone();
+            Ok((procedure.0)(arguments))
+        });
+    }
+}
+
+extern "C" fn native_resume(
+    rv: *mut TrilogyValue,
+    continuation: *mut TrilogyValue,
+    value: *mut TrilogyValue,
+) {
+    unsafe {
+        respond(rv, |program| {
+            let id = id(program.take(continuation))?;
+            let value = program.take(value);
+            let continuation = program
+                .natives()
+                .continuations
+                .borrow_mut()
+                .remove(&id)
+                .ok_or("native continuations may only be resumed once")?;
+            Ok(continuation(value))
+        });
+    }
+}
+
+extern "C" fn host_effect(rv: *mut TrilogyValue, effect: *mut TrilogyValue) -> u8 {
+    let Some(program) = current() else {
+        return 0;
+    };
+    let effect = unsafe { program.take(effect) };
+    let Some(handler) = &program.natives().effect_handler else {
+        return 0;
+    };
+    let (handling, value) = match (handler.0)(effect) {
+        EffectResponse::Unhandled => return 0,
+        EffectResponse::Resume(value) => (1, value),
+        EffectResponse::Cancel(value) => (2, value),
+    };
+    match program.write(&value) {
+        Ok(response) => {
+            unsafe { *rv = response };
+            if handling == 2 {
+                program.natives().cancelled.set(true);
+            }
+            handling
+        }
+        Err(value) => {
+            log::error!("{value:?} cannot be passed to a Trilogy program");
+            0
+        }
+    }
+}
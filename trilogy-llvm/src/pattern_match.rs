@@ -18,6 +18,22 @@ impl<'ctx> Codegen<'ctx> {
         self.compile_pattern_match_with_bindings(pattern, value, on_fail, &mut bound_ids)
     }
 
+    /// Matches a pattern for which there is no alternative if it fails, such as the pattern
+    /// of a `let` binding or the parameters of the last overload of a function. A type
+    /// pattern that fails here yields a `'type_error` rather than failing silently.
+    #[must_use = "must acknowledge continuation of control flow"]
+    pub(crate) fn compile_final_pattern_match(
+        &self,
+        pattern: &ir::Expression,
+        value: PointerValue<'ctx>,
+        on_fail: PointerValue<'ctx>,
+    ) -> Option<()> {
+        let was_final = self.final_pattern.replace(true);
+        let matched = self.compile_pattern_match(pattern, value, on_fail);
+        self.final_pattern.set(was_final);
+        matched
+    }
+
     #[must_use = "must acknowledge continuation of control flow"]
     pub(crate) fn compile_pattern_match_with_bindings(
         &self,
@@ -92,7 +108,11 @@ impl<'ctx> Codegen<'ctx> {
                 self.begin_next_function(first_function);
                 self.become_continuation_point(primary_cp);
                 let bound_before_first_pattern = bound_ids.len();
-                self.match_pattern(&disj.0, value, go_to_second, bound_ids)?;
+                // The second pattern is the alternative, so the first is never final
+                let was_final = self.final_pattern.replace(false);
+                let first = self.match_pattern(&disj.0, value, go_to_second, bound_ids);
+                self.final_pattern.set(was_final);
+                first?;
                 self.destroy_owned_temporary(go_to_second);
                 let closure = self.void_continue_in_scope(on_success_function);
                 self.end_continuation_point_as_merge(&mut merger, closure);
@@ -341,12 +361,78 @@ impl<'ctx> Codegen<'ctx> {
                     self.destroy_owned_temporary(output);
                     Some(())
                 }
+                Value::Builtin(Builtin::TypeCheck) => {
+                    self.match_type(&app.argument, &application.argument, value, on_fail)
+                }
                 _ => panic!("only some operators are usable in pattern matching"),
             },
             _ => panic!("only builtins can be applied in pattern matching context"),
         }
     }
 
+    /// Checks that a value is of a type by applying the type's predicate function to it.
+    fn match_type(
+        &self,
+        description: &ir::Expression,
+        r#type: &ir::Expression,
+        value: PointerValue<'ctx>,
+        on_fail: PointerValue<'ctx>,
+    ) -> Option<()> {
+        let span = description.span.union(r#type.span);
+        let is_final = self.final_pattern.get();
+        let predicate = self.compile_expression(r#type, "type")?;
+        let argument = self.allocate_value("");
+        self.trilogy_value_clone_into(argument, self.use_temporary(value).unwrap());
+        let result = self.apply_function(predicate, argument, "is_type", span);
+        let is_type = self.trilogy_boolean_untag(result, "");
+        self.trilogy_value_destroy(result);
+        if !is_final {
+            self.pm_cont_if(is_type, on_fail);
+            return Some(());
+        }
+
+        let pass_cp = self.branch_continuation_point();
+        let pass = self
+            .context
+            .append_basic_block(self.get_function(), "type.pass");
+        let fail = self
+            .context
+            .append_basic_block(self.get_function(), "type.fail");
+        self.builder
+            .build_conditional_branch(is_type, pass, fail)
+            .unwrap();
+        let snapshot = self.snapshot_function_context();
+
+        self.builder.position_at_end(fail);
+        let type_error = self.allocate_value("type_error");
+        let record = self.trilogy_record_init_cap(type_error, 2, "");
+        let key = self.allocate_const(self.atom_const("type".to_owned()), "");
+        let description = self.compile_expression(description, "type.description")?;
+        self.trilogy_record_insert(record, key, description);
+        let key = self.allocate_const(self.atom_const("value".to_owned()), "");
+        let value_clone = self.allocate_value("");
+        self.trilogy_value_clone_into(value_clone, self.use_temporary(value).unwrap());
+        self.trilogy_record_insert(record, key, value_clone);
+        let effect = self.allocate_value("");
+        self.trilogy_struct_init_new(
+            effect,
+            self.context
+                .i64_type()
+                .const_int(self.atom_value_raw("type_error".to_owned()), false),
+            type_error,
+        );
+        // If the handler resumes, the value was still not of the type, so the match fails
+        let resumed = self.call_yield(effect, "", span);
+        self.trilogy_value_destroy(resumed);
+        let on_fail = self.use_temporary_clone(on_fail).unwrap();
+        self.void_call_continuation(on_fail);
+
+        self.builder.position_at_end(pass);
+        self.become_continuation_point(pass_cp);
+        self.restore_function_context(snapshot);
+        Some(())
+    }
+
     fn compile_match_apply_builtin(
         &self,
         builtin: Builtin,
@@ -150,7 +150,10 @@ impl<'ctx> Codegen<'ctx> {
                 let value = self.function_params.borrow()[n + IMPLICIT_PARAMS];
                 let end = self.get_end_temporary();
                 self.bind_temporary(end);
-                if self.compile_pattern_match(param, value, end).is_none() {
+                if self
+                    .compile_final_pattern_match(param, value, end)
+                    .is_none()
+                {
                     break 'body;
                 }
             }
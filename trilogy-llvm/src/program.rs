@@ -0,0 +1,372 @@
+use crate::codegen::Codegen;
+use crate::embedded::{self, Call, CallError};
+use crate::native::{self, EffectHandler, NativeProcedure, Natives};
+use crate::value::{self, Value};
+use crate::{
+    EmbeddedEntry, ModuleCache, RunEmbeddedEntry, Termination, TrilogyValue, ValueDestroy,
+    call_main, compile, compile_tests, init_args,
+};
+use inkwell::context::Context;
+use inkwell::execution_engine::ExecutionEngine;
+use inkwell::module::Module;
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::fmt::{self, Debug};
+use std::rc::Rc;
+use trilogy_ir::ir;
+
+/// A program that has been compiled and loaded into a JIT execution engine, which may
+/// be run or called into any number of times without being compiled again.
+///
+/// The program's state, such as the values of its module-level constants, is kept
+/// between calls.
+pub struct CompiledProgram {
+    // NOTE: the execution engine and module borrow from the context, so must be
+    // dropped before it, which is ensured by the order of these fields.
+    ee: ExecutionEngine<'static>,
+    _module: Module<'static>,
+    _context: Box<Context>,
+    /// Entrypoints write their results here. The program refers to it by address,
+    /// so it must not move.
+    output: Box<Cell<TrilogyValue>>,
+    /// A token that lives exactly as long as the program, by which values read from
+    /// the program know whether it still exists.
+    alive: Rc<()>,
+    natives: Natives,
+    atoms: HashMap<String, u64>,
+    modules: HashMap<String, ir::Module>,
+    entrymodule: String,
+}
+
+impl Debug for CompiledProgram {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CompiledProgram")
+            .field("entrymodule", &self.entrymodule)
+            .finish_non_exhaustive()
+    }
+}
+
+impl CompiledProgram {
+    /// Compiles a program, along with the entrypoints required to run its `entrypoint`
+    /// procedure (if it has one) and to call any of its exported definitions.
+    ///
+    /// Native procedures are called by the program by their index in `natives`. Effects
+    /// that the program does not handle are offered to the `effect_handler`, if any.
+    /// Modules found in the `cache` are linked in without being compiled again.
+    pub fn new(
+        modules: HashMap<String, &ir::Module>,
+        entrymodule: &str,
+        entrypoint: &str,
+        natives: Vec<NativeProcedure>,
+        effect_handler: Option<EffectHandler>,
+        cache: Option<&dyn ModuleCache>,
+    ) -> Self {
+        Self::load(
+            modules,
+            entrymodule,
+            natives,
+            effect_handler,
+            |context, modules, output| {
+                let codegen = compile(context, modules, cache);
+                let has_entrypoint = modules[entrymodule].definitions().iter().any(|def| {
+                    matches!(&def.item, ir::DefinitionItem::Procedure(..))
+                        && def.name().map(|id| id.name()) == Some(entrypoint)
+                });
+                if has_entrypoint {
+                    codegen.compile_embedded(entrymodule, entrypoint, output);
+                }
+                let (calls, queries) = embedded::arities(modules.values().copied());
+                for arity in calls {
+                    codegen.compile_embedded_call(arity, output);
+                }
+                for arity in queries {
+                    codegen.compile_embedded_query(arity, output);
+                }
+                codegen
+            },
+        )
+    }
+
+    /// Compiles a program that runs the tests whose names start with any of the
+    /// `filter_prefix` when it is [run][CompiledProgram::run].
+    pub(crate) fn tests(
+        modules: HashMap<String, &ir::Module>,
+        filter_prefix: &[impl AsRef<str>],
+        natives: Vec<NativeProcedure>,
+        effect_handler: Option<EffectHandler>,
+    ) -> Self {
+        Self::load(
+            modules,
+            "test",
+            natives,
+            effect_handler,
+            |context, modules, _| compile_tests(context, modules, filter_prefix),
+        )
+    }
+
+    fn load<'m>(
+        modules: HashMap<String, &'m ir::Module>,
+        entrymodule: &str,
+        natives: Vec<NativeProcedure>,
+        effect_handler: Option<EffectHandler>,
+        compile: impl for<'a> FnOnce(
+            &'a Context,
+            &'a HashMap<String, &'m ir::Module>,
+            *mut TrilogyValue,
+        ) -> Codegen<'a>,
+    ) -> Self {
+        let context = Box::new(Context::create());
+        let output = Box::new(Cell::new(TrilogyValue::default()));
+
+        let codegen = compile(&context, &modules, output.as_ptr());
+        let atoms = codegen.atoms.borrow().clone();
+        let (module, ee) = codegen.finish();
+        for (name, address) in native::symbols() {
+            if let Some(function) = module.get_function(name) {
+                ee.add_global_mapping(&function, address);
+            }
+        }
+
+        // SAFETY: The context is boxed, so its address is stable for as long as this
+        // program exists, and it is dropped only after the module and execution engine.
+        let (module, ee) = unsafe {
+            (
+                std::mem::transmute::<Module<'_>, Module<'static>>(module),
+                std::mem::transmute::<ExecutionEngine<'_>, ExecutionEngine<'static>>(ee),
+            )
+        };
+        Self {
+            ee,
+            _module: module,
+            _context: context,
+            output,
+            alive: Rc::new(()),
+            natives: Natives::new(natives, effect_handler),
+            atoms,
+            modules: modules
+                .into_iter()
+                .map(|(location, module)| (location, module.clone()))
+                .collect(),
+            entrymodule: entrymodule.to_owned(),
+        }
+    }
+
+    /// Runs the program's entrypoint procedure with the given command line arguments.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`CallError::InvalidParameter`][] if a command line argument contains a
+    /// NUL character. Exiting is not an error, but is reported by the [`Termination`][].
+    ///
+    /// # Panics
+    ///
+    /// If the entry module does not define the entrypoint procedure.
+    pub fn run(&self, parameters: &[String]) -> Result<Termination, CallError> {
+        let _running = native::Running::new(self);
+        let (exited, status) = unsafe {
+            call_main(
+                &self.ee,
+                &self.entrymodule,
+                parameters,
+                self.output.as_ptr(),
+            )?
+        };
+        if exited {
+            return Ok(Termination::Exited(status));
+        }
+        let value = self.take_output();
+        let exit_code = (status >= 0).then_some(status);
+        if self.natives.take_cancelled() {
+            Ok(Termination::Cancelled { value, exit_code })
+        } else {
+            Ok(Termination::Returned { value, exit_code })
+        }
+    }
+
+    /// Runs the exported 0-arity procedure found by its path from the entry module as
+    /// if it were the program's entrypoint, with the given command line arguments.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`CallError`][] if the path does not refer to an exported 0-arity
+    /// procedure, or if a command line argument contains a NUL character. Exiting is
+    /// not an error, but is reported by the [`Termination`][].
+    pub fn run_member(
+        &self,
+        path: &[&str],
+        parameters: &[String],
+    ) -> Result<Termination, CallError> {
+        unsafe { init_args(&self.ee, &self.entrymodule, parameters)? };
+        match self.call(path, Call::Procedure(vec![])) {
+            Ok(value) => Ok(Termination::Returned {
+                exit_code: Termination::exit_code_of(&value),
+                value,
+            }),
+            Err(CallError::Cancelled(value)) => Ok(Termination::Cancelled {
+                exit_code: Termination::exit_code_of(&value),
+                value: *value,
+            }),
+            Err(CallError::Exited(status)) => Ok(Termination::Exited(status)),
+            Err(error) => Err(error),
+        }
+    }
+
+    /// Calls an exported procedure, function, or rule, found by its path from the
+    /// entry module.
+    ///
+    /// Queries return an array containing every solution, each of which is an array of
+    /// the rule's arguments.
+    pub fn call(&self, path: &[&str], call: Call) -> Result<Value, CallError> {
+        embedded::resolve(&self.modules, &self.entrymodule, path, &call)?;
+        let _running = native::Running::new(self);
+
+        let entry = match &call {
+            Call::Procedure(arguments) => format!("#embedded::call/{}", arguments.len()),
+            Call::Function(..) => "#embedded::call/1".to_owned(),
+            Call::Query(arguments) => format!("#embedded::query/{}", arguments.len()),
+        };
+        unsafe {
+            let mut arguments = vec![TrilogyValue::default()];
+            let written = match &call {
+                Call::Procedure(values) => self.write_all(&mut arguments, values.iter().map(Some)),
+                Call::Function(value) => self.write_all(&mut arguments, [Some(value)]),
+                Call::Query(values) => {
+                    self.write_all(&mut arguments, values.iter().map(Option::as_ref))
+                }
+            };
+            if let Err(error) = written.and_then(|()| {
+                arguments[0] = self.find_member(path)?;
+                Ok(())
+            }) {
+                self.destroy_all(&mut arguments);
+                return Err(error);
+            }
+
+            log::debug!("locating {entry} (compiling llvm)");
+            let entry: EmbeddedEntry =
+                std::mem::transmute(self.ee.get_function_address(&entry).unwrap());
+            log::debug!("calling {}", path.join("::"));
+            self.run_entry(entry, arguments.as_mut_ptr())?;
+        }
+        let value = self.take_output();
+        if self.natives.take_cancelled() {
+            return Err(CallError::Cancelled(Box::new(value)));
+        }
+        Ok(value)
+    }
+
+    /// Runs one of the program's embedded entrypoints, such that exiting returns control
+    /// here rather than ending the process.
+    unsafe fn run_entry(
+        &self,
+        entry: EmbeddedEntry,
+        arguments: *mut TrilogyValue,
+    ) -> Result<(), CallError> {
+        let mut status = 0;
+        let exited = unsafe {
+            self.ee
+                .get_function::<RunEmbeddedEntry>("run_embedded_entry")
+                .unwrap()
+                .call(entry, arguments, &mut status)
+        };
+        if exited {
+            return Err(CallError::Exited(status));
+        }
+        Ok(())
+    }
+
+    /// Writes each value into the program, following the values already in `arguments`,
+    /// where `None` is written as `undefined`. Fails with the first value that cannot be
+    /// represented, leaving those written before it in `arguments`.
+    fn write_all<'a>(
+        &self,
+        arguments: &mut Vec<TrilogyValue>,
+        values: impl IntoIterator<Item = Option<&'a Value>>,
+    ) -> Result<(), CallError> {
+        let writer = value::Writer::new(&self.ee, &self.atoms, &self.alive);
+        for value in values {
+            arguments.push(match value {
+                Some(value) => writer.write(value).map_err(CallError::InvalidArgument)?,
+                None => TrilogyValue::default(),
+            });
+        }
+        Ok(())
+    }
+
+    /// Releases values that were written into the program but never passed to it.
+    ///
+    /// # Safety
+    ///
+    /// The values must be valid values owned by this program.
+    unsafe fn destroy_all(&self, values: &mut [TrilogyValue]) {
+        unsafe {
+            let destroy = self
+                .ee
+                .get_function::<ValueDestroy>("trilogy_value_destroy")
+                .unwrap();
+            for value in values {
+                destroy.call(value);
+            }
+        }
+    }
+
+    /// Reads the result of the last entrypoint that was run, releasing the program's
+    /// copy of it.
+    fn take_output(&self) -> Value {
+        unsafe { self.take(self.output.as_ptr()) }
+    }
+
+    pub(crate) fn natives(&self) -> &Natives {
+        &self.natives
+    }
+
+    /// Reads a value out of the program, releasing the program's copy of it.
+    ///
+    /// # Safety
+    ///
+    /// The value must be a valid value owned by this program.
+    pub(crate) unsafe fn take(&self, value: *mut TrilogyValue) -> Value {
+        unsafe {
+            let read = value::Reader::new(&self.ee, &self.alive).read(&*value);
+            self.ee
+                .get_function::<ValueDestroy>("trilogy_value_destroy")
+                .unwrap()
+                .call(value);
+            read
+        }
+    }
+
+    /// Writes a value into the program, which becomes owned by the program. Fails with
+    /// the offending value if some part of it cannot be represented.
+    pub(crate) fn write(&self, value: &Value) -> Result<TrilogyValue, Box<Value>> {
+        value::Writer::new(&self.ee, &self.atoms, &self.alive).write(value)
+    }
+
+    /// Looks up a member of the program by its path from the entry module, the same way
+    /// as a Trilogy program would access it. The lookup is run as an entrypoint, as
+    /// initializing the modules along the path runs parts of the program.
+    unsafe fn find_member(&self, path: &[&str]) -> Result<TrilogyValue, CallError> {
+        let segments = path
+            .iter()
+            .map(|segment| Value::Atom((*segment).to_owned()))
+            .collect();
+        let Ok(segments) = self.write(&Value::Array(segments)) else {
+            return Err(CallError::NotFound(path.join("::")));
+        };
+        let mut arguments = [TrilogyValue::default(), segments];
+        unsafe {
+            let root: EmbeddedEntry =
+                std::mem::transmute(self.ee.get_function_address(&self.entrymodule).unwrap());
+            let find: EmbeddedEntry =
+                std::mem::transmute(self.ee.get_function_address("embedded_find").unwrap());
+            if let Err(error) = self
+                .run_entry(root, arguments.as_mut_ptr())
+                .and_then(|()| self.run_entry(find, arguments.as_mut_ptr()))
+            {
+                self.destroy_all(&mut arguments);
+                return Err(error);
+            }
+        }
+        Ok(arguments[0])
+    }
+}
@@ -153,19 +153,22 @@ impl<'ctx> Codegen<'ctx> {
     }
 
     pub(crate) fn atom_const(&self, atom: String) -> StructValue<'ctx> {
-        let mut atoms = self.atoms.borrow_mut();
-        let next = atoms.len() as u64;
-        let id = atoms.entry(atom.to_owned()).or_insert(next);
+        let id = self.atom_value_raw(atom);
         self.value_type().const_named_struct(&[
             self.tag_type().const_int(TAG_ATOM, false).into(),
-            self.payload_type().const_int(*id, false).into(),
+            self.payload_type().const_int(id, false).into(),
         ])
     }
 
     pub(crate) fn atom_value_raw(&self, atom: String) -> u64 {
         let mut atoms = self.atoms.borrow_mut();
-        let next = atoms.len() as u64;
-        *atoms.entry(atom.to_owned()).or_insert(next)
+        let id = *atoms.entry(atom.clone()).or_insert_with(|| {
+            let next = self.next_atom.get();
+            self.next_atom.set(next + 1);
+            next
+        });
+        self.used_atoms.borrow_mut().insert(atom, id);
+        id
     }
 
     pub(crate) fn char_const(&self, value: char) -> StructValue<'ctx> {
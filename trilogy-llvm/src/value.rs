@@ -0,0 +1,471 @@
+//! Conversion of runtime values into owned Rust values.
+//!
+//! The layouts here mirror the definitions in `core/types.h`, and must be kept in sync
+//! with them.
+use crate::TrilogyValue;
+use bitvec::prelude::*;
+use inkwell::execution_engine::{ExecutionEngine, JitFunction, UnsafeFunctionPointer};
+use num::bigint::{BigInt, BigUint, Sign};
+use num::{BigRational, Complex};
+use std::collections::HashMap;
+use std::ffi::{c_char, c_void};
+use std::fmt::{self, Debug};
+use std::hash::{Hash, Hasher};
+use std::mem::MaybeUninit;
+use std::rc::{Rc, Weak};
+
+const TAG_UNDEFINED: u8 = 0;
+const TAG_UNIT: u8 = 1;
+const TAG_BOOL: u8 = 2;
+const TAG_ATOM: u8 = 3;
+const TAG_CHAR: u8 = 4;
+const TAG_STRING: u8 = 5;
+const TAG_NUMBER: u8 = 6;
+const TAG_BITS: u8 = 7;
+const TAG_STRUCT: u8 = 8;
+const TAG_TUPLE: u8 = 9;
+const TAG_ARRAY: u8 = 10;
+const TAG_SET: u8 = 11;
+const TAG_RECORD: u8 = 12;
+const TAG_CALLABLE: u8 = 13;
+const TAG_MODULE: u8 = 14;
+const TAG_REFERENCE: u8 = 15;
+
+/// An owned Trilogy value, copied out of the runtime after a program has run.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Value {
+    Unit,
+    Bool(bool),
+    /// An atom, by its name (without the leading `'`).
+    Atom(String),
+    Char(char),
+    String(String),
+    Number(Complex<BigRational>),
+    Bits(BitVec<u8, Msb0>),
+    /// A struct, by the name of its tag and its contained value.
+    Struct(String, Box<Value>),
+    Tuple(Box<Value>, Box<Value>),
+    Array(Vec<Value>),
+    /// The elements of a set, in no particular order.
+    Set(Vec<Value>),
+    /// The entries of a record, in no particular order.
+    Record(Vec<(Value, Value)>),
+    /// A procedure, function, rule, or continuation, which remains owned by the program
+    /// it was read from.
+    Callable(Callable),
+    /// A module. Modules cannot be used once the program has finished running, so
+    /// only their presence is recorded.
+    Module,
+}
+
+/// A reference to a callable value of a compiled program, which may be passed back
+/// into that same program to be called.
+///
+/// Callables may only be used while their program exists. A callable that outlives its
+/// program can no longer be passed to anything.
+#[derive(Clone)]
+pub struct Callable(Rc<Retained>);
+
+struct Retained {
+    value: TrilogyValue,
+    destroy: ValueDestroy,
+    program: Weak<()>,
+}
+
+impl Drop for Retained {
+    fn drop(&mut self) {
+        // Once the program is gone, so is the memory this value refers to.
+        if self.program.upgrade().is_some() {
+            unsafe { (self.destroy)(&mut self.value) }
+        }
+    }
+}
+
+impl PartialEq for Callable {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.value.payload == other.0.value.payload
+    }
+}
+
+impl Eq for Callable {}
+
+impl Hash for Callable {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.value.payload.hash(state);
+    }
+}
+
+impl Debug for Callable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Callable")
+            .field(&self.0.value.payload)
+            .finish()
+    }
+}
+
+#[repr(C)]
+struct StringValue {
+    len: usize,
+    contents: *const c_char,
+}
+
+#[repr(C)]
+struct BitsValue {
+    len: usize,
+    contents: *const u8,
+}
+
+#[repr(C)]
+struct BigInteger {
+    capacity: usize,
+    length: usize,
+    /// Either a pointer to the digits, or (when `capacity` is 0) a single inline digit.
+    contents: u64,
+}
+
+#[repr(C)]
+struct Rational {
+    is_negative: bool,
+    numer: BigInteger,
+    denom: BigInteger,
+}
+
+#[repr(C)]
+struct NumberValue {
+    re: Rational,
+    im: Rational,
+}
+
+#[repr(C)]
+struct StructValue {
+    atom: u64,
+    contents: TrilogyValue,
+}
+
+#[repr(C)]
+struct TupleValue {
+    fst: TrilogyValue,
+    snd: TrilogyValue,
+}
+
+/// Arrays, sets, and records share the same layout, differing only in their contents.
+#[repr(C)]
+struct CollectionValue<T> {
+    rc: u32,
+    len: usize,
+    cap: usize,
+    contents: *const T,
+}
+
+#[repr(C)]
+struct Reference {
+    rc: u32,
+    location: *const TrilogyValue,
+    closed: TrilogyValue,
+}
+
+type AtomRepr = unsafe extern "C" fn(u64) -> *const StringValue;
+type ValueDestroy = unsafe extern "C" fn(*mut TrilogyValue);
+type ValueClone = unsafe extern "C" fn(*mut TrilogyValue, *const TrilogyValue);
+type InitBytes<T> = unsafe extern "C" fn(*mut TrilogyValue, usize, *const T) -> *mut c_void;
+type InitCap = unsafe extern "C" fn(*mut TrilogyValue, usize) -> *mut c_void;
+type Insert = unsafe extern "C" fn(*mut c_void, *mut TrilogyValue);
+type InsertEntry = unsafe extern "C" fn(*mut c_void, *mut TrilogyValue, *mut TrilogyValue);
+type InitRational = unsafe extern "C" fn(*mut Rational, bool, usize, *const u32, usize, *const u32);
+type InitNumber = unsafe extern "C" fn(*mut TrilogyValue, Rational, Rational) -> *mut c_void;
+type InitStruct = unsafe extern "C" fn(*mut TrilogyValue, u64, *mut TrilogyValue) -> *mut c_void;
+type InitTuple =
+    unsafe extern "C" fn(*mut TrilogyValue, *mut TrilogyValue, *mut TrilogyValue) -> *mut c_void;
+
+/// Reads runtime values out of a JIT compiled program, which must not have been
+/// dropped yet.
+///
+/// The program is represented by a token which is dropped along with it, so that
+/// callables read from it know when they are no longer valid.
+pub(crate) struct Reader<'a, 'ctx> {
+    ee: &'a ExecutionEngine<'ctx>,
+    program: &'a Rc<()>,
+}
+
+impl<'a, 'ctx> Reader<'a, 'ctx> {
+    pub(crate) fn new(ee: &'a ExecutionEngine<'ctx>, program: &'a Rc<()>) -> Self {
+        Self { ee, program }
+    }
+
+    /// # Safety
+    ///
+    /// The value must be a valid value produced by the program that is still running in
+    /// this reader's execution engine.
+    pub(crate) unsafe fn read(&self, value: &TrilogyValue) -> Value {
+        unsafe {
+            match value.tag {
+                TAG_UNIT => Value::Unit,
+                TAG_BOOL => Value::Bool(value.payload != 0),
+                TAG_ATOM => Value::Atom(self.atom(value.payload)),
+                TAG_CHAR => Value::Char(
+                    char::from_u32(value.payload as u32).unwrap_or(char::REPLACEMENT_CHARACTER),
+                ),
+                TAG_STRING => Value::String(string(&*(value.payload as *const StringValue))),
+                TAG_NUMBER => {
+                    let number = &*(value.payload as *const NumberValue);
+                    Value::Number(Complex::new(rational(&number.re), rational(&number.im)))
+                }
+                TAG_BITS => {
+                    let bits = &*(value.payload as *const BitsValue);
+                    let bytes = slice(bits.contents, bits.len.div_ceil(8));
+                    let mut bitvec = BitVec::from_slice(bytes);
+                    bitvec.truncate(bits.len);
+                    Value::Bits(bitvec)
+                }
+                TAG_STRUCT => {
+                    let value = &*(value.payload as *const StructValue);
+                    Value::Struct(self.atom(value.atom), Box::new(self.read(&value.contents)))
+                }
+                TAG_TUPLE => {
+                    let tuple = &*(value.payload as *const TupleValue);
+                    Value::Tuple(
+                        Box::new(self.read(&tuple.fst)),
+                        Box::new(self.read(&tuple.snd)),
+                    )
+                }
+                TAG_ARRAY => {
+                    let array = &*(value.payload as *const CollectionValue<TrilogyValue>);
+                    Value::Array(
+                        slice(array.contents, array.len)
+                            .iter()
+                            .map(|element| self.read(element))
+                            .collect(),
+                    )
+                }
+                TAG_SET => Value::Set(
+                    self.entries(value.payload)
+                        .map(|entry| self.read(&entry.fst))
+                        .collect(),
+                ),
+                TAG_RECORD => Value::Record(
+                    self.entries(value.payload)
+                        .map(|entry| (self.read(&entry.fst), self.read(&entry.snd)))
+                        .collect(),
+                ),
+                TAG_CALLABLE => {
+                    let mut retained = TrilogyValue::default();
+                    self.function::<ValueClone>("trilogy_value_clone_into")
+                        .call(&mut retained, value);
+                    Value::Callable(Callable(Rc::new(Retained {
+                        value: retained,
+                        destroy: self
+                            .function::<ValueDestroy>("trilogy_value_destroy")
+                            .as_raw(),
+                        program: Rc::downgrade(self.program),
+                    })))
+                }
+                TAG_MODULE => Value::Module,
+                TAG_REFERENCE => {
+                    let reference = &*(value.payload as *const Reference);
+                    self.read(&*reference.location)
+                }
+                tag => panic!("runtime value has invalid tag {tag}"),
+            }
+        }
+    }
+
+    /// The occupied entries of a set or record, which are stored as a hash table.
+    unsafe fn entries(&self, payload: u64) -> impl Iterator<Item = &TupleValue> {
+        unsafe {
+            let table = &*(payload as *const CollectionValue<TupleValue>);
+            slice(table.contents, table.cap)
+                .iter()
+                .filter(|entry| entry.fst.tag != TAG_UNDEFINED)
+        }
+    }
+
+    unsafe fn atom(&self, id: u64) -> String {
+        unsafe {
+            let repr = self.function::<AtomRepr>("trilogy_atom_repr").call(id);
+            assert!(!repr.is_null(), "atom {id} is not registered");
+            string(&*repr)
+        }
+    }
+
+    unsafe fn function<F: UnsafeFunctionPointer>(&self, name: &str) -> JitFunction<'ctx, F> {
+        unsafe { function(self.ee, name) }
+    }
+}
+
+unsafe fn slice<'a, T>(contents: *const T, len: usize) -> &'a [T] {
+    if len == 0 {
+        return &[];
+    }
+    unsafe { std::slice::from_raw_parts(contents, len) }
+}
+
+unsafe fn string(value: &StringValue) -> String {
+    let bytes = unsafe { slice(value.contents as *const u8, value.len) };
+    String::from_utf8_lossy(bytes).into_owned()
+}
+
+unsafe fn bigint(value: &BigInteger) -> BigUint {
+    let digits = if value.capacity == 0 {
+        vec![value.contents as u32]
+    } else {
+        unsafe { slice(value.contents as *const u32, value.length) }.to_vec()
+    };
+    BigUint::new(digits)
+}
+
+unsafe fn rational(value: &Rational) -> BigRational {
+    let sign = if value.is_negative {
+        Sign::Minus
+    } else {
+        Sign::Plus
+    };
+    unsafe {
+        BigRational::new(
+            BigInt::from_biguint(sign, bigint(&value.numer)),
+            BigInt::from_biguint(Sign::Plus, bigint(&value.denom)),
+        )
+    }
+}
+
+/// Writes owned values into the runtime of a JIT compiled program, so that they may be
+/// passed to it.
+pub(crate) struct Writer<'a, 'ctx> {
+    ee: &'a ExecutionEngine<'ctx>,
+    atoms: &'a HashMap<String, u64>,
+    program: &'a Rc<()>,
+}
+
+impl<'a, 'ctx> Writer<'a, 'ctx> {
+    pub(crate) fn new(
+        ee: &'a ExecutionEngine<'ctx>,
+        atoms: &'a HashMap<String, u64>,
+        program: &'a Rc<()>,
+    ) -> Self {
+        Self { ee, atoms, program }
+    }
+
+    /// Converts a value into its runtime representation, which is then owned by the
+    /// program. Fails with the offending value if some part of it cannot be represented.
+    pub(crate) fn write(&self, value: &Value) -> Result<TrilogyValue, Box<Value>> {
+        let mut output = TrilogyValue::default();
+        unsafe {
+            match value {
+                Value::Unit => output.tag = TAG_UNIT,
+                Value::Bool(value) => {
+                    output.tag = TAG_BOOL;
+                    output.payload = *value as u64;
+                }
+                Value::Atom(atom) => {
+                    output.tag = TAG_ATOM;
+                    output.payload = self.atom(atom).ok_or_else(|| Box::new(value.clone()))?;
+                }
+                Value::Char(value) => {
+                    output.tag = TAG_CHAR;
+                    output.payload = *value as u64;
+                }
+                Value::String(string) => {
+                    self.function::<InitBytes<c_char>>("trilogy_string_init_new")
+                        .call(&mut output, string.len(), string.as_ptr() as *const c_char);
+                }
+                Value::Number(number) => {
+                    let init_number = self.function::<InitNumber>("trilogy_number_init_from_re_im");
+                    init_number.call(
+                        &mut output,
+                        self.rational(&number.re),
+                        self.rational(&number.im),
+                    );
+                }
+                Value::Bits(bits) => {
+                    self.function::<InitBytes<u8>>("trilogy_bits_init_new")
+                        .call(&mut output, bits.len(), bits.as_raw_slice().as_ptr());
+                }
+                Value::Struct(tag, contents) => {
+                    let atom = self.atom(tag).ok_or_else(|| Box::new(value.clone()))?;
+                    let mut contents = self.write(contents)?;
+                    self.function::<InitStruct>("trilogy_struct_init_take")
+                        .call(&mut output, atom, &mut contents);
+                }
+                Value::Tuple(fst, snd) => {
+                    let mut fst = self.write(fst)?;
+                    let mut snd = self.write(snd)?;
+                    self.function::<InitTuple>("trilogy_tuple_init_take").call(
+                        &mut output,
+                        &mut fst,
+                        &mut snd,
+                    );
+                }
+                Value::Array(elements) => {
+                    let array = self
+                        .function::<InitCap>("trilogy_array_init_cap")
+                        .call(&mut output, elements.len());
+                    let push = self.function::<Insert>("trilogy_array_push");
+                    for element in elements {
+                        push.call(array, &mut self.write(element)?);
+                    }
+                }
+                Value::Set(elements) => {
+                    let set = self
+                        .function::<InitCap>("trilogy_set_init_cap")
+                        .call(&mut output, elements.len());
+                    let insert = self.function::<Insert>("trilogy_set_insert");
+                    for element in elements {
+                        insert.call(set, &mut self.write(element)?);
+                    }
+                }
+                Value::Record(entries) => {
+                    let record = self
+                        .function::<InitCap>("trilogy_record_init_cap")
+                        .call(&mut output, entries.len());
+                    let insert = self.function::<InsertEntry>("trilogy_record_insert");
+                    for (key, value) in entries {
+                        insert.call(record, &mut self.write(key)?, &mut self.write(value)?);
+                    }
+                }
+                // Callables can only be passed back to the program they came from.
+                Value::Callable(callable)
+                    if Weak::ptr_eq(&callable.0.program, &Rc::downgrade(self.program)) =>
+                {
+                    self.function::<ValueClone>("trilogy_value_clone_into")
+                        .call(&mut output, &callable.0.value);
+                }
+                Value::Callable(..) | Value::Module => return Err(Box::new(value.clone())),
+            }
+        }
+        Ok(output)
+    }
+
+    fn atom(&self, atom: &str) -> Option<u64> {
+        self.atoms.get(atom).copied()
+    }
+
+    unsafe fn rational(&self, value: &BigRational) -> Rational {
+        let numer = value.numer().magnitude().to_u32_digits();
+        let denom = value.denom().magnitude().to_u32_digits();
+        let mut rational = MaybeUninit::uninit();
+        unsafe {
+            self.function::<InitRational>("rational_init_const").call(
+                rational.as_mut_ptr(),
+                value.numer().sign() == Sign::Minus,
+                numer.len(),
+                numer.as_ptr(),
+                denom.len(),
+                denom.as_ptr(),
+            );
+            rational.assume_init()
+        }
+    }
+
+    unsafe fn function<F: UnsafeFunctionPointer>(&self, name: &str) -> JitFunction<'ctx, F> {
+        unsafe { function(self.ee, name) }
+    }
+}
+
+unsafe fn function<'ctx, F: UnsafeFunctionPointer>(
+    ee: &ExecutionEngine<'ctx>,
+    name: &str,
+) -> JitFunction<'ctx, F> {
+    unsafe {
+        ee.get_function::<F>(name)
+            .unwrap_or_else(|_| panic!("runtime should define {name}"))
+    }
+}
@@ -103,7 +103,7 @@ pub enum BinaryOperator {
 }
 
 impl BinaryOperator {
-    fn parse(parser: &mut Parser) -> Self {
+    pub(crate) fn parse(parser: &mut Parser) -> Self {
         let token = parser.consume();
         match token.token_type {
             OpDot => Self::Access(token),
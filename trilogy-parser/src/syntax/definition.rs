@@ -8,6 +8,8 @@ use trilogy_scanner::TokenType::*;
 pub enum DefinitionItem {
     /// An inline module definition.
     Type(Box<TypeDefinition>),
+    /// A named type definition.
+    NamedType(Box<NamedTypeDefinition>),
     /// An external (imported) module definition.
     Import(Box<ImportDefinition>),
     /// A procedure definition.
@@ -73,15 +75,18 @@ impl Definition {
         let item = match token.token_type {
             KwType => {
                 let head = TypeHead::parse(parser)?;
-                if let Err(token) = parser.check(OBrace) {
+                if parser.check(KwIs).is_ok() {
+                    DefinitionItem::NamedType(Box::new(NamedTypeDefinition::parse(parser, head)?))
+                } else if let Err(token) = parser.check(OBrace) {
                     let error = SyntaxError::new(
                         token.span,
                         "only identifiers are permitted in a type definition",
                     );
                     parser.error(error.clone());
                     return Err(error);
+                } else {
+                    DefinitionItem::Type(Box::new(TypeDefinition::parse(parser, head)?))
                 }
-                DefinitionItem::Type(Box::new(TypeDefinition::parse(parser, head)?))
             }
             KwImport => DefinitionItem::Import(Box::new(ImportDefinition::parse(parser)?)),
             KwExport => DefinitionItem::Export(Box::new(ExportDefinition::parse(parser)?)),
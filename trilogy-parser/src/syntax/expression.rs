@@ -28,6 +28,7 @@ pub enum Expression {
     Unary(Box<UnaryOperation>),
     Let(Box<LetExpression>),
     IfElse(Box<IfElseExpression>),
+    For(Box<ForStatement>),
     Match(Box<MatchExpression>),
     Is(Box<IsExpression>),
     End(Box<EndExpression>),
@@ -44,6 +45,7 @@ pub enum Expression {
     Template(Box<Template>),
     Handled(Box<HandledExpression>),
     Parenthesized(Box<ParenthesizedExpression>),
+    Section(Box<OperatorSection>),
     ModuleAccess(Box<ModuleAccess>),
     Block(Box<Block>),
 }
@@ -137,6 +139,9 @@ impl Expression {
     ];
 
     fn binary(parser: &mut Parser, lhs: Expression) -> SyntaxResult<ExpressionResult> {
+        if OperatorSection::is_left_section_end(parser) {
+            return Ok(ExpressionResult::Done(lhs));
+        }
         let binary = BinaryOperation::parse(parser, lhs)?;
         match binary {
             Ok(binary) => Ok(ExpressionResult::Continue(Self::Binary(Box::new(binary)))),
@@ -364,6 +369,13 @@ impl Expression {
                 }
                 Ok(Ok(Self::IfElse(Box::new(expr))))
             }
+            KwFor => {
+                let expr = ForStatement::parse(parser)?;
+                if !expr.is_strict_expression() {
+                    parser.error(ErrorKind::ForExpressionRestriction.at(expr.span()));
+                }
+                Ok(Ok(Self::For(Box::new(expr))))
+            }
             KwMatch => Ok(Ok(Self::Match(Box::new(MatchExpression::parse(parser)?)))),
             KwEnd => Ok(Ok(Self::End(Box::new(EndExpression::parse(parser)?)))),
             KwExit => Ok(Ok(Self::Exit(Box::new(ExitExpression::parse(parser)?)))),
@@ -391,8 +403,11 @@ impl Expression {
             TemplateStart => Ok(Ok(Self::Template(Box::new(Template::parse_bare(parser)?)))),
             OParen => match KeywordReference::try_parse(parser) {
                 Some(keyword) => Ok(Ok(Self::Keyword(Box::new(keyword)))),
+                None if OperatorSection::is_right_section(parser) => Ok(Ok(Self::Section(
+                    Box::new(OperatorSection::parse_right(parser)?),
+                ))),
                 None => match ParenthesizedExpression::parse(parser)? {
-                    Ok(expression) => Ok(Ok(Self::Parenthesized(Box::new(expression)))),
+                    Ok(expression) => Ok(Ok(expression)),
                     Err(pattern) => Ok(Err(Pattern::Parenthesized(Box::new(pattern)))),
                 },
             },
@@ -715,6 +730,11 @@ mod test {
           })
     );
 
+    test_parse!(expr_for_where: "for x in xs where acc = 0 { acc + x }" => Expression::parse =>
+      Expression::For(ForStatement { accumulator: Some(..), .. })
+    );
+    test_parse_error!(expr_for_without_where: "for x in xs { x }" => Expression::parse => "a `for` expression must have a `where` clause");
+
     test_parse_error!(expr_eq_without_parens: "x == y == z" => Expression::parse => "equality operators cannot be chained, use parentheses to disambiguate");
     test_parse_error!(expr_cmp_without_parens: "x <= y <= z" => Expression::parse => "comparison operators cannot be chained, use parentheses to disambiguate");
     test_parse!(expr_prec_cmp_eq: "x < y == y > z" => Expression::parse =>
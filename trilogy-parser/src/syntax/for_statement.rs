@@ -7,6 +7,7 @@ use trilogy_scanner::{Token, TokenType};
 pub struct ForStatement {
     pub r#for: Token,
     pub query: Query,
+    pub accumulator: Option<WhereClause>,
     pub body: Block,
     pub span: Span,
 }
@@ -21,14 +22,59 @@ impl ForStatement {
     pub(crate) fn parse(parser: &mut Parser) -> SyntaxResult<Self> {
         let r#for = parser.expect(TokenType::KwFor).unwrap();
         let query = Query::parse(parser)?;
+        let accumulator = if parser.check(TokenType::KwWhere).is_ok() {
+            Some(WhereClause::parse(parser)?)
+        } else {
+            None
+        };
         let body = Block::parse(parser)?;
         Ok(Self {
             span: r#for.span.union(body.span()),
             r#for,
             query,
+            accumulator,
             body,
         })
     }
+
+    pub(crate) fn is_strict_expression(&self) -> bool {
+        self.accumulator.is_some()
+    }
+}
+
+/// The `where` clause of a `for` expression, naming the accumulator of the fold
+/// and providing its initial value.
+#[derive(Clone, Debug)]
+pub struct WhereClause {
+    pub r#where: Token,
+    pub identifier: Identifier,
+    pub eq: Token,
+    pub initial: Expression,
+    pub span: Span,
+}
+
+impl Spanned for WhereClause {
+    fn span(&self) -> Span {
+        self.span
+    }
+}
+
+impl WhereClause {
+    fn parse(parser: &mut Parser) -> SyntaxResult<Self> {
+        let r#where = parser.expect(TokenType::KwWhere).unwrap();
+        let identifier = Identifier::parse(parser)?;
+        let eq = parser
+            .expect(TokenType::OpEq)
+            .map_err(|token| parser.expected(token, "expected `=` in `where` clause"))?;
+        let initial = Expression::parse(parser)?;
+        Ok(Self {
+            span: r#where.span.union(initial.span()),
+            r#where,
+            identifier,
+            eq,
+            initial,
+        })
+    }
 }
 
 #[cfg(test)]
@@ -39,5 +85,9 @@ mod test {
     test_parse!(for_lookup: "for check(a, b, 3) {}" => ForStatement::parse => ForStatement { .. });
     test_parse!(for_body: "for check(a, b, 3) { break unit }" => ForStatement::parse => ForStatement { .. });
     test_parse_error!(for_query_expr: "for a + b { break }" => ForStatement::parse);
+    test_parse!(for_where: "for x in xs where acc = 0 { acc + x }" => ForStatement::parse => ForStatement { accumulator: Some(WhereClause { .. }), .. });
+    test_parse!(for_where_block_init: "for vals(x) where list = [] { [..list, x] }" => ForStatement::parse => ForStatement { accumulator: Some(WhereClause { initial: Expression::Array(..), .. }), .. });
+    test_parse_error!(for_where_missing_eq: "for x in xs where acc 0 { acc }" => ForStatement::parse);
+    test_parse_error!(for_where_missing_identifier: "for x in xs where = 0 { x }" => ForStatement::parse);
     test_parse_error!(for_body_expr: "for check(a, b) (a + b)" => ForStatement::parse);
 }
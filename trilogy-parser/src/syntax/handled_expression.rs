@@ -54,7 +54,7 @@ impl HandledExpression {
             }
         };
         Ok(Self {
-            span: with.span.union(handlers.last().unwrap().span()),
+            span: with.span.union(cbrace.span),
             with,
             expression,
             obrace,
@@ -57,17 +57,8 @@ impl MatchExpression {
             .expect(CBrace)
             .map_err(|token| parser.expected(token, "expected } to end `match` body"))?;
 
-        let span = match &else_case {
-            Some(case) => r#match.span.union(case.span()),
-            None => r#match.span.union(
-                cases
-                    .last()
-                    .map_or_else(|| expression.span(), Spanned::span),
-            ),
-        };
-
         Ok(Self {
-            span,
+            span: r#match.span.union(cbrace.span),
             r#match,
             expression,
             obrace,
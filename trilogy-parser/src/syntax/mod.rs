@@ -54,9 +54,11 @@ mod let_statement;
 mod lookup;
 mod match_expression;
 mod module_access;
+mod named_type_definition;
 mod negative_pattern;
 mod not_query;
 mod number_literal;
+mod operator_section;
 mod parameter_list;
 mod parenthesized_expression;
 mod parenthesized_pattern;
@@ -95,7 +97,9 @@ mod template;
 mod test_definition;
 mod tuple_pattern;
 mod type_definition;
+mod type_expression;
 mod type_head;
+mod type_pattern;
 mod type_use;
 mod typeof_pattern;
 mod unary_operation;
@@ -139,7 +143,7 @@ pub use export_definition::ExportDefinition;
 pub use expression::{Expression, FollowingExpression};
 pub use external_procedure_definition::ExternalProcedureDefinition;
 pub use fn_expression::FnExpression;
-pub use for_statement::ForStatement;
+pub use for_statement::{ForStatement, WhereClause};
 pub use function_assignment::FunctionAssignment;
 pub use function_definition::FunctionDefinition;
 pub use function_head::FunctionHead;
@@ -158,9 +162,11 @@ pub use let_statement::LetStatement;
 pub use lookup::Lookup;
 pub use match_expression::{MatchExpression, MatchExpressionCase};
 pub use module_access::ModuleAccess;
+pub use named_type_definition::{NamedTypeDefinition, TypeConstraint};
 pub use negative_pattern::NegativePattern;
 pub use not_query::NotQuery;
 pub use number_literal::NumberLiteral;
+pub use operator_section::{OperatorSection, SectionSide};
 pub use parameter_list::ParameterList;
 pub use parenthesized_expression::ParenthesizedExpression;
 pub use parenthesized_pattern::ParenthesizedPattern;
@@ -199,7 +205,9 @@ pub use template::{Template, TemplateSegment};
 pub use test_definition::TestDefinition;
 pub use tuple_pattern::TuplePattern;
 pub use type_definition::TypeDefinition;
+pub use type_expression::{ParenthesizedType, TypeApplication, TypeExpression};
 pub use type_head::TypeHead;
+pub use type_pattern::TypePattern;
 pub use type_use::TypeUse;
 pub use typeof_pattern::TypeofPattern;
 pub use unary_operation::{UnaryOperation, UnaryOperator};
@@ -0,0 +1,88 @@
+use super::*;
+use crate::{Parser, Spanned};
+use source_span::Span;
+use trilogy_scanner::{Token, TokenType::*};
+
+/// A named type, describing the values that match its pattern and satisfy its
+/// (optional) constraint.
+///
+/// ```trilogy
+/// type Positive is n where is n > 0
+/// type Pair a b is (_ and is a) : (_ and is b)
+/// ```
+#[derive(Clone, Debug)]
+pub struct NamedTypeDefinition {
+    pub head: TypeHead,
+    pub is: Token,
+    pub pattern: Pattern,
+    pub constraint: Option<TypeConstraint>,
+    pub span: Span,
+}
+
+impl Spanned for NamedTypeDefinition {
+    fn span(&self) -> Span {
+        self.span
+    }
+}
+
+impl NamedTypeDefinition {
+    pub(crate) fn parse(parser: &mut Parser, head: TypeHead) -> SyntaxResult<Self> {
+        let is = parser.expect(KwIs).unwrap();
+        let pattern = Pattern::parse(parser)?;
+        let constraint = if parser.check(KwWhere).is_ok() {
+            Some(TypeConstraint::parse(parser)?)
+        } else {
+            None
+        };
+        let span = match &constraint {
+            Some(constraint) => head.span().union(constraint.span()),
+            None => head.span().union(pattern.span()),
+        };
+        Ok(Self {
+            span,
+            head,
+            is,
+            pattern,
+            constraint,
+        })
+    }
+}
+
+/// The `where` clause of a named type, a query which must succeed for a value
+/// to be of that type.
+#[derive(Clone, Debug)]
+pub struct TypeConstraint {
+    pub r#where: Token,
+    pub query: Query,
+    pub span: Span,
+}
+
+impl Spanned for TypeConstraint {
+    fn span(&self) -> Span {
+        self.span
+    }
+}
+
+impl TypeConstraint {
+    fn parse(parser: &mut Parser) -> SyntaxResult<Self> {
+        let r#where = parser.expect(KwWhere).unwrap();
+        let query = Query::parse(parser)?;
+        Ok(Self {
+            span: r#where.span.union(query.span()),
+            r#where,
+            query,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    test_parse!(named_type: "type Name is _ and typeof 'string" => Definition::parse_in_document => Some(Definition { item: DefinitionItem::NamedType(NamedTypeDefinition { constraint: None, .. }), .. }));
+    test_parse!(named_type_where: "type Positive is n where is n > 0" => Definition::parse_in_document => Some(Definition { item: DefinitionItem::NamedType(NamedTypeDefinition { constraint: Some(..), .. }), .. }));
+    test_parse!(named_type_parameters: "type Pair a b is (_ and is a) : (_ and is b)" => Definition::parse_in_document => Some(Definition { item: DefinitionItem::NamedType(NamedTypeDefinition { head: TypeHead { parameters: [_, _], .. }, pattern: Pattern::Tuple(..), .. }), .. }));
+    test_parse!(named_type_in_module: "type Name is _ and typeof 'string" => Definition::parse_in_module => Some(Definition { item: DefinitionItem::NamedType(..), .. }));
+    test_parse_error!(named_type_missing_pattern: "type Name is" => Definition::parse_in_document);
+    test_parse_error!(named_type_missing_query: "type Name is n where" => Definition::parse_in_document);
+}
@@ -0,0 +1,150 @@
+use super::*;
+use crate::{Parser, Spanned, token_pattern::TokenPattern};
+use source_span::Span;
+use trilogy_scanner::{
+    Token,
+    TokenType::{self, *},
+};
+
+/// A binary operator partially applied to one of its operands.
+///
+/// ```trilogy
+/// (lhs +)
+/// (+ rhs)
+/// ```
+///
+/// The operator `-` cannot begin a section, as `(- rhs)` is a negation, and neither can
+/// `^`, as `(^ rhs)` is a pinned pattern. The operand of a left section may not be an
+/// unparenthesized binary operation, as `(a + b *)` would mix precedence levels; it must
+/// be written `((a + b) *)`.
+#[derive(Clone, Debug)]
+pub struct OperatorSection {
+    pub open_paren: Token,
+    pub operand: Expression,
+    pub operator: BinaryOperator,
+    pub side: SectionSide,
+    pub close_paren: Token,
+    pub span: Span,
+}
+
+/// The side of the operator on which the operand of an operator section is written.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum SectionSide {
+    Left,
+    Right,
+}
+
+impl Spanned for OperatorSection {
+    fn span(&self) -> Span {
+        self.span
+    }
+}
+
+impl OperatorSection {
+    const RIGHT: [TokenType; 31] = [
+        OpDot,
+        OpAmpAmp,
+        OpPipePipe,
+        OpPlus,
+        OpStar,
+        OpSlash,
+        OpSlashSlash,
+        OpPercent,
+        OpStarStar,
+        OpEqEq,
+        OpBangEq,
+        OpEqEqEq,
+        OpBangEqEq,
+        OpLt,
+        OpGt,
+        OpLtEq,
+        OpGtEq,
+        OpAmp,
+        OpPipe,
+        OpShl,
+        OpShr,
+        OpShlEx,
+        OpShrEx,
+        OpShlCon,
+        OpShrCon,
+        OpColon,
+        OpGlue,
+        OpLtLt,
+        OpGtGt,
+        OpPipeGt,
+        OpLtPipe,
+    ];
+
+    /// Whether the upcoming tokens begin a right section, such as `(< 3)`.
+    pub(crate) fn is_right_section(parser: &mut Parser) -> bool {
+        parser
+            .peekn(2)
+            .is_some_and(|tokens| tokens[0].token_type == OParen && Self::RIGHT.matches(&tokens[1]))
+    }
+
+    /// Whether the upcoming tokens are a binary operator followed immediately by `)`,
+    /// making them the end of a left section, such as `(3 <)`.
+    pub(crate) fn is_left_section_end(parser: &mut Parser) -> bool {
+        parser.peekn(2).is_some_and(|tokens| {
+            (Self::RIGHT.matches(&tokens[0]) || [OpMinus, OpCaret].matches(&tokens[0]))
+                && tokens[1].token_type == CParen
+        })
+    }
+
+    pub(crate) fn parse_right(parser: &mut Parser) -> SyntaxResult<Self> {
+        let open_paren = parser.expect(OParen).unwrap();
+        let operator = BinaryOperator::parse(parser);
+        let operand = Expression::parse(parser)?;
+        let close_paren = parser
+            .expect(CParen)
+            .map_err(|token| parser.expected(token, "expected `)` to end operator section"))?;
+        Ok(Self {
+            span: open_paren.span.union(close_paren.span),
+            open_paren,
+            operand,
+            operator,
+            side: SectionSide::Right,
+            close_paren,
+        })
+    }
+
+    pub(crate) fn parse_left(
+        parser: &mut Parser,
+        open_paren: Token,
+        operand: Expression,
+    ) -> SyntaxResult<Self> {
+        let operator = BinaryOperator::parse(parser);
+        let close_paren = parser.expect(CParen).unwrap();
+        if let Expression::Binary(binary) = &operand {
+            parser.error(SyntaxError::new(
+                binary.span(),
+                "the operand of a left operator section must be parenthesized when it is a binary operation",
+            ));
+        }
+        Ok(Self {
+            span: open_paren.span.union(close_paren.span),
+            open_paren,
+            operand,
+            operator,
+            side: SectionSide::Left,
+            close_paren,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    test_parse!(section_right: "(< 3)" => Expression::parse => Expression::Section(OperatorSection { operator: BinaryOperator::Lt(..), side: SectionSide::Right, .. }));
+    test_parse!(section_left: "(4 :)" => Expression::parse => Expression::Section(OperatorSection { operator: BinaryOperator::Cons(..), side: SectionSide::Left, .. }));
+    test_parse!(section_left_minus: "(10 -)" => Expression::parse => Expression::Section(OperatorSection { operator: BinaryOperator::Subtract(..), side: SectionSide::Left, .. }));
+    test_parse_error!(section_left_binary: "(a + b *)" => Expression::parse => "the operand of a left operator section must be parenthesized when it is a binary operation");
+    test_parse!(section_left_parenthesized_binary: "((a + b) *)" => Expression::parse => Expression::Section(OperatorSection { operand: Expression::Parenthesized(..), operator: BinaryOperator::Multiply(..), side: SectionSide::Left, .. }));
+    test_parse!(section_left_application: "(f x +)" => Expression::parse => Expression::Section(OperatorSection { operand: Expression::Application(..), operator: BinaryOperator::Add(..), side: SectionSide::Left, .. }));
+    test_parse!(section_right_binary: "(<> a <> b)" => Expression::parse => Expression::Section(OperatorSection { operand: Expression::Binary(..), operator: BinaryOperator::Glue(..), .. }));
+    test_parse!(section_argument: "filter (< 3) xs" => Expression::parse => Expression::Application(Application { function: Expression::Application(Application { argument: Expression::Section(..), .. }), .. }));
+    test_parse!(section_not_negate: "(- 3)" => Expression::parse => Expression::Parenthesized(ParenthesizedExpression { expression: Expression::Unary(..), .. }));
+    test_parse!(section_not_keyword: "(<)" => Expression::parse => Expression::Keyword(..));
+    test_parse_error!(section_unclosed: "(< 3" => Expression::parse);
+}
@@ -18,22 +18,32 @@ impl Spanned for ParenthesizedExpression {
 }
 
 impl ParenthesizedExpression {
-    pub(crate) fn parse(parser: &mut Parser) -> SyntaxResult<Result<Self, ParenthesizedPattern>> {
+    /// Parses a parenthesized expression, or a left operator section, which cannot be
+    /// distinguished until the operator is found after the operand.
+    pub(crate) fn parse(
+        parser: &mut Parser,
+    ) -> SyntaxResult<Result<Expression, ParenthesizedPattern>> {
         let open_paren = parser
             .expect(OParen)
             .map_err(|token| parser.expected(token, "expected `(`"))?;
-        let expression = Expression::parse_or_pattern(parser)?;
+        let expression = match Expression::parse_or_pattern(parser)? {
+            Ok(operand) if OperatorSection::is_left_section_end(parser) => {
+                let section = OperatorSection::parse_left(parser, open_paren, operand)?;
+                return Ok(Ok(Expression::Section(Box::new(section))));
+            }
+            expression => expression,
+        };
         let close_paren = parser
             .expect(CParen)
             .map_err(|token| parser.expected(token, "expected `)`"))?;
         let span = open_paren.span.union(close_paren.span);
         Ok(match expression {
-            Ok(expression) => Ok(Self {
+            Ok(expression) => Ok(Expression::Parenthesized(Box::new(Self {
                 span,
                 open_paren,
                 expression,
                 close_paren,
-            }),
+            }))),
             Err(pattern) => Err(ParenthesizedPattern {
                 span,
                 open_paren,
@@ -20,6 +20,7 @@ pub enum Pattern {
     Negative(Box<NegativePattern>),
     Glue(Box<GluePattern>),
     Typeof(Box<TypeofPattern>),
+    Type(Box<TypePattern>),
     Struct(Box<StructPattern>),
     Tuple(Box<TuplePattern>),
     Array(Box<ArrayPattern>),
@@ -91,6 +92,7 @@ impl Pattern {
             )?))),
             OpMinus => Ok(Self::Negative(Box::new(NegativePattern::parse(parser)?))),
             KwTypeof => Ok(Self::Typeof(Box::new(TypeofPattern::parse(parser)?))),
+            KwIs => Ok(Self::Type(Box::new(TypePattern::parse(parser)?))),
             OpCaret => Ok(Self::Pinned(Box::new(PinnedPattern::parse(parser)?))),
             OBrack => Ok(Self::Array(Box::new(ArrayPattern::parse(parser)?))),
             OBrackPipe => Ok(Self::Set(Box::new(SetPattern::parse(parser)?))),
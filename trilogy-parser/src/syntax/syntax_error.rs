@@ -10,6 +10,7 @@ pub enum ErrorKind {
     MatchStatementExpressionCase,
     TripleDot { dot: Span },
     IfExpressionRestriction,
+    ForExpressionRestriction,
     TaggedTemplateMissingIdentifier,
     TaggedTemplateNotIdentifier,
     DoMissingParameterList,
@@ -74,6 +75,9 @@ impl Display for SyntaxError {
             ErrorKind::IfExpressionRestriction => {
                 write!(f, "an `if` expression must have an `else` clause")?
             }
+            ErrorKind::ForExpressionRestriction => {
+                write!(f, "a `for` expression must have a `where` clause")?
+            }
             ErrorKind::TaggedTemplateMissingIdentifier => write!(
                 f,
                 "the $ operator prefixing a tagged template requires a tag identifier"
@@ -0,0 +1,106 @@
+use super::*;
+use crate::{Parser, Spanned};
+use source_span::Span;
+use trilogy_scanner::{Token, TokenType};
+
+/// A reference to a named type, possibly applied to type arguments.
+///
+/// ```trilogy
+/// List (Pair a b)
+/// ```
+#[derive(Clone, Debug, Spanned)]
+pub enum TypeExpression {
+    Reference(Box<Identifier>),
+    Application(Box<TypeApplication>),
+    Parenthesized(Box<ParenthesizedType>),
+}
+
+impl TypeExpression {
+    fn parse_primary(parser: &mut Parser) -> SyntaxResult<Self> {
+        if parser.check(TokenType::OParen).is_ok() {
+            Ok(Self::Parenthesized(Box::new(ParenthesizedType::parse(
+                parser,
+            )?)))
+        } else {
+            Ok(Self::Reference(Box::new(Identifier::parse(parser)?)))
+        }
+    }
+
+    pub(crate) fn parse(parser: &mut Parser) -> SyntaxResult<Self> {
+        let mut r#type = Self::parse_primary(parser)?;
+        loop {
+            // As with function application, a type application never spans
+            // across two lines.
+            parser.peek();
+            if parser.is_line_start
+                || parser
+                    .check([TokenType::Identifier, TokenType::OParen])
+                    .is_err()
+            {
+                return Ok(r#type);
+            }
+            let argument = Self::parse_primary(parser)?;
+            r#type = Self::Application(Box::new(TypeApplication {
+                span: r#type.span().union(argument.span()),
+                function: r#type,
+                argument,
+            }));
+        }
+    }
+}
+
+/// A type applied to a type argument.
+#[derive(Clone, Debug)]
+pub struct TypeApplication {
+    pub function: TypeExpression,
+    pub argument: TypeExpression,
+    pub span: Span,
+}
+
+impl Spanned for TypeApplication {
+    fn span(&self) -> Span {
+        self.span
+    }
+}
+
+/// A type in parentheses.
+#[derive(Clone, Debug)]
+pub struct ParenthesizedType {
+    pub open_paren: Token,
+    pub r#type: TypeExpression,
+    pub close_paren: Token,
+    pub span: Span,
+}
+
+impl Spanned for ParenthesizedType {
+    fn span(&self) -> Span {
+        self.span
+    }
+}
+
+impl ParenthesizedType {
+    fn parse(parser: &mut Parser) -> SyntaxResult<Self> {
+        let open_paren = parser.expect(TokenType::OParen).unwrap();
+        let r#type = TypeExpression::parse(parser)?;
+        let close_paren = parser
+            .expect(TokenType::CParen)
+            .map_err(|token| parser.expected(token, "expected `)` to end parenthesized type"))?;
+        Ok(Self {
+            span: open_paren.span.union(close_paren.span),
+            open_paren,
+            r#type,
+            close_paren,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    test_parse!(type_reference: "Int" => TypeExpression::parse => TypeExpression::Reference(..));
+    test_parse!(type_application: "Pair a b" => TypeExpression::parse => TypeExpression::Application(TypeApplication { function: TypeExpression::Application(..), argument: TypeExpression::Reference(..), .. }));
+    test_parse!(type_parenthesized: "List (Pair a b)" => TypeExpression::parse => TypeExpression::Application(TypeApplication { argument: TypeExpression::Parenthesized(..), .. }));
+    test_parse_error!(type_unclosed: "List (Pair a" => TypeExpression::parse);
+    test_parse_error!(type_not_identifier: "'atom" => TypeExpression::parse);
+}
@@ -0,0 +1,48 @@
+use super::*;
+use crate::{Parser, Spanned};
+use source_span::Span;
+use trilogy_scanner::{Token, TokenType::*};
+
+/// A pattern matching values that are of a named type.
+///
+/// ```trilogy
+/// is List Int
+/// ```
+#[derive(Clone, Debug)]
+pub struct TypePattern {
+    pub is: Token,
+    pub r#type: TypeExpression,
+    pub span: Span,
+}
+
+impl Spanned for TypePattern {
+    fn span(&self) -> Span {
+        self.span
+    }
+}
+
+impl TypePattern {
+    pub(crate) fn parse(parser: &mut Parser) -> SyntaxResult<Self> {
+        let is = parser.expect(KwIs).unwrap();
+        let r#type = TypeExpression::parse(parser)?;
+        Ok(Self {
+            span: is.span.union(r#type.span()),
+            is,
+            r#type,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    test_parse!(type_pattern: "is Int" => Pattern::parse => Pattern::Type(TypePattern { r#type: TypeExpression::Reference(..), .. }));
+    test_parse!(type_pattern_applied: "is List Int" => Pattern::parse => Pattern::Type(TypePattern { r#type: TypeExpression::Application(..), .. }));
+    test_parse!(type_pattern_then_conjunction: "is List a and x" => Pattern::parse => Pattern::Conjunction(PatternConjunction { lhs: Pattern::Type(..), .. }));
+    test_parse!(type_pattern_conjunction: "x and is Int" => Pattern::parse => Pattern::Conjunction(PatternConjunction { rhs: Pattern::Type(..), .. }));
+    test_parse!(type_pattern_in_array: "[is Int, x]" => Pattern::parse => Pattern::Array(..));
+    test_parse!(type_pattern_let: "let x and is Int = 3" => Statement::parse => Statement::Let(..));
+    test_parse!(type_pattern_func: "func f (x and is Int) = x" => Definition::parse_in_document => Some(Definition { item: DefinitionItem::Function(..), .. }));
+    test_parse_error!(type_pattern_missing_type: "is 3" => Pattern::parse);
+}
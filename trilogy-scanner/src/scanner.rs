@@ -207,7 +207,8 @@ impl<'a> Scanner<'a> {
         if !is_identifier(ch) {
             return self.make_error("Invalid character in atom literal");
         }
-        self.make_token(Atom).with_value(self.identifier(ch.into()))
+        let value = self.identifier(ch.into());
+        self.make_token(Atom).with_value(value)
     }
 
     fn block_comment(&mut self) -> Token {
@@ -255,13 +256,20 @@ impl<'a> Scanner<'a> {
     fn comment(&mut self) -> Token {
         match self.expect("#-!") {
             Some('-') => self.block_comment(),
-            Some('#') => self.make_token(DocOuter).with_value(self.finish_line()),
-            Some('!') => self.make_token(DocInner).with_value(self.finish_line()),
-            None => self.make_token(CommentLine).with_value(self.finish_line()),
+            Some('#') => self.line_comment(DocOuter),
+            Some('!') => self.line_comment(DocInner),
+            None => self.line_comment(CommentLine),
             _ => unreachable!(),
         }
     }
 
+    fn line_comment(&mut self, token_type: TokenType) -> Token {
+        // The line must be consumed before making the token so that its span
+        // covers the whole comment.
+        let line = self.finish_line();
+        self.make_token(token_type).with_value(line)
+    }
+
     fn string_or_template(&mut self, on_continue: TokenType, on_end: TokenType) -> Token {
         let mut content = String::new();
         while let Some(mut ch) = self.consume() {
@@ -127,6 +127,22 @@ fn spans_accurate() {
     );
 }
 
+#[test]
+fn spans_cover_atoms_and_comments() {
+    let spans = Scanner::new("'hello # comment\n## docs")
+        .filter(|token| matches!(token.token_type, Atom | CommentLine | DocOuter))
+        .map(|token| (token.span.start(), token.span.end()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        spans,
+        [
+            (Position::new(0, 0), Position::new(0, 6)),
+            (Position::new(0, 7), Position::new(1, 0)),
+            (Position::new(1, 0), Position::new(1, 7)),
+        ]
+    );
+}
+
 macro_rules! test_tokenize {
     ($name:ident => $src:literal = [$($tok:ident)+]) => {
         #[test]
@@ -0,0 +1,77 @@
+use serde_json::{Value, json};
+use std::ops::Range;
+use trilogy::{ColorChoice, Diagnostic, Label, Location, Report, Severity};
+
+/// How diagnostics are printed.
+#[derive(clap::ValueEnum, Copy, Clone, Debug, Default)]
+pub(crate) enum MessageFormat {
+    /// Human readable reports, printed to standard error.
+    #[default]
+    Human,
+    /// One JSON object per diagnostic, each on its own line, printed to standard output.
+    Json,
+}
+
+pub(crate) fn print<E: std::error::Error + 'static>(
+    report: &Report<E>,
+    format: MessageFormat,
+    color: ColorChoice,
+) {
+    match format {
+        MessageFormat::Human => eprint(report, color),
+        MessageFormat::Json => {
+            for diagnostic in report.diagnostics() {
+                println!("{}", to_json(&diagnostic));
+            }
+        }
+    }
+}
+
+pub(crate) fn eprint<E: std::error::Error + 'static>(report: &Report<E>, color: ColorChoice) {
+    // NOTE: there is nowhere left to report a failure to write to standard error.
+    _ = report.write_to(&mut std::io::stderr(), color);
+}
+
+fn to_json(diagnostic: &Diagnostic) -> Value {
+    let severity = match diagnostic.severity() {
+        Severity::Error => "error",
+        Severity::Warning => "warning",
+    };
+    let span = diagnostic
+        .location()
+        .map(|_| span(diagnostic.span(), diagnostic.byte_range()));
+    json!({
+        "severity": severity,
+        "code": diagnostic.code(),
+        "message": diagnostic.message(),
+        "location": diagnostic.location().map(Location::to_string),
+        "span": span,
+        "labels": diagnostic.labels().iter().map(label).collect::<Vec<_>>(),
+        "notes": diagnostic.notes(),
+        "help": diagnostic.help(),
+    })
+}
+
+fn label(label: &Label) -> Value {
+    json!({
+        "location": label.location().to_string(),
+        "span": span(label.span(), label.byte_range()),
+        "message": label.message(),
+    })
+}
+
+/// Lines and columns are counted from 0, as in the compiler's own spans.
+fn span(span: source_span::Span, offsets: Option<Range<usize>>) -> Value {
+    json!({
+        "start": {
+            "line": span.start().line,
+            "column": span.start().column,
+            "offset": offsets.as_ref().map(|offsets| offsets.start),
+        },
+        "end": {
+            "line": span.end().line,
+            "column": span.end().column,
+            "offset": offsets.as_ref().map(|offsets| offsets.end),
+        },
+    })
+}
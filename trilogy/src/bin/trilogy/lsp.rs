@@ -0,0 +1,296 @@
+use home::home_dir;
+use lsp_server::{Connection, ErrorCode, Message, Notification, Response};
+use lsp_types::notification::{
+    DidChangeTextDocument, DidCloseTextDocument, DidOpenTextDocument, DidSaveTextDocument,
+    Notification as _, PublishDiagnostics,
+};
+use lsp_types::{
+    DiagnosticRelatedInformation, DiagnosticSeverity, DidChangeTextDocumentParams,
+    DidCloseTextDocumentParams, DidOpenTextDocumentParams, PublishDiagnosticsParams, Range,
+    ServerCapabilities, TextDocumentSyncCapability, TextDocumentSyncKind, Url,
+};
+use std::collections::{HashMap, HashSet};
+use std::io;
+use std::ops::Range as Offsets;
+use std::path::PathBuf;
+use std::rc::Rc;
+use trilogy::{Builder, Cache, Diagnostic, FileSystemCache, Location, LockfileMode, Severity};
+
+type Error = Box<dyn std::error::Error + Send + Sync>;
+
+/// Runs the language server over standard input and output until the client
+/// shuts it down.
+///
+/// The provided files are checked as programs, requiring a `main!()` procedure.
+/// Any other documents opened by the client are checked as libraries.
+pub fn run(files: Vec<PathBuf>) -> Result<(), Error> {
+    let home = home_dir().ok_or("the home directory could not be found")?;
+    let cache = Rc::new(FileSystemCache::new(home.join(".trilogy/cache"))?);
+    let (connection, io_threads) = Connection::stdio();
+    let capabilities = serde_json::to_value(ServerCapabilities {
+        text_document_sync: Some(TextDocumentSyncCapability::Kind(TextDocumentSyncKind::FULL)),
+        ..ServerCapabilities::default()
+    })?;
+    connection.initialize(capabilities)?;
+
+    let root_dir = std::env::current_dir()?;
+    let mut server = Server {
+        entrypoints: files.into_iter().map(|file| root_dir.join(file)).collect(),
+        documents: HashMap::new(),
+        published: HashSet::new(),
+        cache,
+    };
+    server.check(&connection)?;
+
+    for message in &connection.receiver {
+        match message {
+            Message::Request(request) => {
+                if connection.handle_shutdown(&request)? {
+                    break;
+                }
+                connection.sender.send(
+                    Response::new_err(
+                        request.id,
+                        ErrorCode::MethodNotFound as i32,
+                        format!("unsupported request {}", request.method),
+                    )
+                    .into(),
+                )?;
+            }
+            Message::Notification(notification) => {
+                if server.notify(notification)? {
+                    server.check(&connection)?;
+                }
+            }
+            Message::Response(..) => {}
+        }
+    }
+
+    io_threads.join()?;
+    Ok(())
+}
+
+struct Server {
+    entrypoints: Vec<PathBuf>,
+    /// The current contents of the documents the client has open, which may differ
+    /// from what is saved on disk.
+    documents: HashMap<Location, String>,
+    /// The documents that had diagnostics published for them on the last check, which
+    /// must be cleared if they no longer have any.
+    published: HashSet<Url>,
+    /// The cache of modules downloaded from the Internet.
+    cache: Rc<FileSystemCache>,
+}
+
+impl Server {
+    /// Handles a notification from the client, returning whether the program
+    /// should be checked again.
+    fn notify(&mut self, notification: Notification) -> Result<bool, Error> {
+        match notification.method.as_str() {
+            DidOpenTextDocument::METHOD => {
+                let params: DidOpenTextDocumentParams =
+                    serde_json::from_value(notification.params)?;
+                self.documents.insert(
+                    Location::from(params.text_document.uri),
+                    params.text_document.text,
+                );
+            }
+            DidChangeTextDocument::METHOD => {
+                let params: DidChangeTextDocumentParams =
+                    serde_json::from_value(notification.params)?;
+                // Using full document sync, so the last change is the whole document.
+                if let Some(change) = params.content_changes.into_iter().last() {
+                    self.documents
+                        .insert(Location::from(params.text_document.uri), change.text);
+                }
+            }
+            DidCloseTextDocument::METHOD => {
+                let params: DidCloseTextDocumentParams =
+                    serde_json::from_value(notification.params)?;
+                self.documents
+                    .remove(&Location::from(params.text_document.uri));
+            }
+            // Other modules may have changed on disk.
+            DidSaveTextDocument::METHOD => {}
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }
+
+    /// Checks every entrypoint and open document, publishing all of their diagnostics.
+    fn check(&mut self, connection: &Connection) -> Result<(), Error> {
+        let mut roots: Vec<(PathBuf, bool)> = self
+            .entrypoints
+            .iter()
+            .map(|path| (path.clone(), false))
+            .collect();
+        for location in self.documents.keys() {
+            if let Some(path) = location.to_local_path()
+                && !self.entrypoints.contains(&path)
+            {
+                roots.push((path, true));
+            }
+        }
+
+        let mut diagnostics: HashMap<Url, Vec<lsp_types::Diagnostic>> = HashMap::new();
+        let mut sources = Sources {
+            documents: &self.documents,
+            files: HashMap::new(),
+        };
+        for (path, is_library) in roots {
+            let cache = Documents {
+                documents: self.documents.clone(),
+                cache: self.cache.clone(),
+            };
+            let Err(report) = Builder::std()
+                .with_cache(cache)
+                .lockfile(LockfileMode::Check)
+                .is_library(is_library)
+                .build_from_source(&path)
+            else {
+                continue;
+            };
+            let root = Location::from(Url::from_file_path(&path).map_err(|()| "invalid path")?);
+            for diagnostic in report.diagnostics() {
+                // Diagnostics that are not attached to a module are reported on the root.
+                let location = diagnostic.location().unwrap_or(&root);
+                if !location.is_local() {
+                    continue;
+                }
+                let converted = convert(&diagnostic, &mut sources);
+                let list = diagnostics.entry(location.as_ref().clone()).or_default();
+                if !list.contains(&converted) {
+                    list.push(converted);
+                }
+            }
+        }
+
+        let published: HashSet<Url> = diagnostics.keys().cloned().collect();
+        for uri in self.published.difference(&published) {
+            diagnostics.entry(uri.clone()).or_default();
+        }
+        for (uri, diagnostics) in diagnostics {
+            let params = PublishDiagnosticsParams::new(uri, diagnostics, None);
+            connection
+                .sender
+                .send(Notification::new(PublishDiagnostics::METHOD.to_owned(), params).into())?;
+        }
+        self.published = published;
+        Ok(())
+    }
+}
+
+/// A module cache that loads the open documents in place of the files on disk.
+struct Documents {
+    documents: HashMap<Location, String>,
+    cache: Rc<FileSystemCache>,
+}
+
+impl Cache for Documents {
+    type Error = io::Error;
+
+    fn has(&self, location: &Location) -> bool {
+        self.documents.contains_key(location) || self.cache.has(location)
+    }
+
+    fn load(&self, location: &Location) -> Result<String, Self::Error> {
+        match self.documents.get(location) {
+            Some(source) => Ok(source.clone()),
+            None => self.cache.load(location),
+        }
+    }
+
+    fn save(&self, location: &Location, source: &str) -> Result<(), Self::Error> {
+        self.cache.save(location, source)
+    }
+}
+
+/// The source text of modules, used to convert positions to the client's encoding.
+struct Sources<'a> {
+    documents: &'a HashMap<Location, String>,
+    files: HashMap<Location, Option<String>>,
+}
+
+impl Sources<'_> {
+    fn get(&mut self, location: &Location) -> Option<&str> {
+        if let Some(source) = self.documents.get(location) {
+            return Some(source);
+        }
+        self.files
+            .entry(location.clone())
+            .or_insert_with(|| {
+                location
+                    .to_local_path()
+                    .and_then(|path| std::fs::read_to_string(path).ok())
+            })
+            .as_deref()
+    }
+
+    /// Converts a span to an LSP range, which counts UTF-16 code units, using its byte
+    /// offsets when they are known.
+    fn range(
+        &mut self,
+        location: &Location,
+        span: source_span::Span,
+        offsets: Option<Offsets<usize>>,
+    ) -> Range {
+        match (self.get(location), offsets) {
+            (Some(source), Some(offsets)) => Range::new(
+                position(source, offsets.start),
+                position(source, offsets.end),
+            ),
+            _ => Range::new(
+                lsp_types::Position::new(span.start().line as u32, span.start().column as u32),
+                lsp_types::Position::new(span.end().line as u32, span.end().column as u32),
+            ),
+        }
+    }
+}
+
+/// Converts a byte offset to an LSP position.
+fn position(source: &str, offset: usize) -> lsp_types::Position {
+    let before = source.get(..offset).unwrap_or(source);
+    let line_start = before.rfind('\n').map_or(0, |index| index + 1);
+    let line = before.matches('\n').count();
+    let character: usize = before[line_start..].chars().map(char::len_utf16).sum();
+    lsp_types::Position::new(line as u32, character as u32)
+}
+
+fn convert(diagnostic: &Diagnostic, sources: &mut Sources) -> lsp_types::Diagnostic {
+    let range = match diagnostic.location() {
+        Some(location) => sources.range(location, diagnostic.span(), diagnostic.byte_range()),
+        None => Range::default(),
+    };
+    let severity = match diagnostic.severity() {
+        Severity::Error => DiagnosticSeverity::ERROR,
+        Severity::Warning => DiagnosticSeverity::WARNING,
+    };
+    let mut message = diagnostic.message().to_owned();
+    for note in diagnostic.notes() {
+        message.push_str(&format!("\nnote: {note}"));
+    }
+    if let Some(help) = diagnostic.help() {
+        message.push_str(&format!("\nhelp: {help}"));
+    }
+    let related_information = diagnostic
+        .labels()
+        .iter()
+        .filter_map(|label| {
+            Some(DiagnosticRelatedInformation {
+                location: lsp_types::Location::new(
+                    label.location().as_ref().clone(),
+                    sources.range(label.location(), label.span(), label.byte_range()),
+                ),
+                message: label.message()?.to_owned(),
+            })
+        })
+        .collect::<Vec<_>>();
+    lsp_types::Diagnostic {
+        range,
+        severity: Some(severity),
+        source: Some("trilogy".to_owned()),
+        message,
+        related_information: (!related_information.is_empty()).then_some(related_information),
+        ..lsp_types::Diagnostic::default()
+    }
+}
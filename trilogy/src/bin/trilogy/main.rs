@@ -1,12 +1,22 @@
 use clap::Parser as _;
+use std::io::Read as _;
 use std::path::PathBuf;
-use trilogy::{Builder, Trilogy};
+use trilogy::{Builder, ColorChoice, FileSystemCache, LockfileMode, Report, Termination, Trilogy};
+
+mod check;
+mod lsp;
 
 /// Trilogy Programming Language
 #[derive(clap::Parser, Clone, Debug)]
 struct Cli {
     #[command(subcommand)]
     command: Command,
+    /// When to use color when printing errors and warnings.
+    #[arg(long, global = true, value_enum, default_value_t = clap::ColorChoice::Auto)]
+    color: clap::ColorChoice,
+    /// Never download remote modules; they must already be cached or vendored.
+    #[arg(long, global = true)]
+    offline: bool,
 }
 
 #[derive(clap::Subcommand, Clone, Debug)]
@@ -14,22 +24,30 @@ enum Command {
     /// Run a Trilogy program.
     Run {
         /// The path to the Trilogy source file containing the `main!()` procedure.
-        file: PathBuf,
-        /// Run without including the standard library.
-        #[arg(short = 'S', long)]
-        /// Print the exit value instead of using it as the exit code.
+        ///
+        /// Defaults to the entrypoint declared in the project's `trilogy.toml`.
+        file: Option<PathBuf>,
+        /// Print the value returned from `main!()`, or the exit code if the program exits,
+        /// before exiting with it.
         #[arg(short, long)]
         print: bool,
-        /// Print the debug trace instead of the regular stack trace on error.
-        #[arg(long)]
+        /// No longer has any effect, but is still accepted so existing scripts keep working.
+        #[arg(long, hide = true)]
         debug: bool,
+        /// Arguments to pass to the program, available from `args!()` in `trilogy:env`.
+        #[arg(last = true)]
+        args: Vec<String>,
     },
     /// Compile a Trilogy program, printing the ASM it compiles to.
     /// Redirect to a file is recommended.
     ///
-    /// Expects a single path in which the `main!()` procedure is found.
+    /// Expects a single path in which the `main!()` procedure is found, unless
+    /// the project's `trilogy.toml` declares one.
     Compile {
-        file: PathBuf,
+        /// The path to the Trilogy source file containing the `main!()` procedure.
+        ///
+        /// Defaults to the entrypoint declared in the project's `trilogy.toml`.
+        file: Option<PathBuf>,
         /// Compile a library instead of a program.
         ///
         /// In this case, no `proc main!()` is required, but the resulting library cannot be used
@@ -43,34 +61,92 @@ enum Command {
         test: bool,
         /// Only tests in modules whose path matches a listed prefix will be run.
         ///
-        /// By default, tests in all locally defined modules are run.
+        /// By default, tests in the modules listed by `test-prefixes` in the project's
+        /// `trilogy.toml` are run, or else tests in all locally defined modules.
         ///
         /// This flag is only relevant when compiling tests.
-        #[arg(long = "prefix", short = 'p', default_values_t = [String::from("file:")])]
+        #[arg(long = "prefix", short = 'p')]
+        filter_prefix: Vec<String>,
+    },
+    /// Compile a Trilogy program to a native executable.
+    ///
+    /// Expects a single path in which the `main!()` procedure is found, unless
+    /// the project's `trilogy.toml` declares one. Linking the executable requires
+    /// `clang`.
+    Build {
+        /// The path to the Trilogy source file containing the `main!()` procedure.
+        ///
+        /// Defaults to the entrypoint declared in the project's `trilogy.toml`.
+        file: Option<PathBuf>,
+        /// The path to write the executable to.
+        ///
+        /// Defaults to the name of the source file, without extension, in the
+        /// current directory.
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+        /// Compile the tests instead of the application.
+        ///
+        /// The resulting binary will run the test suite and print its result when executed.
+        #[arg(long = "test")]
+        test: bool,
+        /// Only tests in modules whose path matches a listed prefix will be run.
+        ///
+        /// By default, tests in the modules listed by `test-prefixes` in the project's
+        /// `trilogy.toml` are run, or else tests in all locally defined modules.
+        ///
+        /// This flag is only relevant when compiling tests.
+        #[arg(long = "prefix", short = 'p')]
         filter_prefix: Vec<String>,
     },
     /// Check the syntax and warnings of a Trilogy program.
     Check {
         /// The path to the Trilogy source file containing the `main!()` procedure.
-        file: PathBuf,
+        ///
+        /// Defaults to the entrypoint declared in the project's `trilogy.toml`.
+        file: Option<PathBuf>,
+        /// How to print the errors and warnings that are found.
+        #[arg(long, value_enum, default_value_t)]
+        message_format: check::MessageFormat,
     },
     /// Runs all tests found in the given module and all its submodules.
     ///
     /// The provided path is not required to define a `main` function as
     /// entrypoint, as it will not be called.
     Test {
-        file: PathBuf,
+        /// The path to the Trilogy source file whose tests are run.
+        ///
+        /// Defaults to the entrypoint declared in the project's `trilogy.toml`.
+        file: Option<PathBuf>,
         /// Only tests in modules whose path matches a listed prefix will be run.
         ///
-        /// By default, tests in all locally defined modules are run.
-        #[arg(long = "prefix", short = 'p', default_values_t = [String::from("file:")])]
+        /// By default, tests in the modules listed by `test-prefixes` in the project's
+        /// `trilogy.toml` are run, or else tests in all locally defined modules.
+        #[arg(long = "prefix", short = 'p')]
         filter_prefix: Vec<String>,
     },
+    /// Refresh the pinned contents of remote modules in the `trilogy.lock` file
+    /// beside a Trilogy program.
+    ///
+    /// Every remote module the program imports is downloaded again, bypassing
+    /// the cache.
+    Update {
+        /// The path to the Trilogy source file containing the `main!()` procedure.
+        file: PathBuf,
+    },
+    /// Copy every remote module imported by a Trilogy program into the `vendor`
+    /// directory beside it.
+    ///
+    /// Vendored modules are used in place of downloading them, so that the
+    /// program can be built without network access.
+    Vendor {
+        /// The path to the Trilogy source file containing the `main!()` procedure.
+        file: PathBuf,
+    },
     /// Format one or many Trilogy files.
     ///
     /// If one file is provided, the output is written to standard output
     /// unless the `--write` flag is passed. With multiple files, the `--write`
-    /// flag must be passed.
+    /// or `--check` flag must be passed.
     Fmt {
         /// The file, or files, to format.
         ///
@@ -79,42 +155,163 @@ enum Command {
         #[arg(short, long)]
         /// Write formatted output directly to the file at the path from where it was read.
         write: bool,
+        /// Check that the files are already formatted, without changing them.
+        ///
+        /// Lists the files that would be changed, and exits with a non-zero
+        /// status if there are any.
+        #[arg(long, conflicts_with = "write")]
+        check: bool,
+    },
+    /// Run the Trilogy language server over standard input and output.
+    Lsp {
+        /// The paths to Trilogy source files containing a `main!()` procedure.
+        ///
+        /// Other files opened in the editor are checked as libraries.
+        files: Vec<PathBuf>,
     },
-    /// Run the Trilogy language server.
-    Lsp { files: Vec<PathBuf> },
     /// Print the version information.
     Version,
 }
 
-fn run(trilogy: Trilogy, print: bool, debug: bool) {
-    let result = trilogy.run();
-    match result {
-        Ok(value) if print => {
-            println!("{value:?}");
+/// Builds the program from `file`, or from the entrypoint declared in the project's
+/// `trilogy.toml` if no file is given.
+fn build(
+    builder: Builder<FileSystemCache>,
+    file: Option<PathBuf>,
+) -> Result<Trilogy, Box<Report<std::io::Error>>> {
+    match file {
+        Some(file) => builder.build_from_source(file),
+        None => builder.build_project(),
+    }
+}
+
+/// The prefixes of the modules whose tests are run: those listed on the command line,
+/// or else those declared in the project's manifest, or else all local modules.
+fn test_prefixes(trilogy: &Trilogy, filter_prefix: Vec<String>) -> Vec<String> {
+    if !filter_prefix.is_empty() {
+        return filter_prefix;
+    }
+    trilogy
+        .manifest()
+        .and_then(|manifest| manifest.test_prefixes())
+        .map(<[String]>::to_vec)
+        .unwrap_or_else(|| vec![String::from("file:")])
+}
+
+fn run(trilogy: Trilogy, args: Vec<String>, print: bool) {
+    let termination = match trilogy.run_with_args(args) {
+        Ok(termination) => termination,
+        Err(error) => {
+            eprintln!("{error}");
+            std::process::exit(255);
         }
-        Ok(_value) => {
-            // NOTE: for now, exit codes don't return to Rust properly, so we don't use them
+    };
+    match &termination {
+        Termination::Returned { value, .. } | Termination::Cancelled { value, .. } if print => {
+            println!("{value:?}")
         }
-        Err(error) if debug => {
-            eprintln!("{error:?}");
-            std::process::exit(255);
+        Termination::Exited(code) if print => println!("{code}"),
+        Termination::Returned {
+            value,
+            exit_code: None,
+        } => {
+            eprintln!("main!() returned a value that is not a valid exit code: {value:?}");
         }
-        Err(error) => {
-            eprintln!("{error:?}");
-            std::process::exit(255);
+        Termination::Cancelled {
+            value,
+            exit_code: None,
+        } => {
+            eprintln!(
+                "the program was cancelled with a value that is not a valid exit code: {value:?}"
+            );
+        }
+        Termination::Returned { .. } | Termination::Cancelled { .. } | Termination::Exited(..) => {}
+    }
+    std::process::exit(termination.exit_code());
+}
+
+/// Formats the source read from `path`, reporting any syntax errors.
+///
+/// Returns `None` if the source could not be formatted.
+fn format(path: &str, source: &str) -> Option<String> {
+    match trilogy::format(source) {
+        Ok(formatted) => Some(formatted),
+        Err(errors) => {
+            for error in errors {
+                eprintln!("{path}: {error}");
+            }
+            None
+        }
+    }
+}
+
+fn fmt(files: Vec<PathBuf>, write: bool, check: bool) -> std::io::Result<()> {
+    if files.is_empty() {
+        let mut source = String::new();
+        std::io::stdin().read_to_string(&mut source)?;
+        let Some(formatted) = format("<stdin>", &source) else {
+            std::process::exit(1);
+        };
+        if !check {
+            print!("{formatted}");
+        } else if formatted != source {
+            println!("<stdin>");
+            std::process::exit(1);
         }
+        return Ok(());
+    }
+
+    if files.len() > 1 && !write && !check {
+        eprintln!("the --write or --check flag is required when formatting multiple files");
+        std::process::exit(1);
     }
+
+    let mut failed = false;
+    for file in files {
+        let source = std::fs::read_to_string(&file)?;
+        let Some(formatted) = format(&file.display().to_string(), &source) else {
+            failed = true;
+            continue;
+        };
+        if check {
+            if formatted != source {
+                println!("{}", file.display());
+                failed = true;
+            }
+        } else if write {
+            if formatted != source {
+                std::fs::write(&file, formatted)?;
+            }
+        } else {
+            print!("{formatted}");
+        }
+    }
+    if failed {
+        std::process::exit(1);
+    }
+    Ok(())
 }
 
 fn main() -> std::io::Result<()> {
     pretty_env_logger::init();
     let args = Cli::parse();
+    let builder = || Builder::std().offline(args.offline);
+    let color = match args.color {
+        clap::ColorChoice::Auto => ColorChoice::Auto,
+        clap::ColorChoice::Always => ColorChoice::Always,
+        clap::ColorChoice::Never => ColorChoice::Never,
+    };
 
     match args.command {
-        Command::Run { file, print, debug } => match Builder::std().build_from_source(file) {
-            Ok(trilogy) => run(trilogy, print, debug),
+        Command::Run {
+            file,
+            print,
+            debug: _,
+            args,
+        } => match build(builder(), file) {
+            Ok(trilogy) => run(trilogy, args, print),
             Err(report) => {
-                report.eprint();
+                check::eprint(&report, color);
                 std::process::exit(1);
             }
         },
@@ -123,43 +320,105 @@ fn main() -> std::io::Result<()> {
             library,
             test,
             filter_prefix,
-        } => match Builder::std()
-            .is_library(library || test)
-            .build_from_source(file)
-        {
+        } => match build(builder().is_library(library || test), file) {
             Ok(trilogy) => {
                 print!(
                     "{}",
                     if test {
-                        trilogy.compile_test(&filter_prefix)
+                        trilogy.compile_test(&test_prefixes(&trilogy, filter_prefix))
                     } else {
                         trilogy.compile()
                     }
                 );
             }
             Err(report) => {
-                report.eprint();
+                check::eprint(&report, color);
                 std::process::exit(1);
             }
         },
-        Command::Check { file, .. } => {
-            if let Err(report) = Trilogy::from_file(file) {
-                report.eprint();
+        Command::Build {
+            file,
+            output,
+            test,
+            filter_prefix,
+        } => match build(builder().is_library(test), file) {
+            Ok(trilogy) => {
+                let output = output.unwrap_or_else(|| {
+                    let entrypoint = trilogy
+                        .source_entrypoint()
+                        .and_then(|location| location.to_local_path());
+                    PathBuf::from(
+                        entrypoint
+                            .as_deref()
+                            .and_then(|path| path.file_stem())
+                            .unwrap_or("main".as_ref()),
+                    )
+                });
+                let result = if test {
+                    trilogy.build_test(&output, &test_prefixes(&trilogy, filter_prefix))
+                } else {
+                    trilogy.build(&output)
+                };
+                if let Err(error) = result {
+                    eprintln!("{error}");
+                    std::process::exit(1);
+                }
+            }
+            Err(report) => {
+                check::eprint(&report, color);
+                std::process::exit(1);
+            }
+        },
+        Command::Check {
+            file,
+            message_format,
+        } => {
+            if let Err(report) = build(builder(), file) {
+                check::print(&report, message_format, color);
                 std::process::exit(1);
             }
         }
         Command::Test {
             file,
             filter_prefix,
-        } => match Builder::std().is_library(true).build_from_source(file) {
+        } => match build(builder().is_library(true), file) {
             Ok(trilogy) => {
-                trilogy.test(&filter_prefix);
+                let exit_code = trilogy.test(&test_prefixes(&trilogy, filter_prefix));
+                if exit_code != 0 {
+                    std::process::exit(exit_code);
+                }
             }
             Err(report) => {
-                report.eprint();
+                check::eprint(&report, color);
                 std::process::exit(1);
             }
         },
+        Command::Update { file } => {
+            if let Err(report) = builder()
+                .is_library(true)
+                .lockfile(LockfileMode::Update)
+                .build_from_source(file)
+            {
+                check::eprint(&report, color);
+                std::process::exit(1);
+            }
+        }
+        Command::Vendor { file } => match builder().is_library(true).vendor(file) {
+            Ok(locations) => {
+                for location in locations {
+                    println!("{location}");
+                }
+            }
+            Err(report) => {
+                check::eprint(&report, color);
+                std::process::exit(1);
+            }
+        },
+        Command::Fmt {
+            files,
+            write,
+            check,
+        } => fmt(files, write, check)?,
         Command::Version => {
             println!(
                 "{} {} -- Trilogy Language Compiler",
@@ -167,7 +426,12 @@ fn main() -> std::io::Result<()> {
                 env!("CARGO_PKG_VERSION")
             )
         }
-        _ => todo!("not yet implemented"),
+        Command::Lsp { files } => {
+            if let Err(error) = lsp::run(files) {
+                eprintln!("{error}");
+                std::process::exit(1);
+            }
+        }
     }
 
     Ok(())
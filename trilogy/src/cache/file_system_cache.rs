@@ -40,14 +40,7 @@ impl FileSystemCache {
         let url = location.as_ref();
         match url.scheme() {
             "file" => Some(url.path().parse().unwrap()),
-            "http" | "https" => {
-                let host = url.host().expect("http(s) url should have a host");
-                let dir = match url.port().filter(|&port| port != 80 && port != 443) {
-                    Some(port) => format!("{host}:{port}"),
-                    None => host.to_string(),
-                };
-                Some(self.cache_dir.join(dir).join(url.path()))
-            }
+            "http" | "https" => Some(self.cache_dir.join(location.remote_path()?)),
             _ => None,
         }
     }
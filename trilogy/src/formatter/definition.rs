@@ -0,0 +1,264 @@
+use super::{Doc, Format, INDENT, Printer};
+use trilogy_parser::Spanned;
+use trilogy_parser::syntax::*;
+
+impl Format for Document {
+    fn format<'a>(&self, printer: &Printer<'a>) -> Doc<'a> {
+        let mut lines = printer.lines();
+        if let Some(documentation) = &self.documentation {
+            lines.item(documentation.span(), || documentation.format(printer));
+        }
+        for definition in &self.definitions {
+            lines.item(definition.span(), || definition.format(printer));
+        }
+        lines
+            .finish(source_span::Position::end())
+            .unwrap_or_else(Doc::nil)
+    }
+}
+
+impl Format for Documentation {
+    fn format<'a>(&self, printer: &Printer<'a>) -> Doc<'a> {
+        Doc::intersperse(
+            self.tokens.iter().map(|token| printer.comment(token)),
+            Doc::hardline(),
+        )
+    }
+}
+
+impl Format for Definition {
+    fn format<'a>(&self, printer: &Printer<'a>) -> Doc<'a> {
+        let documentation = match &self.documentation {
+            Some(documentation) => documentation.format(printer).append(Doc::hardline()),
+            None => Doc::nil(),
+        };
+        let comments = printer
+            .comments_before(self.item.span().start())
+            .into_iter()
+            .map(|comment| printer.comment(&comment).append(Doc::hardline()));
+        documentation
+            .append(Doc::concat(comments))
+            .append(self.item.format(printer))
+    }
+}
+
+impl Format for DefinitionItem {
+    fn format<'a>(&self, printer: &Printer<'a>) -> Doc<'a> {
+        match self {
+            Self::Type(def) => def.format(printer),
+            Self::NamedType(def) => def.format(printer),
+            Self::Import(def) => def.format(printer),
+            Self::Procedure(def) => def.format(printer),
+            Self::ExternalProcedure(def) => def.format(printer),
+            Self::Constant(def) => def.format(printer),
+            Self::Function(def) => def.format(printer),
+            Self::Rule(def) => def.format(printer),
+            Self::Export(def) => def.format(printer),
+            Self::Test(def) => def.format(printer),
+        }
+    }
+}
+
+impl Format for TypeDefinition {
+    fn format<'a>(&self, printer: &Printer<'a>) -> Doc<'a> {
+        let head = self.head.format(printer);
+        let mut lines = printer.lines();
+        for definition in &self.definitions {
+            lines.item(definition.span(), || definition.format(printer));
+        }
+        head.append(Doc::space())
+            .append(lines.braced(&self.close_brace, false))
+    }
+}
+
+impl Format for NamedTypeDefinition {
+    fn format<'a>(&self, printer: &Printer<'a>) -> Doc<'a> {
+        let doc = self
+            .head
+            .format(printer)
+            .append(" is ")
+            .append(self.pattern.format(printer));
+        match &self.constraint {
+            Some(constraint) => doc
+                .append(" where ")
+                .append(constraint.query.format(printer)),
+            None => doc,
+        }
+    }
+}
+
+impl Format for TypeHead {
+    fn format<'a>(&self, printer: &Printer<'a>) -> Doc<'a> {
+        Doc::text("type ")
+            .append(self.name.format(printer))
+            .append(Doc::concat(
+                self.parameters
+                    .iter()
+                    .map(|param| Doc::space().append(param.format(printer))),
+            ))
+    }
+}
+
+impl Format for ImportDefinition {
+    fn format<'a>(&self, printer: &Printer<'a>) -> Doc<'a> {
+        let mut doc = Doc::text("import ").append(self.locator.format(printer));
+        if let Some(type_as) = &self.type_as {
+            doc = doc
+                .append(" as ")
+                .append(type_as.identifier.format(printer));
+        }
+        if let Some(type_use) = &self.type_use {
+            let names = type_use.names.iter().map(|name| {
+                let original = name.original_name();
+                let aliased = name.aliased_name();
+                if original.span == aliased.span {
+                    original.format(printer)
+                } else {
+                    original
+                        .format(printer)
+                        .append(" as ")
+                        .append(aliased.format(printer))
+                }
+            });
+            doc = doc.append(" use ").append(fill(names));
+        }
+        doc
+    }
+}
+
+impl Format for ExportDefinition {
+    fn format<'a>(&self, printer: &Printer<'a>) -> Doc<'a> {
+        let names = self.names.iter().map(|name| name.format(printer));
+        Doc::text("export ").append(fill(names))
+    }
+}
+
+/// Comma separated names, wrapped onto following lines only as needed.
+fn fill<'a>(names: impl Iterator<Item = Doc<'a>>) -> Doc<'a> {
+    Doc::intersperse(names, Doc::text(",").append(Doc::softline())).nest(INDENT)
+}
+
+impl Format for ProcedureDefinition {
+    fn format<'a>(&self, printer: &Printer<'a>) -> Doc<'a> {
+        Doc::text("proc ")
+            .append(self.head.format(printer))
+            .append(Doc::space())
+            .append(self.body.format(printer))
+    }
+}
+
+impl Format for ProcedureHead {
+    fn format<'a>(&self, printer: &Printer<'a>) -> Doc<'a> {
+        let name = self.name.format(printer);
+        let mut list = printer.list();
+        for param in &self.parameters {
+            list.item(param.span(), || param.format(printer));
+        }
+        name.append("!")
+            .append(list.finish(&self.open_paren, &self.close_paren, false))
+    }
+}
+
+impl Format for ExternalProcedureDefinition {
+    fn format<'a>(&self, printer: &Printer<'a>) -> Doc<'a> {
+        Doc::text("extern ")
+            .append(printer.token(&self.call_conv))
+            .append(" proc ")
+            .append(self.head.format(printer))
+    }
+}
+
+impl Format for SlotDefinition {
+    fn format<'a>(&self, printer: &Printer<'a>) -> Doc<'a> {
+        let head = Doc::text("slot ")
+            .append(match &self.r#mut {
+                Some(..) => Doc::text("mut "),
+                None => Doc::nil(),
+            })
+            .append(self.name.format(printer));
+        head.append(definition_body(&self.body, printer))
+    }
+}
+
+impl Format for FunctionDefinition {
+    fn format<'a>(&self, printer: &Printer<'a>) -> Doc<'a> {
+        self.head
+            .format(printer)
+            .append(definition_body(&self.body, printer))
+    }
+}
+
+impl Format for FunctionHead {
+    fn format<'a>(&self, printer: &Printer<'a>) -> Doc<'a> {
+        let mut doc = Doc::text("func ").append(self.name.format(printer));
+        for param in &self.parameters {
+            doc = doc.append(Doc::space()).append(param.format(printer));
+        }
+        if let Some(guard) = &self.guard {
+            doc = doc.append(Doc::space()).append(guard.format(printer));
+        }
+        doc
+    }
+}
+
+/// The `= body` of a function or slot definition.
+///
+/// Pipelines and bracketed bodies start on the same line as the definition; `let`
+/// chains are moved to the next line entirely, one binding per line.
+fn definition_body<'a>(body: &Expression, printer: &Printer<'a>) -> Doc<'a> {
+    match body {
+        Expression::Block(..)
+        | Expression::Array(..)
+        | Expression::Set(..)
+        | Expression::Record(..)
+        | Expression::ArrayComprehension(..)
+        | Expression::SetComprehension(..)
+        | Expression::RecordComprehension(..)
+        | Expression::Do(..) => Doc::text(" = ").append(body.format(printer)),
+        Expression::Let(..) => Doc::text(" =").append(
+            Doc::hardline()
+                .append(super::expression::let_chain(body, printer, true))
+                .nest(INDENT),
+        ),
+        _ if super::expression::is_pipeline(body) => Doc::text(" = ").append(body.format(printer)),
+        _ => Doc::text(" =")
+            .append(Doc::line().append(body.format(printer)).nest(INDENT))
+            .group(),
+    }
+}
+
+impl Format for RuleDefinition {
+    fn format<'a>(&self, printer: &Printer<'a>) -> Doc<'a> {
+        let head = Doc::text("rule ").append(self.head.format(printer));
+        match &self.body {
+            Some(body) if printer.is_multiline(body.span()) => head.append(" <-").append(
+                Doc::hardline()
+                    .append(super::query::chain(body, printer, false))
+                    .nest(INDENT),
+            ),
+            Some(body) => head.append(" <- ").append(body.format(printer)),
+            None => head,
+        }
+    }
+}
+
+impl Format for RuleHead {
+    fn format<'a>(&self, printer: &Printer<'a>) -> Doc<'a> {
+        self.name
+            .format(printer)
+            .append(self.parameter_list.format(printer))
+    }
+}
+
+impl Format for TestDefinition {
+    fn format<'a>(&self, printer: &Printer<'a>) -> Doc<'a> {
+        Doc::text("test ")
+            .append(match &self.not {
+                Some(..) => Doc::text("not "),
+                None => Doc::nil(),
+            })
+            .append(self.name.format(printer))
+            .append(Doc::space())
+            .append(self.body.format(printer))
+    }
+}
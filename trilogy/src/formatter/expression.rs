@@ -0,0 +1,509 @@
+use super::{Doc, Format, INDENT, Printer};
+use trilogy_parser::Spanned;
+use trilogy_parser::syntax::*;
+
+macro_rules! format_token {
+    ($($t:ty),+ $(,)?) => {
+        $(
+            impl Format for $t {
+                fn format<'a>(&self, printer: &Printer<'a>) -> Doc<'a> {
+                    printer.token(&self.token)
+                }
+            }
+        )+
+    };
+}
+
+format_token!(
+    Identifier,
+    NumberLiteral,
+    CharacterLiteral,
+    StringLiteral,
+    BitsLiteral,
+    BooleanLiteral,
+    UnitLiteral,
+    AtomLiteral,
+);
+
+impl Format for Expression {
+    fn format<'a>(&self, printer: &Printer<'a>) -> Doc<'a> {
+        match self {
+            Self::Number(expr) => expr.format(printer),
+            Self::Character(expr) => expr.format(printer),
+            Self::String(expr) => expr.format(printer),
+            Self::Bits(expr) => expr.format(printer),
+            Self::Boolean(expr) => expr.format(printer),
+            Self::Unit(expr) => expr.format(printer),
+            Self::Atom(expr) => expr.format(printer),
+            Self::Struct(expr) => expr.format(printer),
+            Self::Array(expr) => expr.format(printer),
+            Self::Set(expr) => expr.format(printer),
+            Self::Record(expr) => expr.format(printer),
+            Self::ArrayComprehension(expr) => expr.format(printer),
+            Self::SetComprehension(expr) => expr.format(printer),
+            Self::RecordComprehension(expr) => expr.format(printer),
+            Self::Reference(expr) => expr.format(printer),
+            Self::Keyword(expr) => expr.format(printer),
+            Self::Application(expr) => expr.format(printer),
+            Self::Call(expr) => expr.format(printer),
+            Self::Binary(..) if is_pipeline(self) => pipeline(self, printer),
+            Self::Binary(expr) => expr.format(printer),
+            Self::Unary(expr) => expr.format(printer),
+            Self::Let(..) => let_chain(self, printer, false),
+            Self::IfElse(expr) => expr.format(printer),
+            Self::For(expr) => expr.format(printer),
+            Self::Match(expr) => expr.format(printer),
+            Self::Is(expr) => Doc::text("is ").append(expr.query.format(printer)),
+            Self::End(..) => Doc::text("end"),
+            Self::Exit(expr) => Doc::text("exit ").append(expr.expression.format(printer)),
+            Self::Resume(expr) => Doc::text("resume ").append(expr.expression.format(printer)),
+            Self::Become(expr) => Doc::text("become ").append(expr.expression.format(printer)),
+            Self::Cancel(expr) => Doc::text("cancel ").append(expr.expression.format(printer)),
+            Self::Return(expr) => Doc::text("return ").append(expr.expression.format(printer)),
+            Self::Break(expr) => Doc::text("break ").append(expr.expression.format(printer)),
+            Self::Continue(expr) => Doc::text("continue ").append(expr.expression.format(printer)),
+            Self::Fn(expr) => expr.format(printer),
+            Self::Do(expr) => expr.format(printer),
+            Self::Qy(expr) => expr.format(printer),
+            Self::Template(expr) => expr.format(printer),
+            Self::Handled(expr) => expr.format(printer),
+            Self::Parenthesized(expr) => Doc::text("(")
+                .append(expr.expression.format(printer))
+                .append(")"),
+            Self::Section(expr) => expr.format(printer),
+            Self::ModuleAccess(expr) => expr
+                .lhs
+                .format(printer)
+                .append("::")
+                .append(expr.rhs.format(printer)),
+            Self::Block(expr) => expr.format(printer),
+        }
+    }
+}
+
+impl Format for OperatorSection {
+    fn format<'a>(&self, printer: &Printer<'a>) -> Doc<'a> {
+        let operand = self.operand.format(printer);
+        let operator = printer.slice(self.operator.span());
+        let section = match self.side {
+            SectionSide::Left => operand.append(Doc::space()).append(operator),
+            SectionSide::Right => Doc::text(operator).append(Doc::space()).append(operand),
+        };
+        Doc::text("(").append(section).append(")")
+    }
+}
+
+impl Format for FollowingExpression {
+    fn format<'a>(&self, printer: &Printer<'a>) -> Doc<'a> {
+        match self {
+            Self::Then(_, expression) => Doc::text("then ").append(expression.format(printer)),
+            Self::Block(block) => block.format(printer),
+        }
+    }
+}
+
+fn ends_with_block(following: &FollowingExpression) -> bool {
+    matches!(
+        following,
+        FollowingExpression::Block(..) | FollowingExpression::Then(_, Expression::Block(..))
+    )
+}
+
+impl Format for StructLiteral {
+    fn format<'a>(&self, printer: &Printer<'a>) -> Doc<'a> {
+        self.atom
+            .format(printer)
+            .append("(")
+            .append(self.value.format(printer))
+            .append(")")
+    }
+}
+
+impl Format for ArrayLiteral {
+    fn format<'a>(&self, printer: &Printer<'a>) -> Doc<'a> {
+        let mut list = printer.list();
+        for element in &self.elements {
+            list.item(element.span(), || match element {
+                ArrayElement::Element(expression) => expression.format(printer),
+                ArrayElement::Spread(_, expression) => {
+                    Doc::text("..").append(expression.format(printer))
+                }
+            });
+        }
+        list.finish(&self.open_bracket, &self.close_bracket, false)
+    }
+}
+
+impl Format for SetLiteral {
+    fn format<'a>(&self, printer: &Printer<'a>) -> Doc<'a> {
+        let mut list = printer.list();
+        for element in &self.elements {
+            list.item(element.span(), || match element {
+                SetElement::Element(expression) => expression.format(printer),
+                SetElement::Spread(_, expression) => {
+                    Doc::text("..").append(expression.format(printer))
+                }
+            });
+        }
+        list.finish(&self.open_bracket_pipe, &self.close_bracket_pipe, true)
+    }
+}
+
+impl Format for RecordLiteral {
+    fn format<'a>(&self, printer: &Printer<'a>) -> Doc<'a> {
+        let mut list = printer.list();
+        for element in &self.elements {
+            list.item(element.span(), || match element {
+                RecordElement::Element { key, value, .. } => key
+                    .format(printer)
+                    .append(" => ")
+                    .append(value.format(printer)),
+                RecordElement::Spread { value, .. } => {
+                    Doc::text("..").append(value.format(printer))
+                }
+            });
+        }
+        list.finish(&self.open_brace_pipe, &self.close_brace_pipe, true)
+    }
+}
+
+impl Format for ArrayComprehension {
+    fn format<'a>(&self, printer: &Printer<'a>) -> Doc<'a> {
+        Doc::text("[")
+            .append(self.expression.format(printer))
+            .append(" for ")
+            .append(self.query.format(printer))
+            .append("]")
+    }
+}
+
+impl Format for SetComprehension {
+    fn format<'a>(&self, printer: &Printer<'a>) -> Doc<'a> {
+        Doc::text("[| ")
+            .append(self.expression.format(printer))
+            .append(" for ")
+            .append(self.query.format(printer))
+            .append(" |]")
+    }
+}
+
+impl Format for RecordComprehension {
+    fn format<'a>(&self, printer: &Printer<'a>) -> Doc<'a> {
+        Doc::text("{| ")
+            .append(self.key_expression.format(printer))
+            .append(" => ")
+            .append(self.expression.format(printer))
+            .append(" for ")
+            .append(self.query.format(printer))
+            .append(" |}")
+    }
+}
+
+impl Format for KeywordReference {
+    fn format<'a>(&self, printer: &Printer<'a>) -> Doc<'a> {
+        Doc::text("(")
+            .append(printer.slice(self.keyword.span()))
+            .append(")")
+    }
+}
+
+impl Format for Application {
+    fn format<'a>(&self, printer: &Printer<'a>) -> Doc<'a> {
+        self.function
+            .format(printer)
+            .append(Doc::space())
+            .append(self.argument.format(printer))
+    }
+}
+
+impl Format for CallExpression {
+    fn format<'a>(&self, printer: &Printer<'a>) -> Doc<'a> {
+        let procedure = self.procedure.format(printer);
+        let mut list = printer.list();
+        for argument in &self.arguments {
+            list.item(argument.span(), || argument.format(printer));
+        }
+        procedure
+            .append("!")
+            .append(list.finish(&self.open_paren, &self.close_paren, false))
+    }
+}
+
+impl Format for BinaryOperation {
+    fn format<'a>(&self, printer: &Printer<'a>) -> Doc<'a> {
+        let lhs = self.lhs.format(printer);
+        let operator = printer.slice(self.operator.span());
+        let rhs = self.rhs.format(printer);
+        match self.operator {
+            // Access and cons are written both spaced and unspaced, so whichever
+            // the author chose is kept.
+            BinaryOperator::Access(..) | BinaryOperator::Cons(..)
+                if self.lhs.span().end() == self.operator.span().start() =>
+            {
+                lhs.append(operator).append(rhs)
+            }
+            _ => lhs
+                .append(Doc::space())
+                .append(operator)
+                .append(Doc::space())
+                .append(rhs),
+        }
+    }
+}
+
+/// Whether this expression is a chain of `|>` or `>>`, which are printed
+/// with each step on its own line when they do not fit.
+pub(super) fn is_pipeline(expression: &Expression) -> bool {
+    matches!(
+        expression,
+        Expression::Binary(op) if matches!(op.operator, BinaryOperator::Pipe(..) | BinaryOperator::RCompose(..))
+    )
+}
+
+fn pipeline<'a>(expression: &Expression, printer: &Printer<'a>) -> Doc<'a> {
+    let Expression::Binary(root) = expression else {
+        unreachable!()
+    };
+    let mut steps = vec![];
+    let mut head = expression;
+    while let Expression::Binary(op) = head {
+        if std::mem::discriminant(&op.operator) != std::mem::discriminant(&root.operator) {
+            break;
+        }
+        steps.push((&op.operator, &op.rhs));
+        head = &op.lhs;
+    }
+    let span = expression.span();
+    // Pipelines that the author already broke over multiple lines stay that way.
+    let separator = if span.start().line == span.last().line {
+        Doc::line()
+    } else {
+        Doc::hardline()
+    };
+    let mut doc = head.format(printer);
+    let mut rest = Doc::nil();
+    for (operator, rhs) in steps.into_iter().rev() {
+        rest = rest
+            .append(separator.clone())
+            .append(printer.leading(operator.span().start()))
+            .append(printer.slice(operator.span()))
+            .append(Doc::space())
+            .append(rhs.format(printer));
+    }
+    doc = doc.append(rest.nest(INDENT));
+    doc.group()
+}
+
+impl Format for UnaryOperation {
+    fn format<'a>(&self, printer: &Printer<'a>) -> Doc<'a> {
+        let spaced = matches!(
+            self.operator,
+            UnaryOperator::Yield(..) | UnaryOperator::Typeof(..)
+        ) || matches!(self.operand, Expression::Unary(..));
+        Doc::text(printer.slice(self.operator.span()))
+            .append(if spaced { Doc::space() } else { Doc::nil() })
+            .append(self.operand.format(printer))
+    }
+}
+
+/// Prints a sequence of `let` expressions one binding at a time. When forced,
+/// each binding is on its own line; otherwise they are only broken when they
+/// do not fit.
+pub(super) fn let_chain<'a>(
+    expression: &Expression,
+    printer: &Printer<'a>,
+    force: bool,
+) -> Doc<'a> {
+    let separator = if force { Doc::hardline() } else { Doc::line() };
+    let mut doc = Doc::nil();
+    let mut current = expression;
+    while let Expression::Let(expr) = current {
+        doc = doc
+            .append(printer.leading(expr.span().start()))
+            .append("let ")
+            .append(expr.query.format(printer))
+            .append(",")
+            .append(separator.clone());
+        current = &expr.body;
+    }
+    doc.append(printer.leading(current.span().start()))
+        .append(current.format(printer))
+        .group()
+}
+
+impl Format for IfElseExpression {
+    fn format<'a>(&self, printer: &Printer<'a>) -> Doc<'a> {
+        if_else(self, printer, false)
+    }
+}
+
+/// Prints an `if` expression. Blocks are followed by `} else`, while `then` branches
+/// are broken over multiple lines when they do not fit or were written that way, either
+/// as a chain of `else if`, or with `then` and `else` indented below the condition.
+fn if_else<'a>(expr: &IfElseExpression, printer: &Printer<'a>, chained: bool) -> Doc<'a> {
+    let condition = Doc::text("if ").append(expr.condition.format(printer));
+    let when_true = expr.when_true.format(printer);
+    let Some(when_false) = &expr.when_false else {
+        return condition.append(Doc::space()).append(when_true);
+    };
+    if ends_with_block(&expr.when_true) {
+        return condition
+            .append(Doc::space())
+            .append(when_true)
+            .append(" else ")
+            .append(when_false.body.format(printer));
+    }
+    let separator = if printer.is_multiline(expr.span) {
+        Doc::hardline()
+    } else {
+        Doc::line()
+    };
+    match &when_false.body {
+        Expression::IfElse(next) => condition
+            .append(Doc::space())
+            .append(when_true)
+            .append(separator)
+            .append("else ")
+            .append(if_else(next, printer, true))
+            .group(),
+        body if chained => condition
+            .append(Doc::space())
+            .append(when_true)
+            .append(separator)
+            .append("else ")
+            .append(body.format(printer))
+            .group(),
+        body => condition
+            .append(
+                separator
+                    .clone()
+                    .append(when_true)
+                    .append(separator)
+                    .append("else ")
+                    .append(body.format(printer))
+                    .nest(INDENT),
+            )
+            .group(),
+    }
+}
+
+impl Format for MatchExpression {
+    fn format<'a>(&self, printer: &Printer<'a>) -> Doc<'a> {
+        let head = Doc::text("match ").append(self.expression.format(printer));
+        let mut lines = printer.lines();
+        for case in &self.cases {
+            lines.item(case.span(), || case.format(printer));
+        }
+        if let Some(else_case) = &self.else_case {
+            lines.item(else_case.span(), || {
+                Doc::text("else ").append(else_case.body.format(printer))
+            });
+        }
+        let inline =
+            !printer.is_multiline(self.span) && self.cases.len() + self.else_case.iter().len() == 1;
+        head.append(Doc::space())
+            .append(lines.braced(&self.cbrace, inline))
+    }
+}
+
+impl Format for MatchExpressionCase {
+    fn format<'a>(&self, printer: &Printer<'a>) -> Doc<'a> {
+        let mut doc = Doc::text("case");
+        if let Some(pattern) = &self.pattern {
+            doc = doc.append(Doc::space()).append(pattern.format(printer));
+        }
+        if let Some(guard) = &self.guard {
+            doc = doc.append(Doc::space()).append(guard.format(printer));
+        }
+        doc.append(Doc::space()).append(self.body.format(printer))
+    }
+}
+
+impl Format for FnExpression {
+    fn format<'a>(&self, printer: &Printer<'a>) -> Doc<'a> {
+        let mut doc = Doc::text("fn");
+        for param in &self.parameters {
+            doc = doc.append(Doc::space()).append(param.format(printer));
+        }
+        doc.append(". ").append(self.body.format(printer))
+    }
+}
+
+impl Format for DoExpression {
+    fn format<'a>(&self, printer: &Printer<'a>) -> Doc<'a> {
+        let head = self.head.format(printer).append(Doc::space());
+        match &self.body {
+            DoBody::Block(block) => head.append(block.format(printer)),
+            DoBody::Expression(expression) => head.append(expression.format(printer)),
+        }
+    }
+}
+
+impl Format for QyExpression {
+    fn format<'a>(&self, printer: &Printer<'a>) -> Doc<'a> {
+        let mut list = printer.list();
+        for param in &self.parameters {
+            list.item(param.span(), || param.format(printer));
+        }
+        Doc::text("qy")
+            .append(list.finish(&self.open_paren, &self.close_paren, false))
+            .append(" <- ")
+            .append(self.body.format(printer))
+    }
+}
+
+impl Format for Template {
+    fn format<'a>(&self, printer: &Printer<'a>) -> Doc<'a> {
+        let mut doc = match &self.tag {
+            Some((dollar, tag)) => printer.token(dollar).append(tag.format(printer)),
+            None => Doc::nil(),
+        };
+        doc = doc.append(printer.token(&self.template_start));
+        for segment in &self.segments {
+            doc = doc
+                .append(segment.interpolation.format(printer))
+                .append(printer.token(&segment.end));
+        }
+        doc
+    }
+}
+
+impl Format for HandledExpression {
+    fn format<'a>(&self, printer: &Printer<'a>) -> Doc<'a> {
+        let head = Doc::text("with ").append(self.expression.format(printer));
+        let mut lines = printer.lines();
+        for handler in &self.handlers {
+            lines.item(handler.span(), || handler.format(printer));
+        }
+        let inline = !printer.is_multiline(self.span) && self.handlers.len() == 1;
+        head.append(Doc::space())
+            .append(lines.braced(&self.cbrace, inline))
+    }
+}
+
+impl Format for Handler {
+    fn format<'a>(&self, printer: &Printer<'a>) -> Doc<'a> {
+        match self {
+            Self::When(handler) => {
+                let mut doc = Doc::text("when ").append(handler.pattern.format(printer));
+                if let Some(guard) = &handler.guard {
+                    doc = doc.append(" if ").append(guard.format(printer));
+                }
+                doc.append(Doc::space())
+                    .append(handler.strategy.format(printer))
+            }
+            Self::Else(handler) => Doc::text("else ").append(handler.strategy.format(printer)),
+        }
+    }
+}
+
+impl Format for HandlerStrategy {
+    fn format<'a>(&self, printer: &Printer<'a>) -> Doc<'a> {
+        match self {
+            Self::Cancel { body, .. } => Doc::text("cancel ").append(body.format(printer)),
+            Self::Resume { body, .. } => Doc::text("resume ").append(body.format(printer)),
+            Self::Yield(..) => Doc::text("yield"),
+            Self::Bare(following) => following.format(printer),
+        }
+    }
+}
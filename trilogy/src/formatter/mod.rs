@@ -0,0 +1,371 @@
+//! The source formatter behind `trilogy fmt`.
+//!
+//! Formatting is performed on the syntax tree produced by `trilogy-parser`,
+//! which does not include comments, so comments are recovered by scanning
+//! the source a second time and reattached to the nearest definition,
+//! statement, or list element as the tree is printed.
+
+use crate::line_index::LineIndex;
+use pretty::RcDoc;
+use source_span::{Position, Span};
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use trilogy_parser::Parser;
+use trilogy_parser::syntax::SyntaxError;
+use trilogy_scanner::{Scanner, Token, TokenType};
+
+mod definition;
+mod expression;
+mod pattern;
+mod query;
+mod statement;
+
+const WIDTH: usize = 100;
+const INDENT: isize = 2;
+
+type Doc<'a> = RcDoc<'a, ()>;
+
+/// Formats a Trilogy source file.
+///
+/// Comments and (at most single) blank lines between definitions and statements
+/// are preserved; everything else is printed in the canonical style.
+///
+/// # Errors
+///
+/// If the source contains syntax errors, it is not formatted, and the errors
+/// are returned instead.
+pub fn format(source: &str) -> Result<String, Vec<SyntaxError>> {
+    let parse = Parser::new(Scanner::new(source)).parse();
+    if parse.has_errors() {
+        return Err(parse.errors().to_vec());
+    }
+    let printer = Printer::new(source);
+    let doc = parse.ast().format(&printer);
+    let mut output = String::new();
+    doc.render_fmt(WIDTH, &mut output)
+        .expect("writing to a string does not fail");
+    let output = output.trim_end();
+    if output.is_empty() {
+        Ok(String::new())
+    } else {
+        Ok(format!("{output}\n"))
+    }
+}
+
+trait Format {
+    fn format<'a>(&self, printer: &Printer<'a>) -> Doc<'a>;
+}
+
+impl<T: Format> Format for Box<T> {
+    fn format<'a>(&self, printer: &Printer<'a>) -> Doc<'a> {
+        self.as_ref().format(printer)
+    }
+}
+
+struct Printer<'a> {
+    source: &'a str,
+    lines: LineIndex<'a>,
+    comments: RefCell<VecDeque<Token>>,
+}
+
+impl<'a> Printer<'a> {
+    fn new(source: &'a str) -> Self {
+        let comments = Scanner::new(source)
+            .filter(|token| {
+                matches!(
+                    token.token_type,
+                    TokenType::CommentLine | TokenType::CommentBlock | TokenType::CommentInline
+                )
+            })
+            .collect();
+        Self {
+            source,
+            lines: LineIndex::new(source),
+            comments: RefCell::new(comments),
+        }
+    }
+
+    fn offset(&self, position: Position) -> usize {
+        self.lines.offset(position).unwrap_or(self.source.len())
+    }
+
+    fn slice(&self, span: Span) -> &'a str {
+        &self.source[self.offset(span.start())..self.offset(span.end())]
+    }
+
+    /// Prints a token exactly as it was written in the source.
+    fn token(&self, token: &Token) -> Doc<'a> {
+        Doc::text(self.slice(token.span))
+    }
+
+    fn comment(&self, comment: &Token) -> Doc<'a> {
+        Doc::text(self.slice(comment.span).trim_end())
+    }
+
+    /// The last line on which a comment has visible content.
+    fn comment_end_line(&self, comment: &Token) -> usize {
+        comment.span.start().line + self.slice(comment.span).trim_end().matches('\n').count()
+    }
+
+    /// Takes all comments that start before the given position.
+    fn comments_before(&self, position: Position) -> Vec<Token> {
+        let mut comments = self.comments.borrow_mut();
+        let mut taken = vec![];
+        while comments
+            .front()
+            .is_some_and(|comment| comment.span.start() < position)
+        {
+            taken.extend(comments.pop_front());
+        }
+        taken
+    }
+
+    /// Takes all comments that start on or before the given line.
+    fn comments_through(&self, line: usize) -> Vec<Token> {
+        let mut comments = self.comments.borrow_mut();
+        let mut taken = vec![];
+        while comments
+            .front()
+            .is_some_and(|comment| comment.span.start().line <= line)
+        {
+            taken.extend(comments.pop_front());
+        }
+        taken
+    }
+
+    /// Prints the comments that precede a position, each followed by a line
+    /// break unless it is an inline comment.
+    fn leading(&self, position: Position) -> Doc<'a> {
+        Doc::concat(self.comments_before(position).iter().map(|comment| {
+            self.comment(comment).append(if is_inline(comment) {
+                Doc::space()
+            } else {
+                Doc::hardline()
+            })
+        }))
+    }
+
+    /// Whether the source of a node was written across multiple lines. Where the formatter
+    /// has a choice, it respects the author's decision to break something up.
+    fn is_multiline(&self, span: Span) -> bool {
+        span.start().line != span.last().line
+    }
+
+    fn lines(&self) -> Lines<'_, 'a> {
+        Lines {
+            printer: self,
+            doc: Doc::nil(),
+            last: None,
+            has_comments: false,
+        }
+    }
+
+    fn list(&self) -> List<'_, 'a> {
+        List {
+            printer: self,
+            first: None,
+            items: vec![],
+        }
+    }
+}
+
+fn is_inline(comment: &Token) -> bool {
+    comment.token_type == TokenType::CommentInline
+}
+
+/// Items that are each printed on their own line, such as definitions,
+/// statements, and match cases.
+struct Lines<'p, 'a> {
+    printer: &'p Printer<'a>,
+    doc: Doc<'a>,
+    last: Option<usize>,
+    has_comments: bool,
+}
+
+impl<'a> Lines<'_, 'a> {
+    fn push(&mut self, doc: Doc<'a>) {
+        self.doc = std::mem::replace(&mut self.doc, Doc::nil()).append(doc);
+    }
+
+    fn break_before(&mut self, line: usize) {
+        match self.last {
+            None => {}
+            Some(last) if line > last + 1 => {
+                self.push(Doc::text("\n").append(Doc::hardline()));
+            }
+            // When a body is printed on a single line, statements are separated by `;` instead.
+            Some(..) => self.push(Doc::hardline().flat_alt(Doc::text("; "))),
+        }
+    }
+
+    fn comment(&mut self, comment: &Token) {
+        self.has_comments = true;
+        self.break_before(comment.span.start().line);
+        self.push(self.printer.comment(comment));
+        self.last = Some(self.printer.comment_end_line(comment));
+    }
+
+    fn item(&mut self, span: Span, item: impl FnOnce() -> Doc<'a>) {
+        let mut prefix = Doc::nil();
+        let mut attached = false;
+        for comment in self.printer.comments_before(span.start()) {
+            // Inline comments on the same line as the item stay attached to it.
+            if is_inline(&comment) && self.printer.comment_end_line(&comment) == span.start().line {
+                if !attached {
+                    self.has_comments = true;
+                    self.break_before(comment.span.start().line);
+                    attached = true;
+                }
+                prefix = prefix
+                    .append(self.printer.comment(&comment))
+                    .append(Doc::space());
+            } else {
+                self.comment(&comment);
+            }
+        }
+        if !attached {
+            self.break_before(span.start().line);
+        }
+        self.push(prefix.append(item()));
+        let mut last = span.last().line;
+        for comment in self.printer.comments_through(last) {
+            self.has_comments = true;
+            self.push(Doc::space().append(self.printer.comment(&comment)));
+            last = self.printer.comment_end_line(&comment);
+        }
+        self.last = Some(last);
+    }
+
+    /// Finishes the lines, including any remaining comments before the end position.
+    fn finish(mut self, end: Position) -> Option<Doc<'a>> {
+        for comment in self.printer.comments_before(end) {
+            self.comment(&comment);
+        }
+        self.last.map(|_| self.doc)
+    }
+
+    /// Finishes the lines as the contents of a `{}` delimited body. If permitted to be
+    /// inline, the body is kept on one line when it fits and contains no comments.
+    fn braced(self, close: &Token, inline: bool) -> Doc<'a> {
+        let inline = inline && !self.has_comments;
+        match self.finish(close.span.start()) {
+            None => Doc::text("{}"),
+            Some(doc) if inline => Doc::text("{")
+                .append(Doc::line().append(doc).nest(INDENT))
+                .append(Doc::line())
+                .append("}")
+                .group(),
+            Some(doc) => Doc::text("{")
+                .append(Doc::hardline().append(doc).nest(INDENT))
+                .append(Doc::hardline())
+                .append("}"),
+        }
+    }
+}
+
+/// Comma separated items, which are printed on one line if they fit, or
+/// otherwise one per line.
+struct List<'p, 'a> {
+    printer: &'p Printer<'a>,
+    first: Option<Position>,
+    items: Vec<(Doc<'a>, Vec<Token>)>,
+}
+
+impl<'a> List<'_, 'a> {
+    fn item(&mut self, span: Span, item: impl FnOnce() -> Doc<'a>) {
+        self.first.get_or_insert(span.start());
+        let doc = self.printer.leading(span.start()).append(item());
+        let trailing = self.printer.comments_through(span.last().line);
+        self.items.push((doc, trailing));
+    }
+
+    /// Finishes the list between the open and close delimiters. Padded lists are
+    /// separated from their delimiters by a space when printed on one line.
+    ///
+    /// A list whose first element was written on the line after the open delimiter
+    /// is always printed one element per line.
+    fn finish(self, open: &Token, close: &Token, padded: bool) -> Doc<'a> {
+        let rest = self.printer.comments_before(close.span.start());
+        let open_text = self.printer.token(open);
+        let close_text = self.printer.token(close);
+        if self.items.is_empty() && rest.is_empty() {
+            return open_text.append(close_text);
+        }
+        let count = self.items.len();
+        let mut body = Doc::nil();
+        let force = self
+            .first
+            .is_some_and(|first| first.line > open.span.start().line);
+        let mut hard_close = !rest.is_empty() || force;
+        for (i, (doc, trailing)) in self.items.into_iter().enumerate() {
+            body = body.append(doc);
+            if i + 1 < count {
+                body = body.append(",");
+            }
+            let mut hard = false;
+            for comment in &trailing {
+                body = body
+                    .append(Doc::space())
+                    .append(self.printer.comment(comment));
+                hard |= !is_inline(comment);
+            }
+            if i + 1 < count {
+                body = body.append(if hard || force {
+                    Doc::hardline()
+                } else {
+                    Doc::line()
+                });
+            } else {
+                hard_close |= hard;
+            }
+        }
+        for (i, comment) in rest.iter().enumerate() {
+            if i > 0 || count > 0 {
+                body = body.append(Doc::hardline());
+            }
+            body = body.append(self.printer.comment(comment));
+        }
+        let pad = if padded { Doc::line() } else { Doc::line_() };
+        open_text
+            .append(pad.clone().append(body).nest(INDENT))
+            .append(if hard_close { Doc::hardline() } else { pad })
+            .append(close_text)
+            .group()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::format;
+
+    macro_rules! test_format {
+        ($name:ident: $source:expr => $expected:expr) => {
+            #[test]
+            fn $name() {
+                let formatted = format($source).unwrap();
+                assert_eq!(formatted, $expected);
+                assert_eq!(
+                    format(&formatted).unwrap(),
+                    formatted,
+                    "formatting is not idempotent"
+                );
+            }
+        };
+    }
+
+    test_format!(format_empty: "\n" => "");
+    test_format!(format_trailing_newlines: "func f x = x\n\n\n" => "func f x = x\n");
+    test_format!(format_spacing: "func  f   x = x+  1\n\n\n\nproc main!(){f!(1,2)}\n" => "func f x = x + 1\n\nproc main!() { f!(1, 2) }\n");
+    test_format!(format_breaks_blocks: "proc main!(){\nf!(1,2);g!()}\n" => "proc main!() {\n  f!(1, 2)\n  g!()\n}\n");
+    test_format!(format_keeps_comments: "# leading\nproc main!() { # trailing\n  let x = 1 # after\n  #- inline -# x\n  # end\n}\n" => "# leading\nproc main!() {\n  # trailing\n  let x = 1 # after\n  #- inline -# x\n  # end\n}\n");
+    test_format!(format_keeps_documentation: "## Docs\nfunc f x = x\n" => "## Docs\nfunc f x = x\n");
+    test_format!(format_pipe_chain: "func f xs = xs |> map (fn x. x + 1) |> filter (fn x. x > 10) |> fold (fn a b. a + b) 0 |> to_string |> print\n" => "func f xs = xs\n  |> map (fn x. x + 1)\n  |> filter (fn x. x > 10)\n  |> fold (fn a b. a + b) 0\n  |> to_string\n  |> print\n");
+    test_format!(format_let_chain: "func f x = let y = x + 1, let z = y * 2, z\n" => "func f x =\n  let y = x + 1,\n  let z = y * 2,\n  z\n");
+    test_format!(format_collections: "proc main!() {\n  let a = [1,2,..xs]\n  let b = [|1,2|]\n  let c = {|1=>2|}\n  let d = [||]\n}\n" => "proc main!() {\n  let a = [1, 2, ..xs]\n  let b = [| 1, 2 |]\n  let c = {| 1 => 2 |}\n  let d = [||]\n}\n");
+    test_format!(format_handlers: "proc main!() { with f!() { when 'a cancel 1\n else yield } }\n" => "proc main!() {\n  with f!() {\n    when 'a cancel 1\n    else yield\n  }\n}\n");
+
+    #[test]
+    fn format_syntax_error() {
+        assert!(format("proc main!( {").is_err());
+    }
+}
@@ -0,0 +1,136 @@
+use super::{Doc, Format, Printer};
+use trilogy_parser::Spanned;
+use trilogy_parser::syntax::*;
+
+impl Format for Pattern {
+    fn format<'a>(&self, printer: &Printer<'a>) -> Doc<'a> {
+        match self {
+            Self::Conjunction(pattern) => pattern
+                .lhs
+                .format(printer)
+                .append(" and ")
+                .append(pattern.rhs.format(printer)),
+            Self::Disjunction(pattern) => pattern
+                .lhs
+                .format(printer)
+                .append(" or ")
+                .append(pattern.rhs.format(printer)),
+            Self::Number(pattern) => pattern.format(printer),
+            Self::Character(pattern) => pattern.format(printer),
+            Self::String(pattern) => pattern.format(printer),
+            Self::Bits(pattern) => pattern.format(printer),
+            Self::Boolean(pattern) => pattern.format(printer),
+            Self::Unit(pattern) => pattern.format(printer),
+            Self::Atom(pattern) => pattern.format(printer),
+            Self::Wildcard(token) => printer.token(token),
+            Self::Negative(pattern) => Doc::text("-").append(pattern.pattern.format(printer)),
+            Self::Glue(pattern) => pattern
+                .lhs
+                .format(printer)
+                .append(" <> ")
+                .append(pattern.rhs.format(printer)),
+            Self::Typeof(pattern) => Doc::text("typeof ").append(pattern.pattern.format(printer)),
+            Self::Type(pattern) => Doc::text("is ").append(pattern.r#type.format(printer)),
+            Self::Struct(pattern) => pattern
+                .atom
+                .format(printer)
+                .append("(")
+                .append(pattern.pattern.format(printer))
+                .append(")"),
+            Self::Tuple(pattern) => {
+                let cons = if pattern.lhs.span().end() == pattern.cons.span.start() {
+                    Doc::text(":")
+                } else {
+                    Doc::text(" : ")
+                };
+                pattern
+                    .lhs
+                    .format(printer)
+                    .append(cons)
+                    .append(pattern.rhs.format(printer))
+            }
+            Self::Array(pattern) => pattern.format(printer),
+            Self::Set(pattern) => pattern.format(printer),
+            Self::Record(pattern) => pattern.format(printer),
+            Self::Pinned(pattern) => Doc::text("^").append(pattern.identifier.format(printer)),
+            Self::Binding(pattern) => match &pattern.r#mut {
+                Some(..) => Doc::text("mut ").append(pattern.identifier.format(printer)),
+                None => pattern.identifier.format(printer),
+            },
+            Self::Parenthesized(pattern) => Doc::text("(")
+                .append(pattern.pattern.format(printer))
+                .append(")"),
+        }
+    }
+}
+
+impl Format for TypeExpression {
+    fn format<'a>(&self, printer: &Printer<'a>) -> Doc<'a> {
+        match self {
+            Self::Reference(identifier) => identifier.format(printer),
+            Self::Application(application) => application
+                .function
+                .format(printer)
+                .append(Doc::space())
+                .append(application.argument.format(printer)),
+            Self::Parenthesized(parenthesized) => Doc::text("(")
+                .append(parenthesized.r#type.format(printer))
+                .append(")"),
+        }
+    }
+}
+
+impl Format for RestPattern {
+    fn format<'a>(&self, printer: &Printer<'a>) -> Doc<'a> {
+        match &self.pattern {
+            Some(pattern) => Doc::text("..").append(pattern.format(printer)),
+            None => Doc::text(".."),
+        }
+    }
+}
+
+impl Format for ArrayPattern {
+    fn format<'a>(&self, printer: &Printer<'a>) -> Doc<'a> {
+        let mut list = printer.list();
+        for pattern in &self.head {
+            list.item(pattern.span(), || pattern.format(printer));
+        }
+        if let Some(rest) = &self.rest {
+            list.item(rest.span(), || rest.format(printer));
+        }
+        for pattern in &self.tail {
+            list.item(pattern.span(), || pattern.format(printer));
+        }
+        list.finish(&self.open_bracket, &self.close_bracket, false)
+    }
+}
+
+impl Format for SetPattern {
+    fn format<'a>(&self, printer: &Printer<'a>) -> Doc<'a> {
+        let mut list = printer.list();
+        for pattern in &self.elements {
+            list.item(pattern.span(), || pattern.format(printer));
+        }
+        if let Some(rest) = &self.rest {
+            list.item(rest.span(), || rest.format(printer));
+        }
+        list.finish(&self.open_bracket_pipe, &self.close_bracket_pipe, true)
+    }
+}
+
+impl Format for RecordPattern {
+    fn format<'a>(&self, printer: &Printer<'a>) -> Doc<'a> {
+        let mut list = printer.list();
+        for (key, value) in &self.elements {
+            list.item(key.span().union(value.span()), || {
+                key.format(printer)
+                    .append(" => ")
+                    .append(value.format(printer))
+            });
+        }
+        if let Some(rest) = &self.rest {
+            list.item(rest.span(), || rest.format(printer));
+        }
+        list.finish(&self.open_brace_pipe, &self.close_brace_pipe, true)
+    }
+}
@@ -0,0 +1,91 @@
+use super::{Doc, Format, INDENT, Printer};
+use trilogy_parser::Spanned;
+use trilogy_parser::syntax::*;
+
+impl Format for Query {
+    fn format<'a>(&self, printer: &Printer<'a>) -> Doc<'a> {
+        match self {
+            Self::Disjunction(..) | Self::Conjunction(..) => chain(self, printer, true),
+            Self::Implication(query) => Doc::text("if ")
+                .append(query.lhs.format(printer))
+                .append(" then ")
+                .append(query.rhs.format(printer)),
+            Self::Alternative(query) => query
+                .lhs
+                .format(printer)
+                .append(" else ")
+                .append(query.rhs.format(printer)),
+            Self::Direct(query) => query
+                .pattern
+                .format(printer)
+                .append(" = ")
+                .append(query.expression.format(printer)),
+            Self::Element(query) => query
+                .pattern
+                .format(printer)
+                .append(" in ")
+                .append(query.expression.format(printer)),
+            Self::Parenthesized(query) => Doc::text("(")
+                .append(query.query.format(printer))
+                .append(")"),
+            Self::Lookup(query) => query.format(printer),
+            Self::Pass(..) => Doc::text("pass"),
+            Self::End(..) => Doc::text("end"),
+            Self::Is(query) => Doc::text("is ").append(query.expression.format(printer)),
+            Self::Not(query) => Doc::text("not ").append(query.query.format(printer)),
+        }
+    }
+}
+
+/// Prints a sequence of `and` or `or` queries, which are broken before each operator
+/// when they do not fit or were written that way.
+pub(super) fn chain<'a>(query: &Query, printer: &Printer<'a>, nest: bool) -> Doc<'a> {
+    let (operator, conjunction) = match query {
+        Query::Conjunction(..) => ("and ", true),
+        Query::Disjunction(..) => ("or ", false),
+        _ => return query.format(printer),
+    };
+    let mut operands = vec![];
+    flatten(query, conjunction, &mut operands);
+    let separator = if printer.is_multiline(query.span()) {
+        Doc::hardline()
+    } else {
+        Doc::line()
+    };
+    let doc = operands[0].format(printer);
+    let mut rest = Doc::nil();
+    for operand in &operands[1..] {
+        rest = rest
+            .append(separator.clone())
+            .append(printer.leading(operand.span().start()))
+            .append(operator)
+            .append(operand.format(printer));
+    }
+    let rest = if nest { rest.nest(INDENT) } else { rest };
+    doc.append(rest).group()
+}
+
+fn flatten<'q>(query: &'q Query, conjunction: bool, operands: &mut Vec<&'q Query>) {
+    match query {
+        Query::Conjunction(query) if conjunction => {
+            flatten(&query.lhs, conjunction, operands);
+            flatten(&query.rhs, conjunction, operands);
+        }
+        Query::Disjunction(query) if !conjunction => {
+            flatten(&query.lhs, conjunction, operands);
+            flatten(&query.rhs, conjunction, operands);
+        }
+        _ => operands.push(query),
+    }
+}
+
+impl Format for Lookup {
+    fn format<'a>(&self, printer: &Printer<'a>) -> Doc<'a> {
+        let path = self.path.format(printer);
+        let mut list = printer.list();
+        for pattern in &self.patterns {
+            list.item(pattern.span(), || pattern.format(printer));
+        }
+        path.append(list.finish(&self.open_paren, &self.close_paren, false))
+    }
+}
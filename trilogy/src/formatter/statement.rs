@@ -0,0 +1,141 @@
+use super::{Doc, Format, Printer};
+use trilogy_parser::Spanned;
+use trilogy_parser::syntax::*;
+
+impl Format for Block {
+    fn format<'a>(&self, printer: &Printer<'a>) -> Doc<'a> {
+        let mut lines = printer.lines();
+        for statement in &self.statements {
+            lines.item(statement.span(), || statement.format(printer));
+        }
+        lines.braced(&self.close_brace, !printer.is_multiline(self.span))
+    }
+}
+
+impl Format for Statement {
+    fn format<'a>(&self, printer: &Printer<'a>) -> Doc<'a> {
+        match self {
+            Self::Let(statement) => statement.format(printer),
+            Self::Assignment(statement) => statement.format(printer),
+            Self::FunctionAssignment(statement) => statement.format(printer),
+            Self::If(statement) => statement.format(printer),
+            Self::Match(statement) => statement.format(printer),
+            Self::While(statement) => statement.format(printer),
+            Self::For(statement) => statement.format(printer),
+            Self::Defer(statement) => statement.format(printer),
+            Self::Using(statement) => statement.format(printer),
+            Self::Assert(statement) => statement.format(printer),
+            Self::Expression(statement) => statement.format(printer),
+            Self::Block(statement) => statement.format(printer),
+        }
+    }
+}
+
+impl Format for LetStatement {
+    fn format<'a>(&self, printer: &Printer<'a>) -> Doc<'a> {
+        Doc::text("let ").append(self.query.format(printer))
+    }
+}
+
+impl Format for AssignmentStatement {
+    fn format<'a>(&self, printer: &Printer<'a>) -> Doc<'a> {
+        self.lhs
+            .format(printer)
+            .append(Doc::space())
+            .append(printer.slice(self.strategy.span()))
+            .append(Doc::space())
+            .append(self.rhs.format(printer))
+    }
+}
+
+impl Format for FunctionAssignment {
+    fn format<'a>(&self, printer: &Printer<'a>) -> Doc<'a> {
+        let mut doc = self
+            .lhs
+            .format(printer)
+            .append(Doc::space())
+            .append(self.function.format(printer));
+        for argument in &self.arguments {
+            doc = doc.append(Doc::space()).append(argument.format(printer));
+        }
+        doc
+    }
+}
+
+impl Format for WhileStatement {
+    fn format<'a>(&self, printer: &Printer<'a>) -> Doc<'a> {
+        Doc::text("while ")
+            .append(self.condition.format(printer))
+            .append(Doc::space())
+            .append(self.body.format(printer))
+    }
+}
+
+impl Format for ForStatement {
+    fn format<'a>(&self, printer: &Printer<'a>) -> Doc<'a> {
+        let head = Doc::text("for ").append(self.query.format(printer));
+        let head = match &self.accumulator {
+            Some(accumulator) => head
+                .append(" where ")
+                .append(accumulator.identifier.format(printer))
+                .append(" = ")
+                .append(accumulator.initial.format(printer)),
+            None => head,
+        };
+        head.append(Doc::space()).append(self.body.format(printer))
+    }
+}
+
+impl Format for DeferStatement {
+    fn format<'a>(&self, printer: &Printer<'a>) -> Doc<'a> {
+        Doc::text("defer ").append(self.body.format(printer))
+    }
+}
+
+impl Format for UsingStatement {
+    fn format<'a>(&self, printer: &Printer<'a>) -> Doc<'a> {
+        let head = match &self.head {
+            Some(head) => head.format(printer).append(Doc::space()),
+            None => Doc::nil(),
+        };
+        head.append("using ")
+            .append(self.expression.format(printer))
+    }
+}
+
+impl Format for AssertStatement {
+    fn format<'a>(&self, printer: &Printer<'a>) -> Doc<'a> {
+        let message = match &self.message {
+            Some(message) => message.message.format(printer).append(" as "),
+            None => Doc::nil(),
+        };
+        Doc::text("assert ")
+            .append(message)
+            .append(self.assertion.format(printer))
+    }
+}
+
+impl Format for DoHead {
+    fn format<'a>(&self, printer: &Printer<'a>) -> Doc<'a> {
+        match &self.parameter_list {
+            Some(parameter_list) => Doc::text("do").append(parameter_list.format(printer)),
+            None => Doc::text("do"),
+        }
+    }
+}
+
+impl Format for ParameterList {
+    fn format<'a>(&self, printer: &Printer<'a>) -> Doc<'a> {
+        let mut list = printer.list();
+        for param in &self.parameters {
+            list.item(param.span(), || param.format(printer));
+        }
+        list.finish(&self.open_paren, &self.close_paren, false)
+    }
+}
+
+impl Format for Guard {
+    fn format<'a>(&self, printer: &Printer<'a>) -> Doc<'a> {
+        Doc::text("if ").append(self.expression.format(printer))
+    }
+}
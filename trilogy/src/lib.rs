@@ -14,7 +14,7 @@
 //! ```no_run
 //! use trilogy::Trilogy;
 //! let trilogy = Trilogy::from_file("./path/to/main.tri").unwrap();
-//! let exit_value = trilogy.run().unwrap();
+//! let exit_code = trilogy.run().exit_code();
 //! ```
 //!
 //! For more advanced usage, the [`Builder`][] allows for customizing the module
@@ -23,7 +23,18 @@
 //! ```no_run
 //! use trilogy::Builder;
 //! let trilogy = Builder::new().build_from_source("./path/to/main.tri").unwrap();
-//! let exit_value = trilogy.run().unwrap();
+//! let exit_code = trilogy.run().exit_code();
+//! ```
+//!
+//! Exported procedures, functions, and rules can also be called directly, passing
+//! [`Value`][]s in and getting them back out.
+//!
+//! ```no_run
+//! use trilogy::{Trilogy, Value};
+//! let trilogy = Trilogy::from_file("./path/to/main.tri").unwrap();
+//! let parsed = trilogy
+//!     .apply_function(&["util", "parse"][..], Value::String("1 + 2".to_owned()))
+//!     .unwrap();
 //! ```
 
 #[path = "stdlib/mod.rs"]
@@ -31,9 +42,23 @@ mod stdlib;
 
 mod ariadne;
 mod cache;
+mod formatter;
+mod line_index;
 mod location;
+mod manifest;
+mod resolver;
+mod runtime;
 pub(crate) mod trilogy;
 
 pub use cache::{Cache, FileSystemCache, NoopCache};
+pub use formatter::format;
 pub use location::Location;
-pub use trilogy::{Builder, Report, Trilogy};
+pub use manifest::Manifest;
+#[cfg(feature = "http")]
+pub use resolver::HttpResolver;
+pub use resolver::{FileSystemResolver, Resolver, ResolverError};
+pub use runtime::{InvalidName, NativeModule, NativeModuleBuilder};
+pub use trilogy::{
+    Builder, CallError, Callable, ColorChoice, Continuation, Diagnostic, EffectResponse, Label,
+    LockfileMode, Outcome, Report, Severity, Termination, Trilogy, Value,
+};
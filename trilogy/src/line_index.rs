@@ -0,0 +1,84 @@
+use source_span::{DefaultMetrics, Position, Span};
+use std::ops::Range;
+
+/// The metrics by which positions in Trilogy source code are counted.
+const METRICS: DefaultMetrics = DefaultMetrics::with_tab_stop(4);
+
+/// Converts positions in a source file, which count characters (with tab stops), to
+/// byte offsets.
+pub(crate) struct LineIndex<'a> {
+    source: &'a str,
+    /// The byte offset at which each line starts.
+    lines: Vec<usize>,
+}
+
+impl<'a> LineIndex<'a> {
+    pub(crate) fn new(source: &'a str) -> Self {
+        let lines = std::iter::once(0)
+            .chain(source.match_indices('\n').map(|(index, _)| index + 1))
+            .collect();
+        Self { source, lines }
+    }
+
+    /// The byte offset of a position, or `None` if the source does not have that line.
+    ///
+    /// Positions past the end of their line are at the end of that line.
+    pub(crate) fn offset(&self, position: Position) -> Option<usize> {
+        let line_start = *self.lines.get(position.line)?;
+        let line = self.source[line_start..].split('\n').next()?;
+        let mut current = Position::new(position.line, 0);
+        for (index, ch) in line.char_indices() {
+            if current.column >= position.column {
+                return Some(line_start + index);
+            }
+            current = current.next(ch, &METRICS);
+        }
+        Some(line_start + line.len())
+    }
+
+    /// The byte offsets of a span, or `None` if the source does not have its lines.
+    pub(crate) fn range(&self, span: Span) -> Option<Range<usize>> {
+        Some(self.offset(span.start())?..self.offset(span.end())?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::LineIndex;
+    use source_span::Position;
+
+    fn offset(source: &str, position: Position) -> Option<usize> {
+        LineIndex::new(source).offset(position)
+    }
+
+    #[test]
+    fn offset_first_line() {
+        assert_eq!(offset("let x = 1", Position::new(0, 4)), Some(4));
+    }
+
+    #[test]
+    fn offset_later_line() {
+        assert_eq!(offset("ab\ncd\nef", Position::new(2, 1)), Some(7));
+    }
+
+    #[test]
+    fn offset_multibyte() {
+        assert_eq!(offset("é = x", Position::new(0, 1)), Some(2));
+    }
+
+    #[test]
+    fn offset_tab() {
+        assert_eq!(offset("\tx", Position::new(0, 4)), Some(1));
+    }
+
+    #[test]
+    fn offset_end_of_line() {
+        assert_eq!(offset("ab\ncd", Position::new(0, 5)), Some(2));
+    }
+
+    #[test]
+    fn offset_end_of_source() {
+        assert_eq!(offset("ab\n", Position::new(1, 0)), Some(3));
+        assert_eq!(offset("ab", Position::new(3, 0)), None);
+    }
+}
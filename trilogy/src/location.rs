@@ -2,6 +2,7 @@ use std::{
     borrow::Borrow,
     fmt::Display,
     path::{Path, PathBuf},
+    str::FromStr,
 };
 use url::Url;
 
@@ -81,6 +82,21 @@ impl Location {
     pub fn to_local_path(&self) -> Option<PathBuf> {
         self.0.to_file_path().ok()
     }
+
+    /// If this `Location` represents a file on the Internet, returns a relative path at which
+    /// a local copy of that file may be stored, made of its host (and port, if not the default)
+    /// followed by its path. Returns `None` otherwise.
+    pub(crate) fn remote_path(&self) -> Option<PathBuf> {
+        if !matches!(self.0.scheme(), "http" | "https") {
+            return None;
+        }
+        let host = self.0.host()?;
+        let dir = match self.0.port() {
+            Some(port) => format!("{host}:{port}"),
+            None => host.to_string(),
+        };
+        Some(Path::new(&dir).join(self.0.path().trim_start_matches('/')))
+    }
 }
 
 impl From<Url> for Location {
@@ -109,6 +125,23 @@ impl From<Url> for Location {
     }
 }
 
+impl FromStr for Location {
+    type Err = url::ParseError;
+
+    /// Parses a Location from an absolute URL.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use trilogy::Location;
+    /// let location: Location = "https://example.com/lib/../main.tri".parse().unwrap();
+    /// assert_eq!(location.to_string(), "https://example.com/main.tri");
+    /// ```
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(Self::from(s.parse::<Url>()?))
+    }
+}
+
 impl AsRef<Url> for Location {
     fn as_ref(&self) -> &Url {
         &self.0
@@ -183,6 +216,32 @@ mod tests {
         );
     }
 
+    #[test]
+    fn location_from_str() {
+        let location: Location = "corp:/a/b/./c".parse().unwrap();
+        assert_eq!(location, Location::absolute("corp:/a/b/c".parse().unwrap()));
+        assert_eq!(
+            location.relative("../d"),
+            Location::absolute("corp:/a/d".parse().unwrap())
+        );
+    }
+
+    #[test]
+    fn location_remote_path() {
+        let location: Location = "https://example.com:8080/lib/util.tri".parse().unwrap();
+        assert_eq!(
+            location.remote_path(),
+            Some(PathBuf::from("example.com:8080/lib/util.tri"))
+        );
+        let location: Location = "https://example.com/util.tri".parse().unwrap();
+        assert_eq!(
+            location.remote_path(),
+            Some(PathBuf::from("example.com/util.tri"))
+        );
+        let location: Location = "file:///home/trilogy/main.tri".parse().unwrap();
+        assert_eq!(location.remote_path(), None);
+    }
+
     #[test]
     fn library() {
         let location = Location::library("std").unwrap();
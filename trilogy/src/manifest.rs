@@ -0,0 +1,155 @@
+use crate::location::Location;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::{fs, io};
+use url::Url;
+
+/// A project manifest, read from a `trilogy.toml` file.
+///
+/// The manifest declares the entrypoint of a project, the prefixes of the modules
+/// whose tests are run, and the project's dependencies, which modules may import
+/// by name using locators like `"dep:parsers"`.
+///
+/// ```toml
+/// entrypoint = "src/main.tri"
+/// test-prefixes = ["file:"]
+///
+/// [dependencies]
+/// parsers = "https://example.com/parsers/v1.2.0/main.tri"
+/// util = "./lib/util.tri"
+/// ```
+///
+/// Paths in the manifest are relative to the directory containing it.
+#[derive(Clone, Debug)]
+pub struct Manifest {
+    path: PathBuf,
+    entrypoint: Option<PathBuf>,
+    test_prefixes: Option<Vec<String>>,
+    dependencies: BTreeMap<String, Location>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+struct ManifestFile {
+    entrypoint: Option<PathBuf>,
+    test_prefixes: Option<Vec<String>>,
+    #[serde(default)]
+    dependencies: BTreeMap<String, String>,
+}
+
+impl Manifest {
+    /// The name of the manifest file.
+    pub const FILE_NAME: &'static str = "trilogy.toml";
+
+    /// Reads the manifest at the given path.
+    ///
+    /// # Errors
+    ///
+    /// If the file cannot be read, or is not a valid manifest.
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        let path = std::path::absolute(path)?;
+        let invalid = |message: String| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("invalid manifest `{}`: {message}", path.display()),
+            )
+        };
+        let file: ManifestFile = toml::from_str(&fs::read_to_string(&path)?)
+            .map_err(|error| invalid(error.message().to_owned()))?;
+        let dir = path.parent().unwrap_or(Path::new("/"));
+        let base = Url::from_directory_path(dir)
+            .map_err(|()| invalid("manifest is not in a local directory".to_owned()))?;
+        let dependencies = file
+            .dependencies
+            .into_iter()
+            .map(|(name, locator)| match base.join(&locator) {
+                Ok(url) => Ok((name, Location::absolute(url))),
+                Err(error) => Err(invalid(format!(
+                    "dependency `{name}` has invalid location `{locator}`: {error}"
+                ))),
+            })
+            .collect::<io::Result<_>>()?;
+        Ok(Self {
+            entrypoint: file.entrypoint.map(|entrypoint| dir.join(entrypoint)),
+            test_prefixes: file.test_prefixes,
+            dependencies,
+            path,
+        })
+    }
+
+    /// Finds and reads the manifest in the given directory or the nearest of its
+    /// ancestors, if there is one.
+    ///
+    /// # Errors
+    ///
+    /// If a manifest is found but cannot be read, or is not a valid manifest.
+    pub fn discover(dir: impl AsRef<Path>) -> io::Result<Option<Self>> {
+        let dir = std::path::absolute(dir)?;
+        for ancestor in dir.ancestors() {
+            let path = ancestor.join(Self::FILE_NAME);
+            if path.is_file() {
+                return Self::load(path).map(Some);
+            }
+        }
+        Ok(None)
+    }
+
+    /// The path to this manifest's file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The path to the entrypoint of the project, if declared.
+    pub fn entrypoint(&self) -> Option<&Path> {
+        self.entrypoint.as_deref()
+    }
+
+    /// The prefixes of the modules whose tests are run, if declared.
+    pub fn test_prefixes(&self) -> Option<&[String]> {
+        self.test_prefixes.as_deref()
+    }
+
+    /// The location of the dependency with the given name, if declared.
+    pub fn dependency(&self, name: &str) -> Option<&Location> {
+        self.dependencies.get(name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Manifest;
+
+    #[test]
+    fn manifest_load() {
+        let dir = std::env::temp_dir().join(format!("trilogy-manifest-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(Manifest::FILE_NAME);
+        std::fs::write(
+            &path,
+            r#"
+            entrypoint = "src/main.tri"
+            test-prefixes = ["file:"]
+
+            [dependencies]
+            parsers = "https://example.com/parsers/main.tri"
+            util = "./lib/util.tri"
+            "#,
+        )
+        .unwrap();
+        let manifest = Manifest::discover(dir.join("src")).unwrap().unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(manifest.entrypoint(), Some(&*dir.join("src/main.tri")));
+        assert_eq!(manifest.test_prefixes(), Some(&["file:".to_owned()][..]));
+        assert_eq!(
+            manifest.dependency("parsers").unwrap().to_string(),
+            "https://example.com/parsers/main.tri"
+        );
+        assert_eq!(
+            manifest.dependency("util").unwrap().to_local_path(),
+            Some(dir.join("lib/util.tri"))
+        );
+        assert!(manifest.dependency("missing").is_none());
+    }
+}
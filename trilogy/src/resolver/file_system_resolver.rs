@@ -0,0 +1,13 @@
+use super::{Resolver, ResolverError};
+use crate::location::Location;
+use std::fs;
+
+/// Resolves modules from the local file system, for locations with the `file` scheme.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct FileSystemResolver;
+
+impl Resolver for FileSystemResolver {
+    fn resolve(&self, location: &Location) -> Result<Option<String>, ResolverError> {
+        Ok(Some(fs::read_to_string(location.as_ref().path())?))
+    }
+}
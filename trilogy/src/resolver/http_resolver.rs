@@ -0,0 +1,28 @@
+use super::{Resolver, ResolverError};
+use crate::location::Location;
+use reqwest::blocking::Client;
+
+/// Resolves modules by downloading them from the Internet, for locations with the
+/// `http` or `https` schemes.
+///
+/// Modules resolved this way are saved to the module cache.
+#[derive(Clone, Debug, Default)]
+pub struct HttpResolver {
+    client: Client,
+}
+
+impl Resolver for HttpResolver {
+    fn resolve(&self, location: &Location) -> Result<Option<String>, ResolverError> {
+        let source = self
+            .client
+            .get(location.as_ref().clone())
+            .header("Accept", "text/x-trilogy")
+            .send()?
+            .text()?;
+        Ok(Some(source))
+    }
+
+    fn is_cached(&self) -> bool {
+        true
+    }
+}
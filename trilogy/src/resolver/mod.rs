@@ -0,0 +1,33 @@
+use crate::location::Location;
+
+mod file_system_resolver;
+#[cfg(feature = "http")]
+mod http_resolver;
+
+pub use file_system_resolver::FileSystemResolver;
+#[cfg(feature = "http")]
+pub use http_resolver::HttpResolver;
+
+/// An error that occurs when resolving a module.
+pub type ResolverError = Box<dyn std::error::Error + Send + Sync + 'static>;
+
+/// A type that can be used to load the source code of Trilogy modules from their
+/// locations during compilation.
+///
+/// Resolvers are registered on the [`Builder`][crate::Builder] for the URL schemes
+/// they are responsible for. Modules are loaded concurrently, so a resolver may be
+/// asked to resolve several modules at once from different threads.
+pub trait Resolver: Send + Sync {
+    /// Loads the source code for the module at the Location.
+    ///
+    /// Returns `None` if there is no module at that location.
+    fn resolve(&self, location: &Location) -> Result<Option<String>, ResolverError>;
+
+    /// Should modules loaded by this resolver be saved to the module cache?
+    ///
+    /// Modules that are expensive to resolve, such as those downloaded from the
+    /// Internet, should be cached. By default, modules are not cached.
+    fn is_cached(&self) -> bool {
+        false
+    }
+}
@@ -1,65 +1,80 @@
-use crate::Runtime;
-use std::collections::HashMap;
-use trilogy_vm::{Error, Execution, Native, NativeFunction, Value};
+use std::fmt::{self, Display, Write};
+use trilogy_llvm::{NativeProcedure, Outcome, Value};
+use trilogy_scanner::{Scanner, TokenType};
 
-/// A module of native functions.
+/// A module of native procedures.
 ///
 /// Native modules are provided to Trilogy at build time, allowing native Rust functions
 /// to be imported into Trilogy programs by referencing them through an imported module.
 ///
-/// Native modules themselves do not have names, but are installed into other modules
-/// with names, or into the Trilogy runtime at a module location.
-///
-/// It is unlikely (and not recommended) to create a native module manually.
-/// More likely one will be created by using the [`#[module]`][trilogy_derive::module]
-/// proc macro to create a `NativeModule` from a Rust module.
-#[derive(Clone, Debug)]
+/// Native modules themselves do not have names, but are installed into the Trilogy
+/// runtime at a module location using [`Builder::native_module`][crate::Builder::native_module].
+#[derive(Clone, Debug, Default)]
 pub struct NativeModule {
-    pub(crate) items: HashMap<&'static str, Native>,
+    procedures: Vec<(String, usize, NativeProcedure)>,
+}
+
+impl NativeModule {
+    /// Generates the Trilogy source code of this module, in which each procedure calls
+    /// its native implementation by id, starting from `first_id`.
+    pub(crate) fn source(&self, first_id: usize) -> String {
+        let mut source = String::from("import \"trilogy:native\" as native\n");
+        for (id, (name, arity, _)) in self.procedures.iter().enumerate() {
+            let parameters = (0..*arity)
+                .map(|i| format!("arg{i}"))
+                .collect::<Vec<_>>()
+                .join(", ");
+            write!(
+                source,
+                "\nexport {name}\nproc {name}!({parameters}) {{\n  return native::call!({}, [{parameters}])\n}}\n",
+                first_id + id,
+            )
+            .unwrap();
+        }
+        source
+    }
+
+    /// The implementations of this module's procedures, in order of their ids.
+    pub(crate) fn procedures(&self) -> impl Iterator<Item = NativeProcedure> + '_ {
+        self.procedures
+            .iter()
+            .map(|(_, _, procedure)| procedure.clone())
+    }
 }
 
 /// Builder for native modules.
-#[derive(Clone)]
+#[derive(Clone, Debug, Default)]
 pub struct NativeModuleBuilder {
     inner: NativeModule,
 }
 
-impl Default for NativeModuleBuilder {
-    fn default() -> Self {
-        Self {
-            inner: NativeModule {
-                items: Default::default(),
-            },
-        }
-    }
-}
-
 impl NativeModuleBuilder {
     /// Create a new empty module builder.
     pub fn new() -> Self {
         Self::default()
     }
 
-    /// Add a native procedure or module to this module under a given name.
-    ///
-    /// Native functions are typically created using the [`#[proc]`][trilogy_derive::proc]
-    /// or [`#[module]`][trilogy_derive::module] attribute macros.
+    /// Add a native procedure of `N` parameters to this module under a given name.
     ///
     /// The provided name will be used to reference the procedure from a Trilogy program
-    /// so it must be a valid name for use as an identifier in Trilogy, otherwise it will
-    /// not be usable.
+    /// so it must be a valid identifier in Trilogy, and not a keyword.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`InvalidName`][] if the name is not a valid identifier.
     ///
     /// # Examples
     ///
     /// ```
-    /// # use trilogy::{NativeModuleBuilder, proc, Runtime};
-    /// #[proc]
-    /// fn hello(rt: Runtime) -> trilogy::Result<()> {
-    ///     rt.r#return("hello")
-    /// }
-    ///
+    /// # use trilogy::{NativeModuleBuilder, Outcome, Value};
     /// let native_module = NativeModuleBuilder::new()
-    ///     .add_item("hello", hello)
+    ///     .add_procedure("hello", |[name]: [Value; 1]| {
+    ///         let Value::String(name) = name else {
+    ///             return Outcome::Return(Value::Unit);
+    ///         };
+    ///         Outcome::Return(Value::String(format!("hello {name}")))
+    ///     })
+    ///     .unwrap()
     ///     .build();
     /// ```
     ///
@@ -67,14 +82,31 @@ impl NativeModuleBuilder {
     /// procedure could be called as follows:
     ///
     /// ```trilogy
-    /// module native from "trilogy:module"
+    /// import "trilogy:module" as native
     /// proc main!() {
-    ///     let hello = native::hello!()
+    ///     let hello = native::hello!("world")
     /// }
     /// ```
-    pub fn add_item<N: NativeFunction + 'static>(mut self, name: &'static str, proc: N) -> Self {
-        self.inner.items.insert(name, proc.into());
-        self
+    pub fn add_procedure<const N: usize>(
+        mut self,
+        name: &str,
+        procedure: impl Fn([Value; N]) -> Outcome + 'static,
+    ) -> Result<Self, InvalidName> {
+        if !is_identifier(name) {
+            return Err(InvalidName(name.to_owned()));
+        }
+        let procedure = NativeProcedure::new(move |arguments| {
+            procedure(
+                arguments
+                    .try_into()
+                    .expect("native procedures are called with the right number of arguments"),
+            )
+        });
+        self.inner
+            .procedures
+            .retain(|(existing, _, _)| existing != name);
+        self.inner.procedures.push((name.to_owned(), N, procedure));
+        Ok(self)
     }
 
     /// Finish building this native module.
@@ -83,23 +115,67 @@ impl NativeModuleBuilder {
     }
 }
 
-impl NativeFunction for NativeModule {
-    fn arity(&self) -> usize {
-        2 // the symbol + the module key
+/// The error returned when a native procedure is given a name that is not a valid
+/// identifier in Trilogy.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InvalidName(String);
+
+impl Display for InvalidName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "native procedure name {:?} is not a valid Trilogy identifier",
+            self.0
+        )
+    }
+}
+
+impl std::error::Error for InvalidName {}
+
+/// Whether the whole name is scanned as a single identifier, which excludes keywords.
+fn is_identifier(name: &str) -> bool {
+    let mut tokens = Scanner::new(name).filter(|token| {
+        !matches!(
+            token.token_type,
+            TokenType::StartOfFile | TokenType::EndOfFile
+        )
+    });
+    matches!(
+        (tokens.next(), tokens.next()),
+        (Some(token), None)
+            if token.token_type == TokenType::Identifier
+                && token.value.as_ref().and_then(|value| value.as_str()) == Some(name)
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn add(name: &str) -> Result<NativeModuleBuilder, InvalidName> {
+        NativeModuleBuilder::new()
+            .add_procedure(name, |[]: [Value; 0]| Outcome::Return(Value::Unit))
     }
 
-    fn call(&mut self, ex: &mut Execution, input: Vec<Value>) -> Result<(), Error> {
-        let runtime = Runtime::new(ex);
-        let atom = runtime.unlock_module(input)?;
-        if let Some(proc) = self.items.get(atom.as_ref()) {
-            return runtime.r#return(proc.clone());
+    #[test]
+    fn accepts_identifiers() {
+        for name in ["hello", "_private", "HELLO_world", "read2", "matches"] {
+            assert!(add(name).is_ok(), "{name} should be accepted");
         }
+    }
 
-        let symbol_list = self
-            .items
-            .keys()
-            .map(|name| Value::from(runtime.atom(name)))
-            .collect::<Vec<_>>();
-        Err(runtime.unresolved_import(atom, symbol_list))
+    #[test]
+    fn rejects_invalid_names() {
+        for name in [
+            "",
+            "return",
+            "hello world",
+            "2fast",
+            "push=",
+            "kebab-case",
+            "x!() {}\nproc y",
+        ] {
+            assert_eq!(add(name).err(), Some(InvalidName(name.to_owned())));
+        }
     }
 }
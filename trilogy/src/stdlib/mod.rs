@@ -9,6 +9,10 @@ where
             Location::library("io").unwrap(),
             include_str!("./io.tri").to_owned(),
         )
+        .source_module(
+            Location::library("env").unwrap(),
+            include_str!("./env.tri").to_owned(),
+        )
         .source_module(
             Location::library("debug").unwrap(),
             include_str!("./debug.tri").to_owned(),
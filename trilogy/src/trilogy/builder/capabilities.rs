@@ -0,0 +1,190 @@
+use super::Error;
+use super::loader::Module;
+use super::report::ReportBuilder;
+use crate::location::Location;
+use std::collections::HashMap;
+use trilogy_parser::Spanned;
+use trilogy_parser::syntax::{Definition, DefinitionItem};
+
+/// Restrictions on what the modules of a program may do, so that untrusted programs
+/// can be built safely.
+///
+/// The `trilogy:` libraries are trusted, and are never restricted.
+#[derive(Clone, Debug, Default)]
+pub(super) struct Capabilities {
+    /// The location prefixes that imported modules must match, or `None` if any module
+    /// may be imported. A prefix only matches whole path segments, so `file:///srv/rules`
+    /// allows `file:///srv/rules/main.tri` but not `file:///srv/rules-old/main.tri`.
+    pub allowed_imports: Option<Vec<String>>,
+    /// Whether `extern` procedure declarations are rejected.
+    pub deny_extern: bool,
+    /// The locations of the native modules, which alone import `trilogy:native`.
+    pub native_modules: Vec<Location>,
+}
+
+fn is_trusted(location: &Location) -> bool {
+    location.as_ref().scheme() == "trilogy"
+}
+
+impl Capabilities {
+    /// Whether the module at `from` may import the module at `location`.
+    ///
+    /// When imports are restricted, `trilogy:native` is never allowed by a prefix, as it
+    /// calls native procedures by id, bypassing the native modules that wrap them.
+    pub fn allows_import(&self, from: &Location, location: &Location) -> bool {
+        let Some(allowed) = &self.allowed_imports else {
+            return true;
+        };
+        if is_trusted(from) {
+            return true;
+        }
+        if *location == Location::library("native").unwrap() {
+            return self.native_modules.contains(from);
+        }
+        let location = location.to_string();
+        allowed
+            .iter()
+            .any(|prefix| match location.strip_prefix(prefix.as_str()) {
+                Some(rest) => {
+                    rest.is_empty() || rest.starts_with('/') || prefix.ends_with(['/', ':'])
+                }
+                None => false,
+            })
+    }
+
+    /// Reports every `extern` procedure declared outside the `trilogy:` libraries, if they
+    /// are denied.
+    pub fn check_externs<E: std::error::Error>(
+        &self,
+        modules: &HashMap<Location, Module>,
+        report: &mut ReportBuilder<E>,
+    ) {
+        fn check<E: std::error::Error>(
+            location: &Location,
+            definitions: &[Definition],
+            report: &mut ReportBuilder<E>,
+        ) {
+            for definition in definitions {
+                match &definition.item {
+                    DefinitionItem::ExternalProcedure(external) => {
+                        report.error(Error::forbidden_extern(location.clone(), external.span()))
+                    }
+                    DefinitionItem::Type(module) => check(location, &module.definitions, report),
+                    _ => {}
+                }
+            }
+        }
+
+        if !self.deny_extern {
+            return;
+        }
+        let mut locations: Vec<_> = modules
+            .keys()
+            .filter(|location| !is_trusted(location))
+            .collect();
+        locations.sort_by_key(|location| location.to_string());
+        for location in locations {
+            check(
+                location,
+                &modules[location].contents.ast().definitions,
+                report,
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn allows_import() {
+        let capabilities = Capabilities {
+            allowed_imports: Some(vec![
+                "trilogy:std".to_owned(),
+                "file:///srv/rules/".to_owned(),
+            ]),
+            deny_extern: false,
+            ..Capabilities::default()
+        };
+        let script: Location = "file:///srv/rules/main.tri".parse().unwrap();
+        let std = Location::library("std").unwrap();
+        let io = Location::library("io").unwrap();
+
+        assert!(capabilities.allows_import(&script, &std));
+        assert!(capabilities.allows_import(&script, &script.relative("./util.tri")));
+        assert!(!capabilities.allows_import(&script, &io));
+        assert!(!capabilities.allows_import(&script, &script.relative("../secrets.tri")));
+        assert!(
+            !capabilities.allows_import(&script, &"https://example.com/a.tri".parse().unwrap())
+        );
+        assert!(capabilities.allows_import(&std, &io));
+        assert!(Capabilities::default().allows_import(&script, &io));
+    }
+
+    #[test]
+    fn allows_import_whole_segments() {
+        let capabilities = Capabilities {
+            allowed_imports: Some(vec![
+                "trilogy:std".to_owned(),
+                "file:///srv/rules".to_owned(),
+                "https:".to_owned(),
+            ]),
+            deny_extern: false,
+            ..Capabilities::default()
+        };
+        let script: Location = "file:///srv/rules/main.tri".parse().unwrap();
+
+        assert!(capabilities.allows_import(&script, &Location::library("std").unwrap()));
+        assert!(!capabilities.allows_import(&script, &Location::library("stdio").unwrap()));
+        assert!(capabilities.allows_import(&script, &script.relative("./util.tri")));
+        assert!(!capabilities.allows_import(&script, &script.relative("../rules-old/util.tri")));
+        assert!(!capabilities.allows_import(&script, &script.relative("../rules.tri")));
+        assert!(capabilities.allows_import(&script, &"https://example.com/a.tri".parse().unwrap()));
+    }
+
+    #[test]
+    fn allows_import_native_only_from_native_modules() {
+        let native_module: Location = "file:///srv/natives.tri".parse().unwrap();
+        let capabilities = Capabilities {
+            allowed_imports: Some(vec!["trilogy:".to_owned(), "file:///srv/".to_owned()]),
+            native_modules: vec![native_module.clone()],
+            ..Capabilities::default()
+        };
+        let script: Location = "file:///srv/rules/main.tri".parse().unwrap();
+        let native = Location::library("native").unwrap();
+
+        assert!(!capabilities.allows_import(&script, &native));
+        assert!(capabilities.allows_import(&native_module, &native));
+        assert!(capabilities.allows_import(&Location::library("std").unwrap(), &native));
+        assert!(capabilities.allows_import(&script, &native_module));
+    }
+
+    fn check_externs(capabilities: &Capabilities, location: &str, source: &str) -> bool {
+        let modules = HashMap::from([(location.parse().unwrap(), Module::new(source))]);
+        let mut report = ReportBuilder::<std::io::Error>::default();
+        capabilities.check_externs(&modules, &mut report);
+        report.has_errors()
+    }
+
+    #[test]
+    fn check_externs_denied() {
+        let capabilities = Capabilities {
+            allowed_imports: None,
+            deny_extern: true,
+            ..Capabilities::default()
+        };
+        let main = "file:///srv/rules/main.tri";
+        let external = "extern \"c\" proc quit!(code)";
+
+        assert!(check_externs(&capabilities, main, external));
+        assert!(check_externs(
+            &capabilities,
+            main,
+            &format!("type inner {{\n  {external}\n}}")
+        ));
+        assert!(!check_externs(&capabilities, main, "proc main!() {}"));
+        assert!(!check_externs(&capabilities, "trilogy:c", external));
+        assert!(!check_externs(&Capabilities::default(), main, external));
+    }
+}
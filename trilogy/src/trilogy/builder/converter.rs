@@ -1,24 +1,36 @@
 use super::Error;
-use super::loader::Module;
+use super::loader::{self, Module};
 use super::report::ReportBuilder;
 use crate::location::Location;
+use crate::manifest::Manifest;
 use std::collections::HashMap;
 use trilogy_ir::{Converter, Resolver, ir};
 
-impl Resolver for Location {
+/// Resolves the imports of the module at a location.
+struct Importer<'a> {
+    location: &'a Location,
+    manifest: Option<&'a Manifest>,
+}
+
+impl Resolver for Importer<'_> {
     fn resolve(&self, locator: &str) -> String {
-        self.relative(locator).to_string()
+        loader::resolve_import(self.manifest, self.location, locator).to_string()
     }
 }
 
 pub(super) fn convert<E: std::error::Error>(
     modules: HashMap<Location, Module>,
+    manifest: Option<&Manifest>,
     report: &mut ReportBuilder<E>,
 ) -> HashMap<Location, ir::Module> {
     let mut converted = HashMap::default();
 
     for (location, module) in modules {
-        let mut converter = Converter::new(&location, &module.source);
+        let importer = Importer {
+            location: &location,
+            manifest,
+        };
+        let mut converter = Converter::new(&importer, &module.source);
         let module = converter.convert(module.contents.into_ast());
         for error in converter.errors() {
             report.error(Error::ir(location.clone(), error));
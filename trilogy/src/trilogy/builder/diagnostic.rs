@@ -0,0 +1,199 @@
+use crate::line_index::LineIndex;
+use crate::location::Location;
+use source_span::Span;
+use std::ops::Range;
+
+/// The severity of a [`Diagnostic`][].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Severity {
+    /// The program cannot be compiled until this is fixed.
+    Error,
+    /// The program can be compiled, but something looks wrong.
+    Warning,
+}
+
+/// A span of source code referenced by a [`Diagnostic`][], optionally with a message
+/// describing its relevance.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Label {
+    location: Location,
+    span: Span,
+    offsets: Option<Range<usize>>,
+    message: Option<String>,
+}
+
+impl Label {
+    /// The module in which this label's span is found.
+    pub fn location(&self) -> &Location {
+        &self.location
+    }
+
+    /// The span of source code this label refers to.
+    pub fn span(&self) -> Span {
+        self.span
+    }
+
+    /// The byte offsets of this label's span within the source code of its module,
+    /// if that source code could be loaded.
+    pub fn byte_range(&self) -> Option<Range<usize>> {
+        self.offsets.clone()
+    }
+
+    /// The message attached to this label, if any.
+    pub fn message(&self) -> Option<&str> {
+        self.message.as_deref()
+    }
+}
+
+/// A single error or warning from a [`Report`][super::Report], in a structured form
+/// for tools (such as editors) that present diagnostics themselves.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Diagnostic {
+    severity: Severity,
+    code: &'static str,
+    message: String,
+    location: Option<Location>,
+    span: Span,
+    offsets: Option<Range<usize>>,
+    labels: Vec<Label>,
+    notes: Vec<String>,
+    help: Option<String>,
+}
+
+impl Diagnostic {
+    pub(super) fn new(severity: Severity, message: impl Into<String>) -> Self {
+        Self {
+            severity,
+            code: "",
+            message: message.into(),
+            location: None,
+            span: Span::default(),
+            offsets: None,
+            labels: vec![],
+            notes: vec![],
+            help: None,
+        }
+    }
+
+    pub(super) fn at(mut self, location: &Location, span: Span) -> Self {
+        self.location = Some(location.clone());
+        self.span = span;
+        self
+    }
+
+    pub(super) fn with_label(mut self, span: Span, message: impl Into<String>) -> Self {
+        self.labels.push(Label {
+            location: self.primary_location(),
+            span,
+            offsets: None,
+            message: Some(message.into()),
+        });
+        self
+    }
+
+    pub(super) fn with_unlabelled_span(mut self, span: Span) -> Self {
+        self.labels.push(Label {
+            location: self.primary_location(),
+            span,
+            offsets: None,
+            message: None,
+        });
+        self
+    }
+
+    pub(super) fn with_code(mut self, code: &'static str) -> Self {
+        self.code = code;
+        self
+    }
+
+    /// Resolves the byte offsets of this diagnostic's spans, given the source code of
+    /// the modules they refer to.
+    pub(super) fn with_offsets<'a>(
+        mut self,
+        source: impl Fn(&Location) -> Option<&'a str>,
+    ) -> Self {
+        if let Some(location) = &self.location {
+            self.offsets =
+                source(location).and_then(|source| LineIndex::new(source).range(self.span));
+        }
+        for label in &mut self.labels {
+            label.offsets =
+                source(&label.location).and_then(|source| LineIndex::new(source).range(label.span));
+        }
+        self
+    }
+
+    /// The modules referred to by this diagnostic and its labels.
+    pub(super) fn locations(&self) -> impl Iterator<Item = &Location> {
+        self.location
+            .iter()
+            .chain(self.labels.iter().map(|label| &label.location))
+    }
+
+    pub(super) fn with_note(mut self, note: impl Into<String>) -> Self {
+        self.notes.push(note.into());
+        self
+    }
+
+    pub(super) fn with_help(mut self, help: impl Into<String>) -> Self {
+        self.help = Some(help.into());
+        self
+    }
+
+    fn primary_location(&self) -> Location {
+        self.location
+            .clone()
+            .expect("labels are only attached to diagnostics with a location")
+    }
+
+    /// Whether this is an error or a warning.
+    pub fn severity(&self) -> Severity {
+        self.severity
+    }
+
+    /// A short identifier for the kind of this diagnostic, such as `unbound-identifier`,
+    /// which remains the same even if the message is reworded.
+    pub fn code(&self) -> &str {
+        self.code
+    }
+
+    /// The main message describing this diagnostic.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// The module this diagnostic was raised in.
+    ///
+    /// Errors that are not caused by any particular module, such as failing to
+    /// read the working directory, have no location.
+    pub fn location(&self) -> Option<&Location> {
+        self.location.as_ref()
+    }
+
+    /// The span of source code this diagnostic primarily refers to. Only meaningful
+    /// when the diagnostic has a [`location`][Diagnostic::location].
+    pub fn span(&self) -> Span {
+        self.span
+    }
+
+    /// The byte offsets of this diagnostic's span within the source code of its module,
+    /// if that source code could be loaded.
+    pub fn byte_range(&self) -> Option<Range<usize>> {
+        self.offsets.clone()
+    }
+
+    /// The spans of source code that are relevant to this diagnostic.
+    pub fn labels(&self) -> &[Label] {
+        &self.labels
+    }
+
+    /// Additional information about this diagnostic.
+    pub fn notes(&self) -> &[String] {
+        &self.notes
+    }
+
+    /// A suggestion on how to resolve this diagnostic, if there is one.
+    pub fn help(&self) -> Option<&str> {
+        self.help.as_deref()
+    }
+}
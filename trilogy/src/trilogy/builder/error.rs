@@ -1,5 +1,6 @@
 use super::loader;
 use crate::location::Location;
+use source_span::Span;
 use std::fmt::{self, Display};
 use trilogy_parser::syntax::SyntaxError;
 
@@ -13,6 +14,7 @@ pub(super) enum ErrorKind<E: std::error::Error> {
     Syntax(Location, SyntaxError),
     Ir(Location, trilogy_ir::Error),
     Analysis(Location, super::analyzer::ErrorKind),
+    ForbiddenExtern(Location, Span),
 }
 
 impl<E: std::error::Error> Error<E> {
@@ -35,6 +37,10 @@ impl<E: std::error::Error> Error<E> {
     pub(super) fn analysis(location: Location, error: super::analyzer::ErrorKind) -> Self {
         Self(ErrorKind::Analysis(location, error))
     }
+
+    pub(super) fn forbidden_extern(location: Location, span: Span) -> Self {
+        Self(ErrorKind::ForbiddenExtern(location, span))
+    }
 }
 
 impl<E: std::error::Error> From<Box<dyn std::error::Error>> for Error<E> {
@@ -63,6 +69,9 @@ impl<E: std::error::Error> Display for Error<E> {
             ErrorKind::Analysis(location, error) => {
                 writeln!(f, "{location}: {error}")?;
             }
+            ErrorKind::ForbiddenExtern(location, _) => {
+                writeln!(f, "{location}: extern declarations are not allowed")?;
+            }
         }
         Ok(())
     }
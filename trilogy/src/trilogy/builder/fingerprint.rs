@@ -0,0 +1,120 @@
+use super::loader::{Module, resolve_import};
+use crate::location::Location;
+use crate::manifest::Manifest;
+use sha2::{Digest, Sha256};
+use std::collections::btree_map::Entry;
+use std::collections::{BTreeMap, HashMap};
+
+/// Computes a fingerprint for each module, which changes whenever anything that
+/// the module's compiled code depends on changes.
+///
+/// A module's code depends on its own source, the sources of the modules it imports
+/// (whose exports it refers to, and so on, transitively), and the compiler itself.
+pub(super) fn fingerprints(
+    modules: &HashMap<Location, Module>,
+    manifest: Option<&Manifest>,
+) -> HashMap<String, String> {
+    let core = Location::library("core").unwrap();
+    let imports: HashMap<&Location, Vec<Location>> = modules
+        .iter()
+        .map(|(location, module)| {
+            let imports = module
+                .imported_modules()
+                .map(|locator| resolve_import(manifest, location, locator.as_ref()))
+                .chain([core.clone()])
+                .filter(|import| modules.contains_key(import))
+                .collect();
+            (location, imports)
+        })
+        .collect();
+    let compiler = compiler_fingerprint();
+
+    modules
+        .keys()
+        .map(|location| {
+            let mut dependencies = BTreeMap::from([(location.to_string(), location)]);
+            let mut queue = vec![location];
+            while let Some(next) = queue.pop() {
+                for import in &imports[next] {
+                    if let Entry::Vacant(entry) = dependencies.entry(import.to_string()) {
+                        entry.insert(import);
+                        queue.push(import);
+                    }
+                }
+            }
+
+            let mut hasher = Sha256::new();
+            hasher.update(compiler.as_bytes());
+            hasher.update(location.to_string().as_bytes());
+            hasher.update([0]);
+            for (name, dependency) in &dependencies {
+                let source = &modules[*dependency].source;
+                hasher.update(name.as_bytes());
+                hasher.update([0]);
+                hasher.update((source.len() as u64).to_le_bytes());
+                hasher.update(source.as_bytes());
+            }
+            (location.to_string(), format!("{:x}", hasher.finalize()))
+        })
+        .collect()
+}
+
+/// Identifies the compiler, so that modules compiled by one compiler are never reused by
+/// another. This is derived from the compiler itself, not the executable it is part of,
+/// so rebuilding a program that embeds Trilogy does not invalidate its modules.
+fn compiler_fingerprint() -> String {
+    format!(
+        "{} {}",
+        env!("CARGO_PKG_VERSION"),
+        trilogy_llvm::COMPILER_FINGERPRINT
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn modules(util: &str) -> HashMap<Location, Module> {
+        HashMap::from([
+            (
+                "file:///project/main.tri".parse().unwrap(),
+                Module::new("import \"./util.tri\" as util\nproc main!() {}\n"),
+            ),
+            (
+                "file:///project/util.tri".parse().unwrap(),
+                Module::new(util),
+            ),
+            (
+                "file:///project/other.tri".parse().unwrap(),
+                Module::new("export x\nconst x = 1\n"),
+            ),
+        ])
+    }
+
+    #[test]
+    fn fingerprint_changes_with_dependencies() {
+        let before = fingerprints(&modules("export x\nconst x = 1\n"), None);
+        let after = fingerprints(&modules("export x\nconst x = 2\n"), None);
+        assert_ne!(
+            before["file:///project/util.tri"],
+            after["file:///project/util.tri"]
+        );
+        assert_ne!(
+            before["file:///project/main.tri"],
+            after["file:///project/main.tri"]
+        );
+        assert_eq!(
+            before["file:///project/other.tri"],
+            after["file:///project/other.tri"]
+        );
+    }
+
+    #[test]
+    fn fingerprint_is_stable() {
+        let source = "export x\nconst x = 1\n";
+        assert_eq!(
+            fingerprints(&modules(source), None),
+            fingerprints(&modules(source), None)
+        );
+    }
+}
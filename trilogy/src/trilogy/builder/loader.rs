@@ -1,18 +1,22 @@
+use super::capabilities::Capabilities;
+use super::lockfile::{Lockfile, Mismatch};
 use super::report::ReportBuilder;
 use crate::cache::Cache;
 use crate::location::Location;
-
-use reqwest::blocking::Client;
-
+use crate::manifest::Manifest;
+use crate::resolver::{Resolver, ResolverError};
 use source_span::Span;
-use std::collections::{HashMap, VecDeque};
+use std::collections::{HashMap, HashSet};
 use std::fmt::{self, Display};
 use std::fs;
+use std::num::NonZeroUsize;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::time::Instant;
 use trilogy_parser::syntax::{DefinitionItem, Document, StringLiteral, TypeDefinition};
 use trilogy_parser::{Parse, Parser, Spanned};
 use trilogy_scanner::Scanner;
-use url::Url;
 
 #[derive(Clone, Debug)]
 pub(super) struct Module {
@@ -31,8 +35,11 @@ pub(super) struct Error<E: std::error::Error> {
 pub(super) enum ErrorKind<E> {
     InvalidScheme(String),
     Missing,
-    Network(reqwest::Error),
-    Io(std::io::Error),
+    Resolution(ResolverError),
+    Integrity(Mismatch),
+    Offline,
+    UnknownDependency(String),
+    Forbidden,
     Cache(E),
 }
 
@@ -40,8 +47,21 @@ impl<E: std::error::Error> Display for Error<E> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match &self.kind {
             ErrorKind::Cache(error) => write!(f, "{error}"),
-            ErrorKind::Io(error) => write!(f, "{error}"),
-            ErrorKind::Network(error) => write!(f, "{error}"),
+            ErrorKind::Resolution(error) => write!(f, "{error}"),
+            ErrorKind::Integrity(mismatch) => write!(f, "{mismatch}"),
+            ErrorKind::Offline => {
+                write!(f, "remote module is not available offline")
+            }
+            ErrorKind::UnknownDependency(name) => {
+                write!(
+                    f,
+                    "no dependency named `{name}` is declared in `{}`",
+                    Manifest::FILE_NAME
+                )
+            }
+            ErrorKind::Forbidden => {
+                write!(f, "importing this module is not allowed")
+            }
             ErrorKind::InvalidScheme(scheme) => {
                 write!(f, "invalid scheme in module location `{scheme}`")
             }
@@ -62,24 +82,14 @@ impl<E: std::error::Error + 'static> ErrorKind<E> {
     fn cause(&self) -> Option<&dyn std::error::Error> {
         match self {
             ErrorKind::Cache(e) => Some(e),
-            ErrorKind::Network(e) => Some(e),
-            ErrorKind::Io(e) => Some(e),
-            _ => None,
-        }
-    }
-
-    fn into_cause(self) -> Option<Box<dyn std::error::Error + 'static>> {
-        match self {
-            ErrorKind::Cache(e) => Some(Box::new(e)),
-            ErrorKind::Network(e) => Some(Box::new(e)),
-            ErrorKind::Io(e) => Some(Box::new(e)),
+            ErrorKind::Resolution(e) => Some(e.as_ref() as &dyn std::error::Error),
             _ => None,
         }
     }
 }
 
 impl Module {
-    fn new(source: &str) -> Self {
+    pub(super) fn new(source: &str) -> Self {
         let time_parsing = Instant::now();
         let scanner = Scanner::new(source);
         let parser = Parser::new(scanner);
@@ -91,7 +101,7 @@ impl Module {
         }
     }
 
-    fn imported_modules(&self) -> impl Iterator<Item = StringLiteral> + '_ {
+    pub(super) fn imported_modules(&self) -> impl Iterator<Item = StringLiteral> + '_ {
         fn module_imported_modules(module_def: &TypeDefinition) -> Vec<&StringLiteral> {
             module_def
                 .definitions
@@ -117,141 +127,397 @@ impl Module {
     }
 }
 
+/// The resolvers used to load modules, by the URL scheme they are responsible for.
+pub(super) type Resolvers = HashMap<String, Arc<dyn Resolver>>;
+
+/// Whether the module at this location is remote, that is, resolved by a resolver
+/// that caches the modules it resolves.
+pub(super) fn is_remote(resolvers: &Resolvers, location: &Location) -> bool {
+    resolvers
+        .get(location.as_ref().scheme())
+        .is_some_and(|resolver| resolver.is_cached())
+}
+
+/// Resolves the locator of an import to the location of the imported module. Locators
+/// of the form `dep:name` refer to the dependencies declared in the manifest.
+pub(super) fn resolve_import(
+    manifest: Option<&Manifest>,
+    from: &Location,
+    locator: &str,
+) -> Location {
+    locator
+        .strip_prefix("dep:")
+        .and_then(|name| manifest?.dependency(name))
+        .cloned()
+        .unwrap_or_else(|| from.relative(locator))
+}
+
+/// Where remote modules may be loaded from, besides the cache.
+#[derive(Clone, Debug, Default)]
+pub(super) struct RemoteOptions {
+    /// Remote modules that are not cached or vendored fail to load, rather than being resolved.
+    pub offline: bool,
+    /// A directory of vendored copies of remote modules, which are used in place of
+    /// the cache when present.
+    pub vendor_dir: Option<PathBuf>,
+}
+
 #[derive(Clone)]
 pub(super) struct Loader<'a, E> {
-    client: Client, // TODO: generic resolver
+    resolvers: &'a Resolvers,
     cache: &'a dyn Cache<Error = E>,
     libraries: &'a HashMap<Location, String>,
+    remote: &'a RemoteOptions,
+    manifest: Option<&'a Manifest>,
+    capabilities: Option<&'a Capabilities>,
+    refresh: bool,
 }
 
 impl<'a, E> Loader<'a, E>
 where
     E: std::error::Error + 'static,
 {
-    pub fn new(cache: &'a dyn Cache<Error = E>, libraries: &'a HashMap<Location, String>) -> Self {
+    pub fn new(
+        resolvers: &'a Resolvers,
+        cache: &'a dyn Cache<Error = E>,
+        libraries: &'a HashMap<Location, String>,
+        remote: &'a RemoteOptions,
+    ) -> Self {
         Self {
-            client: Client::default(),
+            resolvers,
             libraries,
             cache,
+            remote,
+            manifest: None,
+            capabilities: None,
+            refresh: false,
         }
     }
 
-    fn download(&self, url: &Url) -> Result<String, ErrorKind<E>> {
-        self.client
-            .get(url.clone())
-            .header("Accept", "text/x-trilogy")
-            .send()
-            .map_err(ErrorKind::Network)?
-            .text()
-            .map_err(ErrorKind::Network)
+    /// Sets the manifest by which `dep:` imports are resolved.
+    pub fn with_manifest(mut self, manifest: Option<&'a Manifest>) -> Self {
+        self.manifest = manifest;
+        self
     }
 
-    pub fn load_source(&self, location: &Location) -> Result<Option<String>, ErrorKind<E>> {
+    /// Sets the restrictions on which modules may be imported.
+    pub fn with_capabilities(mut self, capabilities: &'a Capabilities) -> Self {
+        self.capabilities = Some(capabilities);
+        self
+    }
+
+    /// Sets whether remote modules are resolved again instead of being loaded from
+    /// the cache or vendor directory. Their newly resolved sources are still saved to
+    /// the cache.
+    pub fn refreshing(mut self, refresh: bool) -> Self {
+        self.refresh = refresh;
+        self
+    }
+
+    pub fn is_remote(&self, location: &Location) -> bool {
+        is_remote(self.resolvers, location)
+    }
+
+    /// Finds the source of a module without resolving it, which is left for the caller
+    /// when the module must be resolved.
+    fn locate(&self, location: &Location) -> Result<Located<'a>, ErrorKind<E>> {
         log::debug!("locating module `{location}`");
-        if self.cache.has(location) {
+        if let Some(source) = self.libraries.get(location) {
+            return Ok(Located::Source(Some(source.clone())));
+        }
+        let is_remote = self.is_remote(location);
+        if is_remote
+            && !self.refresh
+            && let Some(path) = self.vendored_path(location)
+            && path.exists()
+        {
+            log::trace!("module is vendored");
+            return Ok(Located::Source(Some(
+                fs::read_to_string(path).map_err(|error| ErrorKind::Resolution(error.into()))?,
+            )));
+        }
+        if !(self.refresh && is_remote) && self.cache.has(location) {
             log::trace!("module cache hit");
-            return Ok(Some(self.cache.load(location).map_err(ErrorKind::Cache)?));
+            return Ok(Located::Source(Some(
+                self.cache.load(location).map_err(ErrorKind::Cache)?,
+            )));
+        }
+        let scheme = location.as_ref().scheme();
+        if scheme == "trilogy" {
+            return Ok(Located::Source(None));
+        }
+        if scheme == "dep" {
+            // Declared dependencies are resolved by the importer, so this one is not declared.
+            return Err(ErrorKind::UnknownDependency(
+                location.as_ref().path().to_owned(),
+            ));
         }
-        let url = location.as_ref();
-        match url.scheme() {
-            "file" => Ok(Some(fs::read_to_string(url.path()).map_err(ErrorKind::Io)?)),
-            "http" | "https" => {
-                let source = self.download(url)?;
-                self.cache
-                    .save(location, &source)
-                    .map_err(ErrorKind::Cache)?;
-                Ok(Some(source))
+        if is_remote && self.remote.offline {
+            return Err(ErrorKind::Offline);
+        }
+        match self.resolvers.get(scheme) {
+            Some(resolver) => Ok(Located::Resolve(resolver.as_ref())),
+            None => Err(ErrorKind::InvalidScheme(scheme.to_owned())),
+        }
+    }
+
+    fn vendored_path(&self, location: &Location) -> Option<PathBuf> {
+        Some(
+            self.remote
+                .vendor_dir
+                .as_ref()?
+                .join(location.remote_path()?),
+        )
+    }
+
+    /// Saves the source of a module that was resolved, if its resolver wants it cached.
+    fn resolved(
+        &self,
+        location: &Location,
+        resolver: &dyn Resolver,
+        resolved: Result<Option<String>, ResolverError>,
+    ) -> Result<Option<String>, ErrorKind<E>> {
+        let Some(source) = resolved.map_err(ErrorKind::Resolution)? else {
+            return Ok(None);
+        };
+        if resolver.is_cached() {
+            self.cache
+                .save(location, &source)
+                .map_err(ErrorKind::Cache)?;
+        }
+        Ok(Some(source))
+    }
+
+    pub fn load_source(&self, location: &Location) -> Result<Option<String>, ErrorKind<E>> {
+        match self.locate(location)? {
+            Located::Source(source) => Ok(source),
+            Located::Resolve(resolver) => {
+                self.resolved(location, resolver, resolver.resolve(location))
             }
-            "trilogy" => Ok(self.libraries.get(location).cloned()),
-            scheme => Err(ErrorKind::InvalidScheme(scheme.to_owned())),
         }
     }
 }
 
-pub(super) fn load<C: Cache>(
-    cache: &C,
+enum Located<'a> {
+    Source(Option<String>),
+    Resolve(&'a dyn Resolver),
+}
+
+/// The most modules that are resolved at once.
+const MAX_CONCURRENT_RESOLUTIONS: usize = 16;
+
+/// Applies `f` to every item using a pool of up to `workers` threads, returning the
+/// results in the same order as the items.
+fn parallel_map<T: Sync, R: Send>(
+    items: &[T],
+    workers: usize,
+    f: impl Fn(&T) -> R + Sync,
+) -> Vec<R> {
+    let workers = workers.min(items.len());
+    if workers <= 1 {
+        return items.iter().map(f).collect();
+    }
+    let next = AtomicUsize::new(0);
+    let (next, f) = (&next, &f);
+    let mut results: Vec<(usize, R)> = std::thread::scope(|scope| {
+        let workers: Vec<_> = (0..workers)
+            .map(|_| {
+                scope.spawn(move || {
+                    let mut results = vec![];
+                    loop {
+                        let index = next.fetch_add(1, Ordering::Relaxed);
+                        let Some(item) = items.get(index) else {
+                            break;
+                        };
+                        results.push((index, f(item)));
+                    }
+                    results
+                })
+            })
+            .collect();
+        workers
+            .into_iter()
+            .flat_map(|worker| {
+                worker
+                    .join()
+                    .unwrap_or_else(|panic| std::panic::resume_unwind(panic))
+            })
+            .collect()
+    });
+    results.sort_by_key(|(index, _)| *index);
+    results.into_iter().map(|(_, result)| result).collect()
+}
+
+/// The error for a module that could not be loaded but is not imported by any other
+/// module, so is reported as if it were imported by itself.
+fn root_error<E: std::error::Error>(location: &Location, kind: ErrorKind<E>) -> super::Error<E> {
+    super::Error::resolution(
+        location.clone(),
+        Error {
+            span: Span::default(),
+            location: location.clone(),
+            kind,
+        },
+    )
+}
+
+pub(super) fn load<E: std::error::Error + 'static>(
+    loader: Loader<'_, E>,
     entrypoint: &Location,
-    libraries: &HashMap<Location, String>,
-    report: &mut ReportBuilder<C::Error>,
+    mut lockfile: Option<&mut Lockfile>,
+    report: &mut ReportBuilder<E>,
 ) -> HashMap<Location, Module> {
     let mut modules = HashMap::new();
-    let loader = Loader::new(cache, libraries);
+    // The order in which modules were loaded, so that their errors are reported in
+    // the same order every time.
+    let mut load_order = vec![];
+    let parsers = std::thread::available_parallelism().map_or(1, NonZeroUsize::get);
 
-    let mut module_queue = VecDeque::with_capacity(8);
+    let mut imports = vec![];
 
     // HACK: always force trilogy:core to be imported.
     let core_location = Location::library("core").unwrap();
     let source = match loader.load_source(&core_location) {
         Ok(Some(source)) => source,
-        Ok(None) => unreachable!(),
-        Err(error) => {
-            report.error(error.into_cause().unwrap().into());
+        Ok(None) => {
+            report.error(root_error(&core_location, ErrorKind::Missing));
+            return HashMap::default();
+        }
+        Err(kind) => {
+            report.error(root_error(&core_location, kind));
             return HashMap::default();
         }
     };
     let core_module = Module::new(&source);
     for import in core_module.imported_modules() {
-        module_queue.push_back((core_location.clone(), import));
+        imports.push((core_location.clone(), import));
     }
+    load_order.push(core_location.clone());
     modules.insert(core_location, core_module);
 
     // And then we have to prime the queues with the entrypoint of the program.
     let source = match loader.load_source(entrypoint) {
         Ok(Some(source)) => source,
-        Ok(None) => unreachable!(),
-        Err(error) => {
-            report.error(error.into_cause().unwrap().into());
+        Ok(None) => {
+            report.error(root_error(entrypoint, ErrorKind::Missing));
+            return HashMap::default();
+        }
+        Err(kind) => {
+            report.error(root_error(entrypoint, kind));
             return HashMap::default();
         }
     };
     let entrymodule = Module::new(&source);
     for import in entrymodule.imported_modules() {
-        module_queue.push_back((entrypoint.clone(), import));
+        imports.push((entrypoint.clone(), import));
     }
+    load_order.push(entrypoint.clone());
     modules.insert(entrypoint.clone(), entrymodule);
 
-    while let Some((from_location, locator)) = module_queue.pop_front() {
-        let span = locator.span();
-        let location = from_location.relative(locator.as_ref());
-        let url = location.as_ref();
-        if modules.contains_key(url) {
-            continue;
-        };
-        let source = match loader.load_source(&location).map_err(|kind| {
-            super::Error::resolution(
-                from_location.clone(),
-                Error {
-                    span,
-                    location: location.clone(),
-                    kind,
-                },
-            )
-        }) {
-            Ok(Some(source)) => source,
-            Ok(None) => {
+    // Modules are then loaded breadth first, one level of imports at a time. Each level's
+    // modules are resolved and parsed concurrently, but are always handled in the order
+    // they were imported.
+    let mut failed = HashSet::new();
+    while !imports.is_empty() {
+        let mut locations = vec![];
+        let mut importers = HashMap::new();
+        for (from_location, locator) in imports.drain(..) {
+            let location = resolve_import(loader.manifest, &from_location, locator.as_ref());
+            // Every import is checked, even of modules that were already loaded for
+            // another module that is allowed to import them.
+            if let Some(capabilities) = loader.capabilities
+                && !capabilities.allows_import(&from_location, &location)
+            {
                 report.error(super::Error::resolution(
-                    from_location.clone(),
+                    from_location,
                     Error {
-                        span,
-                        location: location.clone(),
-                        kind: ErrorKind::Missing,
+                        span: locator.span(),
+                        location,
+                        kind: ErrorKind::Forbidden,
                     },
                 ));
                 continue;
             }
-            Err(error) => {
-                report.error(error);
+            if modules.contains_key(&location) || failed.contains(&location) {
                 continue;
             }
-        };
-        let module = Module::new(&source);
-        for import in module.imported_modules() {
-            module_queue.push_back((location.clone(), import));
+            importers.entry(location.clone()).or_insert_with(|| {
+                locations.push(location.clone());
+                (from_location, locator.span())
+            });
+        }
+
+        let mut sources: Vec<_> = locations
+            .iter()
+            .map(|location| loader.locate(location))
+            .collect();
+        let resolutions: Vec<_> = locations
+            .iter()
+            .zip(&sources)
+            .enumerate()
+            .filter_map(|(index, (location, located))| match located {
+                Ok(Located::Resolve(resolver)) => Some((index, location, *resolver)),
+                _ => None,
+            })
+            .collect();
+        let resolved = parallel_map(
+            &resolutions,
+            MAX_CONCURRENT_RESOLUTIONS,
+            |(_, location, resolver)| resolver.resolve(location),
+        );
+        for ((index, location, resolver), resolved) in resolutions.into_iter().zip(resolved) {
+            sources[index] = loader
+                .resolved(location, resolver, resolved)
+                .map(Located::Source);
+        }
+
+        let sources: Vec<_> = locations
+            .into_iter()
+            .zip(sources)
+            .filter_map(|(location, source)| {
+                let (from_location, span) = importers.remove(&location).unwrap();
+                let kind = match source {
+                    Ok(Located::Source(Some(source))) => {
+                        let checked = match &mut lockfile {
+                            Some(lockfile) if loader.is_remote(&location) => {
+                                lockfile.check(&location, &source)
+                            }
+                            _ => Ok(()),
+                        };
+                        match checked {
+                            Ok(()) => return Some((location, source)),
+                            Err(mismatch) => ErrorKind::Integrity(mismatch),
+                        }
+                    }
+                    Ok(Located::Source(None)) => ErrorKind::Missing,
+                    Ok(Located::Resolve(..)) => unreachable!("all modules have been resolved"),
+                    Err(kind) => kind,
+                };
+                report.error(super::Error::resolution(
+                    from_location,
+                    Error {
+                        span,
+                        location: location.clone(),
+                        kind,
+                    },
+                ));
+                failed.insert(location);
+                None
+            })
+            .collect();
+
+        let parsed = parallel_map(&sources, parsers, |(_, source)| Module::new(source));
+        for ((location, _), module) in sources.into_iter().zip(parsed) {
+            for import in module.imported_modules() {
+                imports.push((location.clone(), import));
+            }
+            load_order.push(location.clone());
+            modules.insert(location, module);
         }
-        modules.insert(location, module);
     }
 
-    for (location, module) in &modules {
+    for location in &load_order {
+        let module = &modules[location];
         for error in module.contents.errors() {
             report.error(super::Error::syntax(location.clone(), error.clone()))
         }
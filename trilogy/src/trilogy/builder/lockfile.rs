@@ -0,0 +1,287 @@
+use crate::location::Location;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fmt::{self, Display};
+use std::path::PathBuf;
+use std::{fs, io};
+
+/// The name of the lockfile, which is found beside the entrypoint of a program.
+const LOCKFILE_NAME: &str = "trilogy.lock";
+
+const HEADER: &str = "\
+# This file is generated by Trilogy to pin the contents of remote modules.
+# It is not intended to be edited by hand; run `trilogy update` to refresh it.
+";
+
+/// How a program uses the `trilogy.lock` file found beside its entrypoint, which records
+/// the SHA-256 hash of every remote module the program loads.
+///
+/// Only programs whose entrypoint is a file on the local file system have a lockfile.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum LockfileMode {
+    /// The lockfile is neither read nor written.
+    #[default]
+    Ignore,
+    /// Remote modules must match the contents pinned in the lockfile, and remote modules
+    /// that are not yet pinned are added to it.
+    Verify,
+    /// Remote modules must match the contents pinned in the lockfile, but the lockfile is
+    /// never written, for tools that check a program without building it.
+    Check,
+    /// Remote modules are loaded again, bypassing the cache, and the lockfile is
+    /// rewritten to pin their current contents. Pins for modules that are no longer
+    /// loaded are dropped.
+    Update,
+}
+
+/// The contents of a remote module did not match the contents pinned in the lockfile.
+#[derive(Clone, Debug)]
+pub(super) struct Mismatch {
+    pub expected: String,
+    pub actual: String,
+}
+
+impl Display for Mismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "contents do not match `{LOCKFILE_NAME}` (expected sha256 {}, found {})",
+            self.expected, self.actual
+        )
+    }
+}
+
+#[derive(Serialize, Deserialize, Default)]
+struct LockfileContents {
+    #[serde(default, rename = "module")]
+    modules: Vec<LockedModule>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct LockedModule {
+    url: String,
+    sha256: String,
+}
+
+#[derive(Debug)]
+pub(super) struct Lockfile {
+    path: PathBuf,
+    pins: BTreeMap<String, String>,
+    mode: LockfileMode,
+}
+
+impl Lockfile {
+    /// Opens the lockfile for a program with the given entrypoint, according to the mode.
+    ///
+    /// Returns `None` if the program does not use a lockfile.
+    pub fn open(entrypoint: &Location, mode: LockfileMode) -> io::Result<Option<Self>> {
+        if mode == LockfileMode::Ignore {
+            return Ok(None);
+        }
+        let Some(dir) = entrypoint
+            .as_ref()
+            .to_file_path()
+            .ok()
+            .and_then(|path| Some(path.parent()?.to_owned()))
+        else {
+            return Ok(None);
+        };
+        let path = dir.join(LOCKFILE_NAME);
+        let pins = match mode {
+            LockfileMode::Verify | LockfileMode::Check if path.exists() => {
+                let contents: LockfileContents = toml::from_str(&fs::read_to_string(&path)?)
+                    .map_err(|error| {
+                        io::Error::new(
+                            io::ErrorKind::InvalidData,
+                            format!("invalid lockfile `{}`: {error}", path.display()),
+                        )
+                    })?;
+                contents
+                    .modules
+                    .into_iter()
+                    .map(|module| (module.url, module.sha256))
+                    .collect()
+            }
+            _ => BTreeMap::new(),
+        };
+        Ok(Some(Self { path, pins, mode }))
+    }
+
+    /// Whether remote modules should be loaded again rather than read from the cache.
+    pub fn is_refreshing(&self) -> bool {
+        self.mode == LockfileMode::Update
+    }
+
+    /// Checks the source of a remote module against its pin, pinning it if it was not
+    /// pinned already.
+    pub fn check(&mut self, location: &Location, source: &str) -> Result<(), Mismatch> {
+        let actual = format!("{:x}", Sha256::digest(source.as_bytes()));
+        let url = location.to_string();
+        match self.pins.get(&url) {
+            Some(expected) if *expected != actual => Err(Mismatch {
+                expected: expected.clone(),
+                actual,
+            }),
+            Some(..) => Ok(()),
+            None => {
+                self.pins.insert(url, actual);
+                Ok(())
+            }
+        }
+    }
+
+    /// Writes the pins back to the lockfile. Pins for modules that this program did not
+    /// load are kept, as other programs beside it share the same lockfile, unless the
+    /// lockfile is being updated.
+    ///
+    /// The lockfile is not written if it would not change, and is not created at all for
+    /// programs that have no remote modules.
+    pub fn save(self) -> io::Result<()> {
+        if self.mode == LockfileMode::Check {
+            return Ok(());
+        }
+        let exists = self.path.exists();
+        if !exists && self.pins.is_empty() {
+            return Ok(());
+        }
+        let contents = LockfileContents {
+            modules: self
+                .pins
+                .into_iter()
+                .map(|(url, sha256)| LockedModule { url, sha256 })
+                .collect(),
+        };
+        let serialized = format!(
+            "{HEADER}\n{}",
+            toml::to_string(&contents).map_err(io::Error::other)?
+        );
+        if exists && fs::read_to_string(&self.path)? == serialized {
+            return Ok(());
+        }
+        fs::write(&self.path, serialized)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{LOCKFILE_NAME, Lockfile, LockfileMode};
+    use crate::location::Location;
+    use std::path::PathBuf;
+
+    const REMOTE: &str = "https://example.com/lib.tri";
+    const OTHER: &str = "https://example.com/other.tri";
+
+    fn project(name: &str) -> (PathBuf, Location) {
+        let dir =
+            std::env::temp_dir().join(format!("trilogy-lockfile-{name}-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let entrypoint = Location::entrypoint(dir.clone(), "main.tri");
+        (dir, entrypoint)
+    }
+
+    fn remote(url: &str) -> Location {
+        Location::absolute(url.parse().unwrap())
+    }
+
+    #[test]
+    fn lockfile_check() {
+        let (dir, entrypoint) = project("check");
+        let mut lockfile = Lockfile::open(&entrypoint, LockfileMode::Verify)
+            .unwrap()
+            .unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert!(lockfile.check(&remote(REMOTE), "source").is_ok());
+        assert!(lockfile.check(&remote(REMOTE), "source").is_ok());
+        let mismatch = lockfile.check(&remote(REMOTE), "changed").unwrap_err();
+        assert_eq!(mismatch.expected, lockfile.pins[REMOTE]);
+        assert_ne!(mismatch.actual, mismatch.expected);
+    }
+
+    #[test]
+    fn lockfile_save_keeps_unused_pins() {
+        let (dir, entrypoint) = project("save");
+        let mut lockfile = Lockfile::open(&entrypoint, LockfileMode::Verify)
+            .unwrap()
+            .unwrap();
+        lockfile.check(&remote(REMOTE), "source").unwrap();
+        lockfile.check(&remote(OTHER), "other").unwrap();
+        lockfile.save().unwrap();
+
+        let mut lockfile = Lockfile::open(&entrypoint, LockfileMode::Verify)
+            .unwrap()
+            .unwrap();
+        let mismatch = lockfile.check(&remote(REMOTE), "changed");
+        lockfile.save().unwrap();
+        let mut reopened = Lockfile::open(&entrypoint, LockfileMode::Verify)
+            .unwrap()
+            .unwrap();
+        let other = reopened.check(&remote(OTHER), "changed");
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert!(mismatch.is_err());
+        assert!(other.is_err());
+    }
+
+    #[test]
+    fn lockfile_update_drops_unused_pins() {
+        let (dir, entrypoint) = project("update");
+        let mut lockfile = Lockfile::open(&entrypoint, LockfileMode::Verify)
+            .unwrap()
+            .unwrap();
+        lockfile.check(&remote(REMOTE), "source").unwrap();
+        lockfile.check(&remote(OTHER), "other").unwrap();
+        lockfile.save().unwrap();
+
+        let mut lockfile = Lockfile::open(&entrypoint, LockfileMode::Update)
+            .unwrap()
+            .unwrap();
+        let updated = lockfile.check(&remote(REMOTE), "changed");
+        lockfile.save().unwrap();
+        let mut reopened = Lockfile::open(&entrypoint, LockfileMode::Verify)
+            .unwrap()
+            .unwrap();
+        let other = reopened.check(&remote(OTHER), "changed");
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert!(updated.is_ok());
+        assert!(other.is_ok());
+    }
+
+    #[test]
+    fn lockfile_save_without_remotes() {
+        let (dir, entrypoint) = project("empty");
+        let lockfile = Lockfile::open(&entrypoint, LockfileMode::Verify)
+            .unwrap()
+            .unwrap();
+        lockfile.save().unwrap();
+        let exists = dir.join(LOCKFILE_NAME).exists();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert!(!exists);
+    }
+
+    #[test]
+    fn lockfile_check_mode_does_not_write() {
+        let (dir, entrypoint) = project("readonly");
+        let mut lockfile = Lockfile::open(&entrypoint, LockfileMode::Check)
+            .unwrap()
+            .unwrap();
+        lockfile.check(&remote(REMOTE), "source").unwrap();
+        lockfile.save().unwrap();
+        let exists = dir.join(LOCKFILE_NAME).exists();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert!(!exists);
+    }
+
+    #[test]
+    fn lockfile_ignored() {
+        let (dir, entrypoint) = project("ignore");
+        let lockfile = Lockfile::open(&entrypoint, LockfileMode::Ignore).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert!(lockfile.is_none());
+    }
+}
@@ -1,23 +1,38 @@
 use crate::stdlib;
 
-use super::{Source, Trilogy};
+use super::{CompilationCache, Source, Trilogy};
 use crate::FileSystemCache;
 use crate::location::Location;
+use crate::manifest::Manifest;
+use crate::resolver::{FileSystemResolver, Resolver};
+use crate::runtime::{NATIVE_SUPPORT, NativeModule};
 use crate::{Cache, NoopCache};
 use home::home_dir;
 use std::collections::HashMap;
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use std::time::Instant;
+use std::{fs, io};
+use trilogy_llvm::{EffectHandler, EffectResponse, NativeProcedure, Value};
 
 mod analyzer;
+mod capabilities;
 mod converter;
+mod diagnostic;
 mod error;
+mod fingerprint;
 mod loader;
+mod lockfile;
 mod report;
 
+use capabilities::Capabilities;
+pub use diagnostic::{Diagnostic, Label, Severity};
 pub use error::Error;
-pub use report::Report;
+use loader::{Loader, RemoteOptions, Resolvers};
+use lockfile::Lockfile;
+pub use lockfile::LockfileMode;
 use report::ReportBuilder;
+pub use report::{ColorChoice, Report};
 
 /// Builder for instances of [`Trilogy`][].
 ///
@@ -26,6 +41,16 @@ use report::ReportBuilder;
 pub struct Builder<C: Cache + 'static> {
     root_dir: Option<PathBuf>,
     source_modules: HashMap<Location, String>,
+    natives: Vec<NativeProcedure>,
+    effect_handler: Option<EffectHandler>,
+    resolvers: Resolvers,
+    compilation_cache: Option<PathBuf>,
+    lockfile: LockfileMode,
+    offline: bool,
+    vendor_dir: Option<PathBuf>,
+    manifest: Option<Manifest>,
+    discover_manifest: bool,
+    capabilities: Capabilities,
     is_library: bool,
     cache: C,
 }
@@ -38,15 +63,21 @@ impl Builder<FileSystemCache> {
     ///
     /// The default resolver expects the existence of a file system with a home directory and uses
     /// the directory `$HOME/.trilogy/cache` to cache Trilogy modules downloaded from the Internet.
+    /// Compiled modules are cached in `$HOME/.trilogy/compiled`, and remote modules are
+    /// verified against the `trilogy.lock` file beside the entrypoint. Remote modules that
+    /// have been vendored into the `vendor` directory beside the entrypoint are loaded
+    /// from there. The project's `trilogy.toml` manifest is discovered and applied.
     pub fn std() -> Self {
-        let home = home_dir()
-            .expect("home dir should exist")
-            .join(".trilogy/cache");
+        let home = home_dir().expect("home dir should exist").join(".trilogy");
         Builder::default()
             .with_cache(
-                FileSystemCache::new(home)
+                FileSystemCache::new(home.join("cache"))
                     .expect("canonical cache dir ~/.trilogy/cache is occupied"),
             )
+            .compilation_cache(home.join("compiled"))
+            .lockfile(LockfileMode::Verify)
+            .vendor_dir("vendor")
+            .discover_manifest(true)
             .map(stdlib::apply)
     }
 
@@ -65,7 +96,8 @@ impl Builder<NoopCache> {
     /// Creates a new Trilogy builder with nothing added.
     ///
     /// Programs created from this builder will not have the standard library (unless you manually
-    /// re-add it).
+    /// re-add it). Modules are resolved from the local file system and, if the `http` feature is
+    /// enabled, from the Internet.
     ///
     /// This builder also does not come with a cache for some reason.
     pub fn new() -> Self {
@@ -73,12 +105,28 @@ impl Builder<NoopCache> {
     }
 
     fn empty() -> Self {
-        Self {
+        let builder = Self {
             root_dir: None,
             source_modules: HashMap::new(),
+            natives: vec![],
+            effect_handler: None,
+            resolvers: Resolvers::new(),
+            compilation_cache: None,
+            lockfile: LockfileMode::Ignore,
+            offline: false,
+            vendor_dir: None,
+            manifest: None,
+            discover_manifest: false,
+            capabilities: Capabilities::default(),
             is_library: false,
             cache: NoopCache,
         }
+        .resolver("file", FileSystemResolver);
+        #[cfg(feature = "http")]
+        let builder = builder
+            .resolver("http", crate::resolver::HttpResolver::default())
+            .resolver("https", crate::resolver::HttpResolver::default());
+        builder
     }
 }
 
@@ -92,6 +140,69 @@ impl<C: Cache> Builder<C> {
         self
     }
 
+    /// Adds a native module to this builder, which Trilogy programs may import from
+    /// the given location as if it were any other module.
+    ///
+    /// Native procedures can only be called by programs run from this process, so a
+    /// program that uses native modules cannot be compiled to a standalone executable.
+    pub fn native_module(mut self, location: Location, module: NativeModule) -> Self {
+        self.source_modules
+            .insert(location.clone(), module.source(self.natives.len()));
+        self.capabilities.native_modules.push(location);
+        self.natives.extend(module.procedures());
+        self.source_modules
+            .entry(Location::library("native").unwrap())
+            .or_insert_with(|| NATIVE_SUPPORT.to_owned());
+        self
+    }
+
+    /// Registers a resolver, which will be used to load modules from locations using the
+    /// given URL scheme, replacing any resolver previously registered for that scheme.
+    ///
+    /// Modules at locations using a scheme with no resolver fail to resolve.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use trilogy::{Builder, Location, Resolver, ResolverError};
+    /// struct CorpResolver;
+    ///
+    /// impl Resolver for CorpResolver {
+    ///     fn resolve(&self, _location: &Location) -> Result<Option<String>, ResolverError> {
+    ///         Ok(None)
+    ///     }
+    /// }
+    ///
+    /// let builder = Builder::new().resolver("corp", CorpResolver);
+    /// ```
+    pub fn resolver(mut self, scheme: &str, resolver: impl Resolver + 'static) -> Self {
+        self.resolvers.insert(scheme.to_owned(), Arc::new(resolver));
+        self
+    }
+
+    /// Sets the handler for effects that reach the top of a program without being handled
+    /// by the program itself.
+    ///
+    /// The handler may resume the program from where the effect was yielded, or cancel
+    /// it, ending the program as if it returned the provided value. Effects it leaves
+    /// unhandled end the program with a runtime error, as they would with no handler.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use trilogy::{Builder, EffectResponse, Value};
+    /// let builder = Builder::new().effect_handler(|effect| match effect {
+    ///     Value::Struct(tag, key) if tag == "config" && *key == Value::String("name".to_owned()) => {
+    ///         EffectResponse::Resume(Value::String("trilogy".to_owned()))
+    ///     }
+    ///     _ => EffectResponse::Unhandled,
+    /// });
+    /// ```
+    pub fn effect_handler(mut self, handler: impl Fn(Value) -> EffectResponse + 'static) -> Self {
+        self.effect_handler = Some(EffectHandler::new(handler));
+        self
+    }
+
     /// Sets the module cache for this Builder. The module cache is used when building
     /// the Trilogy instance to load modules previously loaded from the Internet from
     /// somewhere hopefully faster to reach.
@@ -99,11 +210,105 @@ impl<C: Cache> Builder<C> {
         Builder {
             root_dir: self.root_dir,
             source_modules: self.source_modules,
+            natives: self.natives,
+            effect_handler: self.effect_handler,
+            resolvers: self.resolvers,
+            compilation_cache: self.compilation_cache,
+            lockfile: self.lockfile,
+            offline: self.offline,
+            vendor_dir: self.vendor_dir,
+            manifest: self.manifest,
+            discover_manifest: self.discover_manifest,
+            capabilities: self.capabilities,
             is_library: false,
             cache,
         }
     }
 
+    /// Sets the directory in which compiled modules are cached.
+    ///
+    /// Modules that have not changed since they were last compiled, along with the modules
+    /// they import, are then linked into the program from the cache instead of being
+    /// compiled again. By default, compiled modules are not cached.
+    pub fn compilation_cache(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.compilation_cache = Some(dir.into());
+        self
+    }
+
+    /// Sets how the `trilogy.lock` file beside the entrypoint is used to pin the contents
+    /// of remote modules. By default, the lockfile is ignored.
+    ///
+    /// When verifying, a remote module whose contents differ from those pinned is reported
+    /// as an error when building.
+    pub fn lockfile(mut self, mode: LockfileMode) -> Self {
+        self.lockfile = mode;
+        self
+    }
+
+    /// Sets whether this builder works offline, in which case remote modules that are not
+    /// cached or vendored fail to load instead of being downloaded.
+    pub fn offline(mut self, offline: bool) -> Self {
+        self.offline = offline;
+        self
+    }
+
+    /// Sets the directory of vendored remote modules, as created by [`vendor`][Builder::vendor].
+    /// A relative path is taken relative to the directory containing the entrypoint.
+    ///
+    /// Remote modules found in this directory are loaded from there, instead of from the
+    /// cache or the Internet.
+    pub fn vendor_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.vendor_dir = Some(dir.into());
+        self
+    }
+
+    /// Sets the project manifest, whose dependencies may be imported by name, as in
+    /// `import "dep:parsers"`.
+    pub fn manifest(mut self, manifest: Manifest) -> Self {
+        self.manifest = Some(manifest);
+        self
+    }
+
+    /// Sets whether the project manifest is discovered automatically, when one is not
+    /// set explicitly.
+    ///
+    /// The manifest is the `trilogy.toml` file in the directory containing the entrypoint,
+    /// or the nearest of its ancestors.
+    pub fn discover_manifest(mut self, discover: bool) -> Self {
+        self.discover_manifest = discover;
+        self
+    }
+
+    /// Restricts the modules that a program may import to those whose locations start
+    /// with one of the given prefixes, such as a scheme (`"trilogy:"`), a library
+    /// (`"trilogy:std"`), or a directory (`"file:///srv/rules/"`). By default, any
+    /// module may be imported.
+    ///
+    /// The `trilogy:` libraries may always import each other. Imports of other modules
+    /// are reported as errors when building.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use trilogy::Builder;
+    /// let builder = Builder::new()
+    ///     .allow_imports(["trilogy:std", "file:///srv/rules/"])
+    ///     .allow_extern(false);
+    /// ```
+    pub fn allow_imports<S: Into<String>>(mut self, prefixes: impl IntoIterator<Item = S>) -> Self {
+        self.capabilities.allowed_imports = Some(prefixes.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Sets whether modules other than the `trilogy:` libraries may declare `extern`
+    /// procedures, which bind to arbitrary symbols in the process. By default, they may.
+    ///
+    /// When disallowed, `extern` declarations are reported as errors when building.
+    pub fn allow_extern(mut self, allow: bool) -> Self {
+        self.capabilities.deny_extern = !allow;
+        self
+    }
+
     /// Sets this builder to being in library mode, where having a `proc main!()` in the
     /// entrypoint is __not__ required.
     ///
@@ -129,40 +334,263 @@ impl<C: Cache> Builder<C> {
         self,
         file: impl AsRef<Path>,
     ) -> Result<Trilogy, Box<Report<C::Error>>> {
-        log::trace!("begin constructing Trilogy program");
-        let Self {
-            mut cache,
-            root_dir,
-            source_modules,
-            is_library,
-        } = self;
-        let mut report = ReportBuilder::default();
-        report.add_libraries(source_modules.clone());
-        let root_path = match root_dir {
-            Some(root_dir) => root_dir,
-            None => match std::env::current_dir() {
-                Ok(dir) => dir,
-                Err(error) => {
-                    report.error(Error::external(error));
-                    return Err(Box::new(report.report(file.as_ref().to_owned(), cache)));
-                }
-            },
+        let root_path = match self.root_path() {
+            Ok(root_path) => root_path,
+            Err(error) => return Err(self.fail(file.as_ref(), error)),
         };
         let entrypoint = Location::entrypoint(root_path.clone(), file);
+        self.build(root_path, entrypoint)
+    }
+
+    /// Build a Trilogy instance from the entrypoint declared by the project manifest.
+    ///
+    /// If no manifest is set, the manifest is discovered from the root directory (usually
+    /// the current directory) or the nearest of its ancestors.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error report when there is no manifest, or it declares no entrypoint, or
+    /// when there are any errors in the Trilogy source files, as with
+    /// [`build_from_source`][Builder::build_from_source].
+    pub fn build_project(mut self) -> Result<Trilogy, Box<Report<C::Error>>> {
+        let root_path = match self.root_path() {
+            Ok(root_path) => root_path,
+            Err(error) => return Err(self.fail(Path::new("."), error)),
+        };
+        if self.manifest.is_none() {
+            match Manifest::discover(&root_path) {
+                Ok(manifest) => self.manifest = manifest,
+                Err(error) => return Err(self.fail(&root_path, error)),
+            }
+        }
+        let entrypoint = match &self.manifest {
+            None => Err(format!(
+                "no `{}` was found for this project",
+                Manifest::FILE_NAME
+            )),
+            Some(manifest) => manifest.entrypoint().map(Path::to_owned).ok_or_else(|| {
+                format!(
+                    "no entrypoint is declared in `{}`",
+                    manifest.path().display()
+                )
+            }),
+        };
+        match entrypoint {
+            Ok(entrypoint) => self.build(root_path, Location::local_absolute(entrypoint)),
+            Err(message) => Err(self.fail(&root_path, io::Error::other(message))),
+        }
+    }
+
+    /// Build a Trilogy instance from Trilogy source code in memory, treating it as the
+    /// source of the module at the given location.
+    ///
+    /// Relative imports are resolved against that location, the same way as they would
+    /// be if the source had been loaded from there. The location itself is never read.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error report when there are any errors in the Trilogy source, as
+    /// with [`build_from_source`][Builder::build_from_source].
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use trilogy::{Builder, Location};
+    /// let location: Location = "file:///srv/scripts/main.tri".parse().unwrap();
+    /// let trilogy = Builder::std()
+    ///     .build_from_string(location, "proc main!() { return 3 }")
+    ///     .unwrap();
+    /// ```
+    pub fn build_from_string(
+        mut self,
+        location: Location,
+        source: impl Into<String>,
+    ) -> Result<Trilogy, Box<Report<C::Error>>> {
+        let root_path = self
+            .root_dir
+            .clone()
+            .or_else(|| std::env::current_dir().ok())
+            .unwrap_or_default();
+        self.source_modules.insert(location.clone(), source.into());
+        self.build(root_path, location)
+    }
+
+    /// Copies every remote module that a Trilogy source file imports, directly or
+    /// indirectly, into the vendor directory, returning the locations of the modules
+    /// that were copied.
+    ///
+    /// Programs built by a builder with the same vendor directory then load those modules
+    /// from there, rather than from the cache or the Internet. Modules are always copied
+    /// from their usual sources, so vendoring again replaces any previous copies.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error report if no vendor directory is set, if the program's modules
+    /// cannot all be loaded, or if they cannot be written to the vendor directory.
+    pub fn vendor(self, file: impl AsRef<Path>) -> Result<Vec<Location>, Box<Report<C::Error>>> {
+        let root_path = match self.root_path() {
+            Ok(root_path) => root_path,
+            Err(error) => return Err(self.fail(file.as_ref(), error)),
+        };
+        let entrypoint = Location::entrypoint(root_path.clone(), file);
+        let mut report = ReportBuilder::default();
+        let vendor_dir = self.remote_options(&root_path, &entrypoint).vendor_dir;
+        let remote = RemoteOptions {
+            offline: self.offline,
+            vendor_dir: None,
+        };
+        let manifest = self.project_manifest(&root_path, &entrypoint, &mut report);
+        let modules = self.load(&entrypoint, remote, manifest.as_ref(), &mut report);
+        let mut remote_modules: Vec<_> = modules
+            .keys()
+            .filter(|location| loader::is_remote(&self.resolvers, location))
+            .cloned()
+            .collect();
+        remote_modules.sort_by_key(|location| location.to_string());
+        let cache = report.checkpoint(&root_path, self.cache)?;
+
+        let Some(vendor_dir) = vendor_dir else {
+            report.error(Error::external(io::Error::other(
+                "no vendor directory is set",
+            )));
+            return Err(Box::new(report.report(root_path, cache)));
+        };
+        for location in &remote_modules {
+            let path = vendor_dir.join(location.remote_path().unwrap());
+            let written = path
+                .parent()
+                .map_or(Ok(()), fs::create_dir_all)
+                .and_then(|_| fs::write(&path, &modules[location].source));
+            if let Err(error) = written {
+                report.error(Error::external(error));
+            }
+        }
+        report.checkpoint(&root_path, cache)?;
+        Ok(remote_modules)
+    }
+
+    /// The directory that the paths of local source files are relative to.
+    fn root_path(&self) -> io::Result<PathBuf> {
+        match &self.root_dir {
+            Some(root_dir) => Ok(root_dir.clone()),
+            None => std::env::current_dir(),
+        }
+    }
+
+    /// Reports an error that prevented the program at the given file from being loaded at all.
+    fn fail(self, file: &Path, error: io::Error) -> Box<Report<C::Error>> {
+        let mut report = ReportBuilder::default();
+        report.error(Error::external(error));
+        Box::new(report.report(file.to_owned(), self.cache))
+    }
+
+    /// Where remote modules may be loaded from for a program with the given entrypoint.
+    /// A relative vendor directory is found beside the entrypoint.
+    fn remote_options(&self, root_path: &Path, entrypoint: &Location) -> RemoteOptions {
+        let base = entrypoint
+            .to_local_path()
+            .and_then(|path| Some(path.parent()?.to_owned()))
+            .unwrap_or_else(|| root_path.to_owned());
+        RemoteOptions {
+            offline: self.offline,
+            vendor_dir: self.vendor_dir.as_ref().map(|dir| base.join(dir)),
+        }
+    }
+
+    /// The manifest of the project containing the given entrypoint, if there is one.
+    fn project_manifest(
+        &self,
+        root_path: &Path,
+        entrypoint: &Location,
+        report: &mut ReportBuilder<C::Error>,
+    ) -> Option<Manifest> {
+        if self.manifest.is_some() || !self.discover_manifest {
+            return self.manifest.clone();
+        }
+        let dir = entrypoint
+            .to_local_path()
+            .and_then(|path| Some(path.parent()?.to_owned()))
+            .unwrap_or_else(|| root_path.to_owned());
+        Manifest::discover(dir).unwrap_or_else(|error| {
+            report.error(Error::external(error));
+            None
+        })
+    }
+
+    /// Loads all the modules of a program, verifying remote modules against the lockfile.
+    fn load(
+        &self,
+        entrypoint: &Location,
+        remote: RemoteOptions,
+        manifest: Option<&Manifest>,
+        report: &mut ReportBuilder<C::Error>,
+    ) -> HashMap<Location, loader::Module> {
+        report.add_libraries(self.source_modules.clone());
+        report.add_resolvers(self.resolvers.clone());
+        report.set_remote_options(remote.clone());
+        let mut lockfile = match Lockfile::open(entrypoint, self.lockfile) {
+            Ok(lockfile) => lockfile,
+            Err(error) => {
+                report.error(Error::external(error));
+                return HashMap::new();
+            }
+        };
         let time_loading = Instant::now();
-        let modules = loader::load(&cache, &entrypoint, &source_modules, &mut report);
-        cache = report.checkpoint(&root_path, cache)?;
+        let loader = Loader::new(&self.resolvers, &self.cache, &self.source_modules, &remote)
+            .refreshing(lockfile.as_ref().is_some_and(Lockfile::is_refreshing))
+            .with_manifest(manifest)
+            .with_capabilities(&self.capabilities);
+        let modules = loader::load(loader, entrypoint, lockfile.as_mut(), report);
+        if !report.has_errors()
+            && let Some(lockfile) = lockfile
+            && let Err(error) = lockfile.save()
+        {
+            report.error(Error::external(error));
+        }
         log::trace!("all modules loaded: {:?}", time_loading.elapsed());
+        modules
+    }
+
+    fn build(
+        self,
+        root_path: PathBuf,
+        entrypoint: Location,
+    ) -> Result<Trilogy, Box<Report<C::Error>>> {
+        log::trace!("begin constructing Trilogy program");
+        let mut report = ReportBuilder::default();
+        let remote = self.remote_options(&root_path, &entrypoint);
+        let manifest = self.project_manifest(&root_path, &entrypoint, &mut report);
+        let modules = self.load(&entrypoint, remote, manifest.as_ref(), &mut report);
+        self.capabilities.check_externs(&modules, &mut report);
+        let Self {
+            cache,
+            natives,
+            effect_handler,
+            compilation_cache,
+            is_library,
+            ..
+        } = self;
+        let cache = report.checkpoint(&root_path, cache)?;
+
+        let compilation_cache = compilation_cache.map(|dir| {
+            CompilationCache::new(dir, fingerprint::fingerprints(&modules, manifest.as_ref()))
+        });
 
         let time_analyzing = Instant::now();
-        let mut modules = converter::convert(modules, &mut report);
+        let mut modules = converter::convert(modules, manifest.as_ref(), &mut report);
         analyzer::analyze(&mut modules, &entrypoint, &mut report, is_library);
         report.checkpoint(&root_path, cache)?;
         log::trace!("program analyzed: {:?}", time_analyzing.elapsed());
 
-        Ok(Trilogy::new(Source {
-            modules,
-            entrypoint,
-        }))
+        Ok(Trilogy::new(
+            Source {
+                modules,
+                entrypoint,
+            },
+            natives,
+            effect_handler,
+            compilation_cache,
+            manifest,
+        ))
     }
 }
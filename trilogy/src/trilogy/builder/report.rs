@@ -1,12 +1,15 @@
 use super::Error;
+use super::diagnostic::{Diagnostic, Severity};
 use super::error::ErrorKind;
-use super::loader::Loader;
+use super::loader::{self, Loader, RemoteOptions, Resolvers};
 use crate::Cache;
 use crate::ariadne::{CacheExt, LoaderCache};
 use crate::location::Location;
-use ariadne::{ColorGenerator, Config, Fmt, FnCache, Label, ReportKind};
+use ariadne::{Color, ColorGenerator, Config, Fmt, FnCache, ReportKind};
+use source_span::Span;
 use std::collections::HashMap;
-use std::fmt::{self, Debug};
+use std::fmt::{self, Debug, Display};
+use std::io::{self, IsTerminal, Write};
 use std::path::{Path, PathBuf};
 use trilogy_ir::ir::DefinitionItem;
 use trilogy_parser::Spanned;
@@ -25,6 +28,8 @@ pub struct Report<E: std::error::Error> {
     errors: Vec<Error<E>>,
     warnings: Vec<Error<E>>,
     libraries: HashMap<Location, String>,
+    resolvers: Resolvers,
+    remote: RemoteOptions,
 }
 
 impl<E: std::error::Error> Debug for Report<E> {
@@ -39,10 +44,34 @@ impl<E: std::error::Error> Debug for Report<E> {
 impl<E: std::error::Error + 'static> Report<E> {
     /// Print this report to standard error.
     ///
-    /// This is the intended way of consuming a Report.
+    /// This is the intended way of consuming a Report. Output is colored if standard
+    /// error is a terminal. Failures to write to standard error are ignored, as there is
+    /// nowhere left to report them.
     pub fn eprint(&self) {
+        _ = self.write_to(&mut std::io::stderr(), ColorChoice::Auto);
+    }
+
+    /// Write this report to a stream, in the same format as [`eprint`][Report::eprint].
+    ///
+    /// With [`ColorChoice::Auto`][], output is colored if that stream is a terminal.
+    /// To write a report somewhere else, such as into a buffer, use its [`Display`][]
+    /// implementation, which is never colored.
+    ///
+    /// # Errors
+    ///
+    /// Returns any error that occurs while writing.
+    pub fn write_to(
+        &self,
+        writer: &mut (impl Write + IsTerminal),
+        color: ColorChoice,
+    ) -> io::Result<()> {
+        let color = color.enabled(writer);
+        self.write(writer, color)
+    }
+
+    fn write(&self, writer: &mut impl Write, color: bool) -> io::Result<()> {
         // NOTE: errors in libraries are unexpected, and cannot be reported accurately at this time
-        let loader = Loader::new(self.cache.as_ref(), &self.libraries);
+        let loader = self.loader();
         let cache = FnCache::new(move |loc: &Location| {
             loader
                 .load_source(loc)
@@ -51,446 +80,485 @@ impl<E: std::error::Error + 'static> Report<E> {
         });
         let mut cache = LoaderCache::<_, String>::new(&self.relative_base, cache);
 
-        for warning in &self.warnings {
-            warning.eprint(&mut cache, ReportKind::Warning);
-        }
-        for error in &self.errors {
-            error.eprint(&mut cache, ReportKind::Error);
+        for diagnostic in self.diagnostics() {
+            diagnostic.write(&mut cache, &mut *writer, color)?;
         }
+        Ok(())
+    }
+
+    /// The diagnostics in this report, warnings first and then errors.
+    ///
+    /// This is how tools such as editors can present the contents of a report
+    /// in their own way, instead of printing it.
+    pub fn diagnostics(&self) -> impl Iterator<Item = Diagnostic> + '_ {
+        let loader = self.loader();
+        let mut sources = HashMap::<Location, Option<String>>::new();
+        self.warnings
+            .iter()
+            .map(|warning| warning.diagnostic(Severity::Warning))
+            .chain(
+                self.errors
+                    .iter()
+                    .map(|error| error.diagnostic(Severity::Error)),
+            )
+            .map(move |diagnostic| {
+                for location in diagnostic.locations() {
+                    if !sources.contains_key(location) {
+                        let source = loader.load_source(location).ok().flatten();
+                        sources.insert(location.clone(), source);
+                    }
+                }
+                diagnostic.with_offsets(|location| sources.get(location)?.as_deref())
+            })
+    }
+
+    fn loader(&self) -> Loader<'_, E> {
+        Loader::new(
+            &self.resolvers,
+            self.cache.as_ref(),
+            &self.libraries,
+            &self.remote,
+        )
     }
 }
 
 impl<E: std::error::Error> Error<E> {
-    fn eprint<C: ariadne::Cache<Location>>(&self, mut cache: C, kind: ReportKind) {
-        let mut colors = ColorGenerator::new();
-        let primary = colors.next();
-        let secondary = colors.next();
-        let tertiary = colors.next();
+    fn diagnostic(&self, severity: Severity) -> Diagnostic {
+        self.describe(severity).with_code(self.code())
+    }
 
-        let report = match &self.0 {
-            ErrorKind::External(error) => {
-                eprintln!("{error}");
-                return;
-            }
-            ErrorKind::Ir(location, error) => {
+    /// A short, stable identifier for the kind of this error.
+    fn code(&self) -> &'static str {
+        match &self.0 {
+            ErrorKind::External(..) => "external",
+            ErrorKind::Resolver(_, error) => match error.kind {
+                loader::ErrorKind::Integrity(..) => "lockfile-mismatch",
+                loader::ErrorKind::Offline => "offline",
+                loader::ErrorKind::UnknownDependency(..) => "unknown-dependency",
+                loader::ErrorKind::Forbidden => "forbidden-import",
+                _ => "module-resolution",
+            },
+            ErrorKind::Ir(_, error) => {
                 use trilogy_ir::Error;
                 match error {
-                    Error::Unimplemented { feature, span } => {
-                        let span = cache.span(location, *span);
-                        ariadne::Report::build(kind, span.clone())
-                            .with_message(format!(
-                                "feature `{}` is not implemented",
-                                feature.fg(primary)
-                            ))
-                            .with_label(
-                                Label::new(span)
-                                    .with_color(primary)
-                                    .with_message("used here"),
-                            )
-                    }
-                    Error::UnknownExport { name } => {
-                        let span = cache.span(location, name.span());
-                        ariadne::Report::build(kind, span.clone())
-                            .with_message(format!(
-                                "exporting undeclared identifier `{}`",
-                                name.as_ref().fg(primary)
-                            ))
-                            .with_label(
-                                Label::new(span)
-                                    .with_color(primary)
-                                    .with_message("listed here"),
-                            )
+                    Error::Unimplemented { .. } => "unimplemented",
+                    Error::UnknownExport { .. } => "unknown-export",
+                    Error::UnboundIdentifier { .. } => "unbound-identifier",
+                    Error::DuplicateDefinition { .. } => "duplicate-definition",
+                    Error::UnknownCallingConvention { .. } => "unknown-calling-convention",
+                    Error::IdentifierInOwnDefinition { .. } => "identifier-in-own-definition",
+                    Error::AssignedImmutableBinding { .. } => "assigned-immutable-binding",
+                    Error::InvalidAssignmentTarget { .. } => "invalid-assignment-target",
+                    Error::DuplicateExport { .. } => "duplicate-export",
+                    Error::GluePatternMissingLiteral { .. } => "glue-pattern-missing-literal",
+                    Error::NonConstantExpressionInConstant { .. } => "non-constant-expression",
+                    Error::NoReturnFromRule { .. } => "return-from-rule",
+                    Error::MultiValuedPatternInSet { .. } => "multi-valued-pattern-in-set",
+                    Error::MultiValuedPatternInRecordKey { .. } => {
+                        "multi-valued-pattern-in-record-key"
                     }
-                    Error::UnboundIdentifier { name } => {
-                        let span = cache.span(location, name.span());
-                        ariadne::Report::build(kind, span.clone())
-                            .with_message(format!(
-                                "reference to undeclared identifier `{}`",
-                                name.as_ref().fg(primary),
-                            ))
-                            .with_label(
-                                Label::new(span)
-                                    .with_color(primary)
-                                    .with_message("referenced here"),
-                            )
+                    Error::ResumeOutsideHandlerContext { .. } => "resume-outside-handler",
+                    Error::CancelOutsideHandlerContext { .. } => "cancel-outside-handler",
+                    Error::BecomeOutsideHandlerContext { .. } => "become-outside-handler",
+                    Error::BreakOutsideLoopContext { .. } => "break-outside-loop",
+                    Error::ContinueOutsideLoopContext { .. } => "continue-outside-loop",
+                }
+            }
+            ErrorKind::Analysis(_, error) => {
+                use super::analyzer::ErrorKind;
+                match error {
+                    ErrorKind::NoMainProcedure => "no-main-procedure",
+                    ErrorKind::MainHasParameters { .. } => "main-has-parameters",
+                    ErrorKind::MainNotProcedure { .. } => "main-not-procedure",
+                }
+            }
+            ErrorKind::ForbiddenExtern(..) => "forbidden-extern",
+            ErrorKind::Syntax(_, error) => {
+                use trilogy_parser::syntax::ErrorKind;
+                match error.kind() {
+                    ErrorKind::Unknown(..) => "syntax",
+                    ErrorKind::RuleRightArrow => "rule-right-arrow",
+                    ErrorKind::KwNotInExpression => "not-in-expression",
+                    ErrorKind::MatchStatementExpressionCase => "match-statement-expression-case",
+                    ErrorKind::TripleDot { .. } => "triple-dot",
+                    ErrorKind::IfExpressionRestriction => "if-expression-without-else",
+                    ErrorKind::ForExpressionRestriction => "for-expression-without-where",
+                    ErrorKind::TaggedTemplateMissingIdentifier => {
+                        "tagged-template-missing-identifier"
                     }
+                    ErrorKind::TaggedTemplateNotIdentifier => "tagged-template-not-identifier",
+                    ErrorKind::DoMissingParameterList => "do-missing-parameter-list",
+                    ErrorKind::DoUnnecessaryBangOParen => "do-unnecessary-bang",
+                }
+            }
+        }
+    }
+
+    fn describe(&self, severity: Severity) -> Diagnostic {
+        match &self.0 {
+            ErrorKind::External(error) => Diagnostic::new(severity, error.to_string()),
+            ErrorKind::Ir(location, error) => {
+                use trilogy_ir::Error;
+                match error {
+                    Error::Unimplemented { feature, span } => Diagnostic::new(
+                        severity,
+                        format!("feature `{feature}` is not implemented"),
+                    )
+                    .at(location, *span)
+                    .with_label(*span, "used here"),
+                    Error::UnknownExport { name } => Diagnostic::new(
+                        severity,
+                        format!("exporting undeclared identifier `{}`", name.as_ref()),
+                    )
+                    .at(location, name.span())
+                    .with_label(name.span(), "listed here"),
+                    Error::UnboundIdentifier { name } => Diagnostic::new(
+                        severity,
+                        format!("reference to undeclared identifier `{}`", name.as_ref()),
+                    )
+                    .at(location, name.span())
+                    .with_label(name.span(), "referenced here"),
                     Error::DuplicateDefinition {
                         original,
                         duplicate,
-                    } => {
-                        let span = cache.span(location, duplicate.span());
-                        let original = cache.span(location, *original);
-                        ariadne::Report::build(kind, span.clone())
-                            .with_message(format!(
-                                "duplicate declaration of `{}` conflicts with {}",
-                                duplicate.as_ref().fg(primary),
-                                "original declaration".fg(secondary),
-                            ))
-                            .with_label(
-                                Label::new(span)
-                                    .with_color(primary)
-                                    .with_message("this declaration...")
-                                    .with_order(1)
-                            )
-                            .with_label(
-                                Label::new(original)
-                                    .with_color(secondary)
-                                    .with_message("... conflicts with the original declaration here")
-                                    .with_order(2)
-                            )
-                            .with_note("all declarations in the same scope with the same name must be of the same type and arity")
-                    }
+                    } => Diagnostic::new(
+                        severity,
+                        format!(
+                            "duplicate declaration of `{}` conflicts with original declaration",
+                            duplicate.as_ref()
+                        ),
+                    )
+                    .at(location, duplicate.span())
+                    .with_label(duplicate.span(), "this declaration...")
+                    .with_label(*original, "... conflicts with the original declaration here")
+                    .with_note("all declarations in the same scope with the same name must be of the same type and arity"),
                     Error::DuplicateExport {
                         original,
                         duplicate,
-                    } => {
-                        let span = cache.span(location, duplicate.span());
-                        let original = cache.span(location, *original);
-                        ariadne::Report::build(kind, span.clone())
-                            .with_message(format!(
-                                "identifier `{}` has already been exported",
-                                duplicate.as_ref().fg(primary),
-                            ))
-                            .with_label(
-                                Label::new(span)
-                                    .with_color(primary)
-                                    .with_message("this export...")
-                                    .with_order(1),
-                            )
-                            .with_label(
-                                Label::new(original)
-                                    .with_color(secondary)
-                                    .with_message("... was already listed here")
-                                    .with_order(2),
-                            )
-                    }
-                    Error::IdentifierInOwnDefinition { name } => {
-                        let span = cache.span(location, name.span);
-                        let declaration_span = cache.span(location, name.declaration_span);
-                        ariadne::Report::build(kind, span.clone())
-                            .with_message(format!(
-                                "declaration of `{}` references itself in its own initializer",
-                                name.id.name().fg(primary),
-                            ))
-                            .with_label(
-                                Label::new(declaration_span)
-                                    .with_color(primary)
-                                    .with_message("variable being declared here")
-                                    .with_order(1),
-                            )
-                            .with_label(
-                                Label::new(span)
-                                    .with_color(primary)
-                                    .with_message("is referenced in its own initializer")
-                                    .with_order(2),
-                            )
-                            .with_config(Config::default().with_cross_gap(false))
-                    }
-                    Error::AssignedImmutableBinding { name, assignment } => {
-                        let span = cache.span(location, *assignment);
-                        let declaration_span = cache.span(location, name.declaration_span);
-                        ariadne::Report::build(kind, span.clone())
-                            .with_message(format!(
-                                "cannot reassign immutable variable `{}`",
-                                name.id.name().fg(primary)
-                            ))
-                            .with_label(
-                                Label::new(declaration_span)
-                                    .with_color(primary)
-                                    .with_message("variable declared immutably")
-                                    .with_order(1),
-                            )
-                            .with_label(
-                                Label::new(span)
-                                    .with_color(primary)
-                                    .with_message("is being reassigned here")
-                                    .with_order(2),
-                            )
-                            .with_help(format!(
-                                "consider making this binding mutable: `mut {}`",
-                                name.id.name(),
-                            ))
-                    }
+                    } => Diagnostic::new(
+                        severity,
+                        format!(
+                            "identifier `{}` has already been exported",
+                            duplicate.as_ref()
+                        ),
+                    )
+                    .at(location, duplicate.span())
+                    .with_label(duplicate.span(), "this export...")
+                    .with_label(*original, "... was already listed here"),
+                    Error::IdentifierInOwnDefinition { name } => Diagnostic::new(
+                        severity,
+                        format!(
+                            "declaration of `{}` references itself in its own initializer",
+                            name.id.name()
+                        ),
+                    )
+                    .at(location, name.span)
+                    .with_label(name.declaration_span, "variable being declared here")
+                    .with_label(name.span, "is referenced in its own initializer"),
+                    Error::AssignedImmutableBinding { name, assignment } => Diagnostic::new(
+                        severity,
+                        format!("cannot reassign immutable variable `{}`", name.id.name()),
+                    )
+                    .at(location, *assignment)
+                    .with_label(name.declaration_span, "variable declared immutably")
+                    .with_label(*assignment, "is being reassigned here")
+                    .with_help(format!(
+                        "consider making this binding mutable: `mut {}`",
+                        name.id.name(),
+                    )),
                     Error::InvalidAssignmentTarget { target } => {
-                        let span = cache.span(location, *target);
-                        ariadne::Report::build(kind, span.clone())
-                            .with_message("invalid assignment target")
-                            .with_label(
-                                Label::new(span)
-                                    .with_color(primary)
-                                    .with_message("this expression is not assignable")
-                                    .with_order(1),
-                            )
+                        Diagnostic::new(severity, "invalid assignment target")
+                            .at(location, *target)
+                            .with_label(*target, "this expression is not assignable")
                             .with_help("only single identifiers or member access expressions (`.`) can be assigned to")
                     }
-                    Error::GluePatternMissingLiteral { lhs, glue, rhs } => {
-                        let lhs = cache.span(location, *lhs);
-                        let glue = cache.span(location, *glue);
-                        let rhs = cache.span(location, *rhs);
-                        ariadne::Report::build(kind, glue.clone())
-                            .with_message(
-                                "at least one side of a glue pattern must be a string literal",
-                            )
-                            .with_label(
-                                Label::new(glue)
-                                    .with_message("in this glue pattern")
-                                    .with_color(primary)
-                                    .with_order(1),
-                            )
-                            .with_label(
-                                Label::new(lhs)
-                                    .with_message("neither the left hand side")
-                                    .with_color(secondary)
-                                    .with_order(2),
-                            )
-                            .with_label(
-                                Label::new(rhs)
-                                    .with_message("nor the right hand side is a string literal")
-                                    .with_color(tertiary)
-                                    .with_order(3),
-                            )
-                    }
-                    Error::NonConstantExpressionInConstant { expression } => {
-                        let span = cache.span(location, *expression);
-                        ariadne::Report::build(kind, span.clone())
-                            .with_message(
-                                "only constant expressions are allowed in constant definitions",
-                            )
-                            .with_label(
-                                Label::new(span)
-                                    .with_message("in this expression")
-                                    .with_color(primary)
-                                    .with_order(1),
-                            )
-                    }
-                    Error::NoReturnFromRule { expression } => {
-                        let span = cache.span(location, *expression);
-                        ariadne::Report::build(kind, span.clone())
-                            .with_message(
-                                "use of the return keyword is not valid in the body of a rule",
-                            )
-                            .with_label(
-                                Label::new(span)
-                                    .with_message("in this expression")
-                                    .with_color(primary)
-                                    .with_order(1),
-                            )
-                    }
-                    Error::MultiValuedPatternInSet { expression } => {
-                        let span = cache.span(location, *expression);
-                        ariadne::Report::build(kind, span.clone())
-                            .with_message(
-                                "the elements of a set pattern may only bind to a single value",
-                            )
-                            .with_label(
-                                Label::new(span)
-                                    .with_message(
-                                        "this pattern can possibly match more than one value",
-                                    )
-                                    .with_color(primary)
-                                    .with_order(1),
-                            )
-                    }
-                    Error::MultiValuedPatternInRecordKey { expression } => {
-                        let span = cache.span(location, *expression);
-                        ariadne::Report::build(kind, span.clone())
-                            .with_message(
-                                "the keys of a record pattern may only bind to a single value",
-                            )
-                            .with_label(
-                                Label::new(span)
-                                    .with_message(
-                                        "this pattern can possibly match more than one value",
-                                    )
-                                    .with_color(primary)
-                                    .with_order(1),
-                            )
-                    }
+                    Error::GluePatternMissingLiteral { lhs, glue, rhs } => Diagnostic::new(
+                        severity,
+                        "at least one side of a glue pattern must be a string literal",
+                    )
+                    .at(location, *glue)
+                    .with_label(*glue, "in this glue pattern")
+                    .with_label(*lhs, "neither the left hand side")
+                    .with_label(*rhs, "nor the right hand side is a string literal"),
+                    Error::NonConstantExpressionInConstant { expression } => Diagnostic::new(
+                        severity,
+                        "only constant expressions are allowed in constant definitions",
+                    )
+                    .at(location, *expression)
+                    .with_label(*expression, "in this expression"),
+                    Error::NoReturnFromRule { expression } => Diagnostic::new(
+                        severity,
+                        "use of the return keyword is not valid in the body of a rule",
+                    )
+                    .at(location, *expression)
+                    .with_label(*expression, "in this expression"),
+                    Error::MultiValuedPatternInSet { expression } => Diagnostic::new(
+                        severity,
+                        "the elements of a set pattern may only bind to a single value",
+                    )
+                    .at(location, *expression)
+                    .with_label(
+                        *expression,
+                        "this pattern can possibly match more than one value",
+                    ),
+                    Error::MultiValuedPatternInRecordKey { expression } => Diagnostic::new(
+                        severity,
+                        "the keys of a record pattern may only bind to a single value",
+                    )
+                    .at(location, *expression)
+                    .with_label(
+                        *expression,
+                        "this pattern can possibly match more than one value",
+                    ),
                     Error::BreakOutsideLoopContext { span } => {
-                        let span = cache.span(location, *span);
-                        ariadne::Report::build(kind, span.clone())
-                            .with_message("break may not be used outside of a loop")
-                            .with_label(
-                                Label::new(span)
-                                    .with_message("break used here")
-                                    .with_color(primary)
-                                    .with_order(1),
-                            )
+                        Diagnostic::new(severity, "break may not be used outside of a loop")
+                            .at(location, *span)
+                            .with_label(*span, "break used here")
                     }
                     Error::ContinueOutsideLoopContext { span } => {
-                        let span = cache.span(location, *span);
-                        ariadne::Report::build(kind, span.clone())
-                            .with_message("continue may not be used outside of a loop")
-                            .with_label(
-                                Label::new(span)
-                                    .with_message("continue used here")
-                                    .with_color(primary)
-                                    .with_order(1),
-                            )
+                        Diagnostic::new(severity, "continue may not be used outside of a loop")
+                            .at(location, *span)
+                            .with_label(*span, "continue used here")
                     }
                     Error::CancelOutsideHandlerContext { span } => {
-                        let span = cache.span(location, *span);
-                        ariadne::Report::build(kind, span.clone())
-                            .with_message("cancel may not be used outside of a handler")
-                            .with_label(
-                                Label::new(span)
-                                    .with_message("cancel used here")
-                                    .with_color(primary)
-                                    .with_order(1),
-                            )
+                        Diagnostic::new(severity, "cancel may not be used outside of a handler")
+                            .at(location, *span)
+                            .with_label(*span, "cancel used here")
                     }
                     Error::ResumeOutsideHandlerContext { span } => {
-                        let span = cache.span(location, *span);
-                        ariadne::Report::build(kind, span.clone())
-                            .with_message("resume may not be used outside of a handler")
-                            .with_label(
-                                Label::new(span)
-                                    .with_message("resume used here")
-                                    .with_color(primary)
-                                    .with_order(1),
-                            )
+                        Diagnostic::new(severity, "resume may not be used outside of a handler")
+                            .at(location, *span)
+                            .with_label(*span, "resume used here")
                     }
                     Error::BecomeOutsideHandlerContext { span } => {
-                        let span = cache.span(location, *span);
-                        ariadne::Report::build(kind, span.clone())
-                            .with_message("become may not be used outside of a handler")
-                            .with_label(
-                                Label::new(span)
-                                    .with_message("become used here")
-                                    .with_color(primary)
-                                    .with_order(1),
-                            )
-                    }
-                    Error::UnknownCallingConvention { span, value } => {
-                        let span = cache.span(location, *span);
-                        ariadne::Report::build(kind, span.clone())
-                            .with_message(format!("{value} is not a valid calling convention for an external procedure"))
-                            .with_label(
-                                Label::new(span)
-                                    .with_message("named here")
-                                    .with_color(primary)
-                                    .with_order(1),
-                            )
-                            .with_note("valid calling conventions are: \"c\" or \"trilogy\"")
+                        Diagnostic::new(severity, "become may not be used outside of a handler")
+                            .at(location, *span)
+                            .with_label(*span, "become used here")
                     }
+                    Error::UnknownCallingConvention { span, value } => Diagnostic::new(
+                        severity,
+                        format!("{value} is not a valid calling convention for an external procedure"),
+                    )
+                    .at(location, *span)
+                    .with_label(*span, "named here")
+                    .with_note("valid calling conventions are: \"c\" or \"trilogy\""),
                 }
             }
             ErrorKind::Analysis(location, error) => {
                 use super::analyzer::ErrorKind;
                 match error {
                     ErrorKind::NoMainProcedure => {
-                        ariadne::Report::build(kind, (location.clone(), 0..0))
-                            .with_message("no definition of `proc main!()` was found")
+                        Diagnostic::new(severity, "no definition of `proc main!()` was found")
+                            .at(location, Span::default())
                     }
                     ErrorKind::MainHasParameters { proc } => {
-                        let span = cache.span(location, proc.overloads[0].span);
-                        ariadne::Report::build(kind, span.clone())
-                            .with_message("definition of `proc main!()` must not accept parameters")
-                            .with_label(Label::new(span).with_color(primary).with_message(format!(
+                        let span = proc.overloads[0].span;
+                        Diagnostic::new(
+                            severity,
+                            "definition of `proc main!()` must not accept parameters",
+                        )
+                        .at(location, span)
+                        .with_label(
+                            span,
+                            format!(
                                 "procedure accepts {} parameters",
                                 proc.overloads[0].parameters.len()
-                            )))
+                            ),
+                        )
+                    }
+                    ErrorKind::MainNotProcedure { item } => {
+                        let (span, found) = match item {
+                            DefinitionItem::Function(func) => {
+                                (func.overloads[0].head_span, "func main")
+                            }
+                            DefinitionItem::Constant(constant) => {
+                                (constant.name.span, "const main")
+                            }
+                            DefinitionItem::Rule(rule) => {
+                                (rule.overloads[0].head_span, "rule main")
+                            }
+                            DefinitionItem::Module(module) => (module.name.span, "module main"),
+                            DefinitionItem::Test(..) => unreachable!(),
+                            DefinitionItem::Procedure(..) => unreachable!(),
+                        };
+                        Diagnostic::new(severity, "no definition of `proc main!()` was found")
+                            .at(location, span)
+                            .with_label(
+                                span,
+                                format!("`{found}` was found, but main must be a procedure"),
+                            )
                     }
-                    ErrorKind::MainNotProcedure { item } => match item {
-                        DefinitionItem::Function(func) => {
-                            let span = cache.span(location, func.overloads[0].head_span);
-                            ariadne::Report::build(kind, span.clone())
-                                .with_message("no definition of `proc main!()` was found")
-                                .with_label(Label::new(span).with_color(primary).with_message(
-                                    "`func main` was found, but main must be a procedure",
-                                ))
-                        }
-                        DefinitionItem::Constant(constant) => {
-                            let span = cache.span(location, constant.name.span);
-                            ariadne::Report::build(kind, span.clone())
-                                .with_message("no definition of `proc main!()` was found")
-                                .with_label(Label::new(span).with_color(primary).with_message(
-                                    "`const main` was found, but main must be a procedure",
-                                ))
-                        }
-                        DefinitionItem::Rule(rule) => {
-                            let span = cache.span(location, rule.overloads[0].head_span);
-                            ariadne::Report::build(kind, span.clone())
-                                .with_message("no definition of `proc main!()` was found")
-                                .with_label(Label::new(span).with_color(primary).with_message(
-                                    "`rule main` was found, but main must be a procedure",
-                                ))
-                        }
-                        DefinitionItem::Module(module) => {
-                            let span = cache.span(location, module.name.span);
-                            ariadne::Report::build(kind, span.clone())
-                                .with_message("no definition of `proc main!()` was found")
-                                .with_label(Label::new(span).with_color(primary).with_message(
-                                    "`module main` was found, but main must be a procedure",
-                                ))
-                        }
-                        DefinitionItem::Test(..) => unreachable!(),
-                        DefinitionItem::Procedure(..) => unreachable!(),
-                    },
                 }
             }
+            ErrorKind::ForbiddenExtern(location, span) => Diagnostic::new(
+                severity,
+                "external procedures may only be declared by the `trilogy:` libraries",
+            )
+            .at(location, *span)
+            .with_label(*span, "declared here")
+            .with_note("this program was built with `extern` declarations disallowed"),
             ErrorKind::Syntax(location, error) => {
                 use trilogy_parser::syntax::ErrorKind;
-                let span = cache.span(location, error.span());
+                let span = error.span();
+                let diagnostic =
+                    |message: &str| Diagnostic::new(severity, message).at(location, span);
                 match error.kind() {
-                    ErrorKind::Unknown(message) => ariadne::Report::build(kind, span.clone())
-                        .with_message(message)
-                        .with_label(Label::new(span).with_color(primary)),
-                    ErrorKind::RuleRightArrow => ariadne::Report::build(kind, span.clone())
-                        .with_message(format!("a `{}` may not be used in a rule definition", "->".fg(primary)))
-                        .with_label(Label::new(span).with_color(primary).with_message("try replacing it with a `<-`")),
-                    ErrorKind::KwNotInExpression => ariadne::Report::build(kind, span.clone())
-                        .with_message(format!("the `{}` keyword may not be used in an expression, did you mean to use the `!` operator?", "not".fg(primary)))
-                        .with_label(Label::new(span).with_color(primary).with_message("try replacing this `not` with `!`")),
-                    ErrorKind::MatchStatementExpressionCase => ariadne::Report::build(kind, span.clone())
-                        .with_message("cases in a match statement must be handled with blocks")
-                        .with_label(Label::new(span).with_color(primary).with_message("try replacing this handler with a block")),
-                    ErrorKind::TripleDot { dot } => {
-                        let dot = cache.span(location, *dot);
-                        ariadne::Report::build(kind, span.clone())
-                            .with_message(format!("unexpected extra `{}` in spread (`{}`) expression", ".".fg(primary), "..".fg(secondary)))
-                            .with_label(Label::new(span).with_color(secondary).with_message("in this spread expression"))
-                            .with_label(Label::new(dot).with_color(primary).with_message("try removing this `.`"))
-                            .with_help("the spread operator uses only two (`..`)")
-                    }
-                    ErrorKind::IfExpressionRestriction => ariadne::Report::build(kind, span)
-                        .with_message("an `if` expression must have an `else` clause"),
-                    ErrorKind::TaggedTemplateMissingIdentifier => ariadne::Report::build(kind, span.clone())
-                        .with_message("a tagged template requires a tag identifier")
-                        .with_label(Label::new(span).with_color(primary).with_message("try inserting an identifier here")),
-                    ErrorKind::TaggedTemplateNotIdentifier => ariadne::Report::build(kind, span.clone())
-                        .with_message("the `$` operator prefixing a tagged template requires an identifier")
-                        .with_label(Label::new(span).with_color(primary).with_message("this must be an identifier")),
-                    ErrorKind::DoMissingParameterList => ariadne::Report::build(kind, span.clone())
-                        .with_message("a `do` closure requires a parameter list, even if empty")
-                        .with_label(Label::new(span).with_color(primary).with_message("try adding `()` after this `do`")),
-                    ErrorKind::DoUnnecessaryBangOParen => ariadne::Report::build(kind, span.clone())
-                        .with_message("a `do` closure definition does not use `!`")
-                        .with_label(Label::new(span).with_color(primary).with_message("try removing this `!`")),
+                    ErrorKind::Unknown(message) => diagnostic(message).with_unlabelled_span(span),
+                    ErrorKind::RuleRightArrow => diagnostic("a `->` may not be used in a rule definition")
+                        .with_label(span, "try replacing it with a `<-`"),
+                    ErrorKind::KwNotInExpression => diagnostic("the `not` keyword may not be used in an expression, did you mean to use the `!` operator?")
+                        .with_label(span, "try replacing this `not` with `!`"),
+                    ErrorKind::MatchStatementExpressionCase => diagnostic("cases in a match statement must be handled with blocks")
+                        .with_label(span, "try replacing this handler with a block"),
+                    ErrorKind::TripleDot { dot } => diagnostic("unexpected extra `.` in spread (`..`) expression")
+                        .with_label(*dot, "try removing this `.`")
+                        .with_label(span, "in this spread expression")
+                        .with_help("the spread operator uses only two (`..`)"),
+                    ErrorKind::IfExpressionRestriction => diagnostic("an `if` expression must have an `else` clause"),
+                    ErrorKind::ForExpressionRestriction => diagnostic("a `for` expression must have a `where` clause")
+                        .with_help("the `where` clause names the accumulator and its initial value, as in `for Q where acc = init { ... }`"),
+                    ErrorKind::TaggedTemplateMissingIdentifier => diagnostic("a tagged template requires a tag identifier")
+                        .with_label(span, "try inserting an identifier here"),
+                    ErrorKind::TaggedTemplateNotIdentifier => diagnostic("the `$` operator prefixing a tagged template requires an identifier")
+                        .with_label(span, "this must be an identifier"),
+                    ErrorKind::DoMissingParameterList => diagnostic("a `do` closure requires a parameter list, even if empty")
+                        .with_label(span, "try adding `()` after this `do`"),
+                    ErrorKind::DoUnnecessaryBangOParen => diagnostic("a `do` closure definition does not use `!`")
+                        .with_label(span, "try removing this `!`"),
                 }
             }
             ErrorKind::Resolver(location, error) => {
-                let span = cache.span(location, error.span);
-                ariadne::Report::build(kind, span.clone())
-                    .with_message(format!(
-                        "module resolution failed for module {}: {error}",
-                        error.location.as_ref().fg(primary)
-                    ))
-                    .with_label(
-                        Label::new(span)
-                            .with_message("module referenced here")
-                            .with_color(primary),
-                    )
+                let diagnostic = Diagnostic::new(
+                    severity,
+                    format!(
+                        "module resolution failed for module `{}`: {error}",
+                        error.location
+                    ),
+                );
+                // A module that is not imported by another, such as the entrypoint, has
+                // no source to point at.
+                let diagnostic = if *location == error.location {
+                    diagnostic
+                } else {
+                    diagnostic
+                        .at(location, error.span)
+                        .with_label(error.span, "module referenced here")
+                };
+                match error.kind {
+                    loader::ErrorKind::Integrity(..) => diagnostic.with_note(
+                        "the remote module has changed since it was pinned; if the change is expected, run `trilogy update` to pin its new contents",
+                    ),
+                    loader::ErrorKind::Forbidden => diagnostic.with_note(
+                        "this program was built with imports restricted to certain locations",
+                    ),
+                    loader::ErrorKind::Offline => diagnostic.with_note(
+                        "remote modules must be cached or vendored to be used offline; run `trilogy vendor` while online to vendor them",
+                    ),
+                    _ => diagnostic,
+                }
+            }
+        }
+    }
+}
+
+impl<E: std::error::Error + 'static> Display for Report<E> {
+    /// Formats this report as it would be printed, but without color.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut output = vec![];
+        self.write(&mut output, false).map_err(|_| fmt::Error)?;
+        write!(f, "{}", String::from_utf8_lossy(&output))
+    }
+}
+
+/// Whether a [`Report`][] is written with color.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum ColorChoice {
+    /// Use color if the stream being written to is a terminal, unless the `NO_COLOR`
+    /// environment variable is set.
+    #[default]
+    Auto,
+    /// Always use color.
+    Always,
+    /// Never use color.
+    Never,
+}
+
+impl ColorChoice {
+    fn enabled(self, stream: &impl IsTerminal) -> bool {
+        match self {
+            Self::Auto => {
+                stream.is_terminal()
+                    && std::env::var_os("NO_COLOR").is_none_or(|value| value.is_empty())
             }
+            Self::Always => true,
+            Self::Never => false,
+        }
+    }
+}
+
+impl Diagnostic {
+    fn write<C: ariadne::Cache<Location>>(
+        &self,
+        mut cache: C,
+        mut writer: impl Write,
+        color: bool,
+    ) -> io::Result<()> {
+        let Some(location) = self.location() else {
+            return writeln!(writer, "{}", self.message());
         };
-        report.finish().eprint(cache).unwrap();
+        let kind = match self.severity() {
+            Severity::Error => ReportKind::Error,
+            Severity::Warning => ReportKind::Warning,
+        };
+        let mut colors = ColorGenerator::new();
+        let primary = colors.next();
+        let mut report = ariadne::Report::build(kind, cache.span(location, self.span()))
+            .with_config(Config::default().with_color(color))
+            .with_message(highlight(self.message(), Some(primary).filter(|_| color)));
+        for (order, label) in self.labels().iter().enumerate() {
+            let color = if order == 0 { primary } else { colors.next() };
+            let mut ariadne_label = ariadne::Label::new(cache.span(label.location(), label.span()))
+                .with_color(color)
+                .with_order(order as i32);
+            if let Some(message) = label.message() {
+                ariadne_label = ariadne_label.with_message(message);
+            }
+            report = report.with_label(ariadne_label);
+        }
+        for note in self.notes() {
+            report = report.with_note(note);
+        }
+        if let Some(help) = self.help() {
+            report = report.with_help(help);
+        }
+        report.finish().write(cache, writer)
     }
 }
 
+/// Colours the `quoted` parts of a message.
+fn highlight(message: &str, color: Option<Color>) -> String {
+    let Some(color) = color else {
+        return message.to_owned();
+    };
+    message
+        .split('`')
+        .enumerate()
+        .map(|(i, part)| {
+            if i % 2 == 0 {
+                part.to_owned()
+            } else {
+                format!("`{}`", part.fg(color))
+            }
+        })
+        .collect()
+}
+
 pub(super) struct ReportBuilder<E: std::error::Error> {
     errors: Vec<Error<E>>,
     warnings: Vec<Error<E>>,
     libraries: HashMap<Location, String>,
+    resolvers: Resolvers,
+    remote: RemoteOptions,
 }
 
 impl<E: std::error::Error> Default for ReportBuilder<E> {
@@ -499,6 +567,8 @@ impl<E: std::error::Error> Default for ReportBuilder<E> {
             errors: vec![],
             warnings: vec![],
             libraries: HashMap::default(),
+            resolvers: Resolvers::default(),
+            remote: RemoteOptions::default(),
         }
     }
 }
@@ -520,6 +590,14 @@ impl<E: std::error::Error> ReportBuilder<E> {
         self.libraries.extend(libraries);
     }
 
+    pub fn add_resolvers(&mut self, resolvers: Resolvers) {
+        self.resolvers.extend(resolvers);
+    }
+
+    pub fn set_remote_options(&mut self, remote: RemoteOptions) {
+        self.remote = remote;
+    }
+
     pub fn report<C: Cache<Error = E> + 'static>(
         self,
         relative_base: PathBuf,
@@ -531,6 +609,8 @@ impl<E: std::error::Error> ReportBuilder<E> {
             errors: self.errors,
             warnings: self.warnings,
             libraries: self.libraries.clone(),
+            resolvers: self.resolvers,
+            remote: self.remote,
         }
     }
 
@@ -0,0 +1,142 @@
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime};
+use trilogy_llvm::{CachedModule, ModuleCache};
+
+/// How long a compiled module is kept without being used before it is evicted.
+const MAX_AGE: Duration = Duration::from_secs(30 * 24 * 60 * 60);
+
+/// A cache of compiled modules on the file system, so that modules that have not changed
+/// since they were last compiled are only linked, not compiled again.
+///
+/// Compiled modules are stored by their fingerprint, which covers the module's source,
+/// the sources of the modules it imports, and the compiler. Since a module that changes
+/// is stored anew, modules that have not been used for [`MAX_AGE`][] are evicted.
+#[derive(Clone, Debug)]
+pub(crate) struct CompilationCache {
+    dir: PathBuf,
+    fingerprints: HashMap<String, String>,
+}
+
+impl CompilationCache {
+    pub(crate) fn new(dir: PathBuf, fingerprints: HashMap<String, String>) -> Self {
+        Self { dir, fingerprints }
+    }
+
+    /// Removes the modules that have not been used for [`MAX_AGE`][]. This scans the
+    /// whole cache, so is done once per build rather than as each module is saved.
+    pub(crate) fn evict(&self) {
+        let Ok(entries) = fs::read_dir(&self.dir) else {
+            return;
+        };
+        let now = SystemTime::now();
+        for entry in entries.flatten() {
+            let expired = entry
+                .metadata()
+                .and_then(|metadata| metadata.modified())
+                .is_ok_and(|modified| now.duration_since(modified).is_ok_and(|age| age > MAX_AGE));
+            if expired && let Err(error) = fs::remove_file(entry.path()) {
+                log::warn!(
+                    "failed to evict compiled module {}: {error}",
+                    entry.path().display()
+                );
+            }
+        }
+    }
+
+    fn path(&self, location: &str) -> Option<PathBuf> {
+        let fingerprint = self.fingerprints.get(location)?;
+        Some(self.dir.join(format!("{fingerprint}.bc")))
+    }
+}
+
+impl ModuleCache for CompilationCache {
+    fn load(&self, location: &str) -> Option<CachedModule> {
+        let path = self.path(location)?;
+        let bytes = fs::read(&path).ok()?;
+        let module = CachedModule::from_bytes(&bytes)?;
+        // Marks the module as used, so it is not evicted.
+        let _ = File::options()
+            .write(true)
+            .open(&path)
+            .and_then(|file| file.set_modified(SystemTime::now()));
+        Some(module)
+    }
+
+    fn save(&self, location: &str, module: &CachedModule) {
+        let Some(path) = self.path(location) else {
+            return;
+        };
+        // Written to a temporary file first, so that another compilation never sees a
+        // partially written module.
+        let temporary = path.with_extension(format!("{}.tmp", std::process::id()));
+        let result = fs::create_dir_all(&self.dir)
+            .and_then(|_| fs::write(&temporary, module.to_bytes()))
+            .and_then(|_| fs::rename(&temporary, &path));
+        if let Err(error) = result {
+            log::warn!("failed to cache compiled module {location}: {error}");
+            let _ = fs::remove_file(&temporary);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn cache(name: &str) -> CompilationCache {
+        let dir = std::env::temp_dir().join(format!(
+            "trilogy-compilation-cache-{name}-{}",
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        CompilationCache::new(
+            dir,
+            HashMap::from([
+                ("main".to_owned(), "aaaa".to_owned()),
+                ("util".to_owned(), "bbbb".to_owned()),
+            ]),
+        )
+    }
+
+    fn module() -> CachedModule {
+        CachedModule::from_bytes(&[b"TRILOGY-MODULE-1".as_slice(), &[0; 24]].concat()).unwrap()
+    }
+
+    #[test]
+    fn evicts_unused_modules() {
+        let cache = cache("evict");
+        cache.save("main", &module());
+        cache.save("util", &module());
+        let old = SystemTime::now() - MAX_AGE - Duration::from_secs(60);
+        File::options()
+            .write(true)
+            .open(cache.path("util").unwrap())
+            .and_then(|file| file.set_modified(old))
+            .unwrap();
+
+        cache.evict();
+        assert!(cache.load("main").is_some());
+        assert!(cache.load("util").is_none());
+        fs::remove_dir_all(&cache.dir).unwrap();
+    }
+
+    #[test]
+    fn load_marks_modules_used() {
+        let cache = cache("used");
+        cache.save("main", &module());
+        let old = SystemTime::now() - MAX_AGE - Duration::from_secs(60);
+        let path = cache.path("main").unwrap();
+        File::options()
+            .write(true)
+            .open(&path)
+            .and_then(|file| file.set_modified(old))
+            .unwrap();
+
+        assert!(cache.load("main").is_some());
+        cache.evict();
+        assert!(path.exists());
+        fs::remove_dir_all(&cache.dir).unwrap();
+    }
+}
@@ -0,0 +1,56 @@
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Links a compiled object file into a native executable.
+///
+/// The object file already contains the Trilogy runtime, so it only needs to be linked
+/// against the system libraries. Linking is done by `clang`, which is taken from the
+/// same LLVM installation that Trilogy was built with if `LLVM_SYS_191_PREFIX` is set.
+pub(super) fn link(object: &Path, output: &Path) -> io::Result<()> {
+    let clang = std::env::var("LLVM_SYS_191_PREFIX")
+        .map(|prefix| PathBuf::from(prefix).join("bin").join("clang"))
+        .unwrap_or_else(|_| PathBuf::from("clang"));
+    let result = Command::new(&clang)
+        .arg(object)
+        // Symbols must be exported so that `extern` procedures can be located at runtime.
+        .args(["-ldl", "-rdynamic", "-o"])
+        .arg(output)
+        .output()
+        .map_err(|error| {
+            io::Error::new(
+                error.kind(),
+                format!("failed to run linker `{}`: {error}", clang.display()),
+            )
+        })?;
+    if !result.status.success() {
+        return Err(io::Error::other(format!(
+            "linking failed:\n{}",
+            String::from_utf8_lossy(&result.stderr)
+        )));
+    }
+    Ok(())
+}
+
+/// A temporary path for an object file, which is removed when dropped.
+pub(super) struct ObjectFile(PathBuf);
+
+impl ObjectFile {
+    pub(super) fn new(output: &Path) -> Self {
+        let name = output
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_else(|| "main".to_owned());
+        Self(std::env::temp_dir().join(format!("{name}-{}.o", std::process::id())))
+    }
+
+    pub(super) fn path(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for ObjectFile {
+    fn drop(&mut self) {
+        std::fs::remove_file(&self.0).ok();
+    }
+}
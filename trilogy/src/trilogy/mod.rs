@@ -1,11 +1,22 @@
 use crate::location::Location;
+use crate::manifest::Manifest;
+use compilation_cache::CompilationCache;
+use std::cell::OnceCell;
 use std::collections::HashMap;
+use std::io;
 use std::path::Path;
+use std::rc::Rc;
 use trilogy_ir::ir::Module;
+use trilogy_llvm::{CompiledProgram, EffectHandler, ModuleCache, NativeProcedure};
 
 mod builder;
+mod compilation_cache;
+mod link;
 
-pub use builder::{Builder, Report};
+pub use builder::{Builder, ColorChoice, Diagnostic, Label, LockfileMode, Report, Severity};
+pub use trilogy_llvm::{
+    CallError, Callable, Continuation, EffectResponse, Outcome, Termination, Value,
+};
 
 #[derive(Clone, Debug)]
 struct Source {
@@ -17,9 +28,21 @@ struct Source {
 ///
 /// This is the entrypoint to the whole Trilogy Programming Language, by which
 /// you can run Trilogy programs and embed them within larger Rust programs.
+///
+/// A `Trilogy` is neither [`Send`][] nor [`Sync`][]: the program is compiled just in time
+/// the first time it is run, and the compiled program is bound to the thread that
+/// compiled it. Clones share that compiled program. To run the same program on another
+/// thread, build another `Trilogy` there. This is a breaking change from earlier
+/// versions, as recorded in the changelog.
 #[derive(Clone, Debug)]
 pub struct Trilogy {
     source: Source,
+    natives: Vec<NativeProcedure>,
+    effect_handler: Option<EffectHandler>,
+    compilation_cache: Option<CompilationCache>,
+    manifest: Option<Manifest>,
+    /// The program, compiled the first time it is run, and reused for every call after.
+    program: OnceCell<Rc<CompiledProgram>>,
 }
 
 pub trait ModulePath {
@@ -39,14 +62,65 @@ impl ModulePath for &[&str] {
 }
 
 impl Trilogy {
-    fn new(source: Source) -> Self {
-        Self { source }
+    fn new(
+        source: Source,
+        natives: Vec<NativeProcedure>,
+        effect_handler: Option<EffectHandler>,
+        compilation_cache: Option<CompilationCache>,
+        manifest: Option<Manifest>,
+    ) -> Self {
+        Self {
+            source,
+            natives,
+            effect_handler,
+            compilation_cache,
+            manifest,
+            program: OnceCell::new(),
+        }
+    }
+
+    fn program(&self) -> &CompiledProgram {
+        self.program.get_or_init(|| {
+            let modules = self
+                .source
+                .modules
+                .iter()
+                .map(|(location, module)| (location.to_string(), module))
+                .collect();
+            let program = CompiledProgram::new(
+                modules,
+                &self.source.entrypoint.to_string(),
+                "main",
+                self.natives.clone(),
+                self.effect_handler.clone(),
+                self.module_cache(),
+            );
+            self.evict_cache();
+            Rc::new(program)
+        })
+    }
+
+    fn module_cache(&self) -> Option<&dyn ModuleCache> {
+        self.compilation_cache
+            .as_ref()
+            .map(|cache| cache as &dyn ModuleCache)
+    }
+
+    fn evict_cache(&self) {
+        if let Some(cache) = &self.compilation_cache {
+            cache.evict();
+        }
     }
 
     pub fn source_entrypoint(&self) -> Option<&Location> {
         Some(&self.source.entrypoint)
     }
 
+    /// The manifest of the project this program was built from, if it has one.
+    pub fn manifest(&self) -> Option<&Manifest> {
+        self.manifest.as_ref()
+    }
+
     /// Loads a Trilogy program from a Trilogy source file on the local file system.
     ///
     /// A program loaded this way uses the default global `~/.trilogy/` cache directory
@@ -63,56 +137,159 @@ impl Trilogy {
 
     /// Runs the loaded Trilogy program by evaluating `main!()`.
     ///
-    /// This is equivalent to `self.call("main", vec![])`.
-    #[expect(clippy::result_unit_err, reason = "This is placeholder")]
-    pub fn run(&self) -> Result<trilogy_llvm::TrilogyValue, ()> {
-        Ok(self.call("main", vec![]))
+    /// The program is compiled the first time it is run or called into, and is reused
+    /// after that, so the state of its module-level constants persists between runs.
+    ///
+    /// This is equivalent to `self.call("main", vec![])`, which cannot fail.
+    pub fn run(&self) -> Termination {
+        self.run_with_args(vec![])
+            .expect("a program run without arguments cannot fail")
+    }
+
+    /// Runs the loaded Trilogy program by evaluating `main!()`, providing it with
+    /// command line arguments.
+    ///
+    /// This is equivalent to `self.call("main", args)`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`CallError::InvalidParameter`][] if an argument contains a NUL character,
+    /// which cannot be passed to a program.
+    pub fn run_with_args(&self, args: Vec<String>) -> Result<Termination, CallError> {
+        self.program().run(&args)
     }
 
     /// Compiles and runs the the loaded Trilogy program in test mode.
-    pub fn test(&self, filter_prefix: &[impl AsRef<str>]) {
+    ///
+    /// Returns the exit code of the test runner, which is non-zero if any test failed.
+    pub fn test(&self, filter_prefix: &[impl AsRef<str>]) -> i32 {
         let modules = self
             .source
             .modules
             .iter()
             .map(|(location, module)| (location.to_string(), module))
             .collect();
-        trilogy_llvm::evaluate_tests(modules, filter_prefix)
+        trilogy_llvm::evaluate_tests(
+            modules,
+            filter_prefix,
+            self.natives.clone(),
+            self.effect_handler.clone(),
+        )
     }
 
     /// Runs the loaded Trilogy, evaluating the exported 0-arity procedure pointed to by
     /// the given path.
     ///
-    /// The returned value is the exit value of the program. This value is either:
+    /// The path is resolved from the entrypoint module as it is for
+    /// [`call_procedure`][Trilogy::call_procedure].
+    ///
+    /// The `parameters` are provided to the program as its command line arguments, which
+    /// it can retrieve using `args!()` from `trilogy:env`.
+    ///
+    /// The returned [`Termination`][] describes how the program finished, from which
+    /// its [exit code][Termination::exit_code] is determined. The exit code is either:
     /// * the value provided to the first `exit` statement that gets executed.
-    /// * the value returned from `main!()`, if it is not `unit`
-    /// * `0` if `main!()` returns `unit`
+    /// * the value returned from the procedure, if it is not `unit`
+    /// * `0` if the procedure returns `unit`
+    /// * `255` if a runtime error occurs, or the value returned from the procedure is not a number.
+    ///
+    /// If the effect handler cancels the program, the value it was cancelled with is reported
+    /// as [`Termination::Cancelled`][] and determines the exit code as a returned value would.
+    ///
+    /// Runtime errors are reported on standard error. Unfortunately at this time, those
+    /// errors are hard to diagnose and could be anything from a bug in the compiler to an
+    /// error in the Trilogy program.
     ///
     /// # Errors
     ///
-    /// If a runtime error occurs while executing this program, that error
-    /// is returned. Unfortunately at this time, those errors are hard to
-    /// diagnose and could be anything from a bug in the compiler to an error
-    /// in the Trilogy program.
+    /// Returns a [`CallError`][] if the path does not refer to an exported 0-arity
+    /// procedure, or if a parameter contains a NUL character. The path `main` runs the
+    /// program's entrypoint, which is never reported as missing.
     pub fn call(
         &self,
         main: impl ModulePath,
         parameters: Vec<String>,
-    ) -> trilogy_llvm::TrilogyValue {
-        let modules = self
-            .source
-            .modules
-            .iter()
-            .map(|(location, module)| (location.to_string(), module))
-            .collect();
+    ) -> Result<Termination, CallError> {
         let path = main.path();
-        let main_name = path.last().unwrap();
-        trilogy_llvm::evaluate(
-            modules,
-            &self.source.entrypoint.to_string(),
-            main_name,
-            parameters,
-        )
+        if path == ["main"] {
+            return self.program().run(&parameters);
+        }
+        self.program().run_member(&path, &parameters)
+    }
+
+    /// Calls the exported procedure pointed to by the given path, returning its result.
+    ///
+    /// The path is resolved from the entrypoint module, through exported submodules,
+    /// so `["util", "parse"]` refers to `parse!` exported by the module `util`. Modules
+    /// that require parameters cannot be traversed this way.
+    ///
+    /// The arguments are converted into Trilogy values before they are passed to the
+    /// procedure. Values that cannot be represented in the program, such as atoms it
+    /// never mentions, are rejected.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`CallError`][] if the path does not refer to an exported procedure
+    /// taking the provided number of arguments, or if the program exits or is cancelled
+    /// by the effect handler before the procedure returns.
+    pub fn call_procedure(
+        &self,
+        path: impl ModulePath,
+        args: Vec<Value>,
+    ) -> Result<Value, CallError> {
+        self.evaluate_call(path, trilogy_llvm::Call::Procedure(args))
+    }
+
+    /// Applies the exported function pointed to by the given path to an argument,
+    /// returning its result.
+    ///
+    /// Paths and arguments are handled as they are for [`call_procedure`][Trilogy::call_procedure].
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`CallError`][] if the path does not refer to an exported function, or
+    /// if the program exits or is cancelled by the effect handler before the function
+    /// returns.
+    pub fn apply_function(&self, path: impl ModulePath, arg: Value) -> Result<Value, CallError> {
+        self.evaluate_call(path, trilogy_llvm::Call::Function(arg))
+    }
+
+    /// Queries the exported rule pointed to by the given path, returning every solution.
+    ///
+    /// Arguments that are `None` are left unbound. Each solution contains the value
+    /// of every argument of the rule, bound or not, in order.
+    ///
+    /// Paths and arguments are handled as they are for [`call_procedure`][Trilogy::call_procedure].
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`CallError`][] if the path does not refer to an exported rule taking
+    /// the provided number of arguments, or if the program exits or is cancelled by the
+    /// effect handler before all solutions are found.
+    pub fn query(
+        &self,
+        path: impl ModulePath,
+        args: Vec<Option<Value>>,
+    ) -> Result<Vec<Vec<Value>>, CallError> {
+        let Value::Array(solutions) = self.evaluate_call(path, trilogy_llvm::Call::Query(args))?
+        else {
+            unreachable!("the solutions of a query are an array");
+        };
+        Ok(solutions
+            .into_iter()
+            .map(|solution| match solution {
+                Value::Array(values) => values,
+                _ => unreachable!("each solution is an array of values"),
+            })
+            .collect())
+    }
+
+    fn evaluate_call(
+        &self,
+        path: impl ModulePath,
+        call: trilogy_llvm::Call,
+    ) -> Result<Value, CallError> {
+        self.program().call(&path.path(), call)
     }
 
     /// Compiles a Trilogy program to LLVM assembly code, returning a single linked module as a string.
@@ -136,4 +313,143 @@ impl Trilogy {
             .collect();
         trilogy_llvm::compile_tests_to_llvm(modules, filter_prefix)
     }
+
+    /// Compiles a Trilogy program to a native executable, written to the given path.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the object code cannot be generated for this machine, or if
+    /// linking fails. Linking requires `clang` to be available.
+    pub fn build(&self, output: impl AsRef<Path>) -> io::Result<()> {
+        let modules = self
+            .source
+            .modules
+            .iter()
+            .map(|(location, module)| (location.to_string(), module))
+            .collect();
+        let object = link::ObjectFile::new(output.as_ref());
+        trilogy_llvm::compile_to_object(
+            modules,
+            &self.source.entrypoint.to_string(),
+            "main",
+            object.path(),
+            self.module_cache(),
+        )
+        .map_err(io::Error::other)?;
+        self.evict_cache();
+        link::link(object.path(), output.as_ref())
+    }
+
+    /// Compiles a Trilogy testsuite to a native executable, written to the given path.
+    ///
+    /// The resulting executable runs the test suite and prints its result.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the object code cannot be generated for this machine, or if
+    /// linking fails. Linking requires `clang` to be available.
+    pub fn build_test(
+        &self,
+        output: impl AsRef<Path>,
+        filter_prefix: &[impl AsRef<str>],
+    ) -> io::Result<()> {
+        let modules = self
+            .source
+            .modules
+            .iter()
+            .map(|(location, module)| (location.to_string(), module))
+            .collect();
+        let object = link::ObjectFile::new(output.as_ref());
+        trilogy_llvm::compile_tests_to_object(modules, filter_prefix, object.path())
+            .map_err(io::Error::other)?;
+        link::link(object.path(), output.as_ref())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Builder;
+    use num::{BigRational, Complex, Zero};
+
+    const SOURCE: &str = "export ask, asked
+
+proc ask!() {
+  return yield 'ask
+}
+
+rule asked(x) <- x = yield 'ask
+";
+
+    fn cancelling() -> Trilogy {
+        Builder::new()
+            .effect_handler(|_| EffectResponse::Cancel(Value::Array(vec![])))
+            .build_from_string("file:///cancel/main.tri".parse().unwrap(), SOURCE)
+            .unwrap()
+    }
+
+    #[test]
+    fn module_state_persists_between_calls() {
+        let trilogy = Builder::new()
+            .build_from_string(
+                "file:///state/main.tri".parse().unwrap(),
+                "export increment\n\nslot mut count = 0\n\nproc increment!() {\n  count += 1\n  return count\n}\n",
+            )
+            .unwrap();
+        for expected in 1..=3 {
+            let count = trilogy.call_procedure("increment", vec![]).unwrap();
+            assert_eq!(
+                count,
+                Value::Number(Complex::new(
+                    BigRational::from_integer(expected.into()),
+                    BigRational::zero()
+                ))
+            );
+        }
+    }
+
+    #[test]
+    fn nul_parameter() {
+        let trilogy = Builder::new()
+            .build_from_string(
+                "file:///args/main.tri".parse().unwrap(),
+                "proc main!() {}\n",
+            )
+            .unwrap();
+        let parameter = "a\0b".to_owned();
+        assert!(matches!(
+            trilogy.run_with_args(vec![parameter.clone()]),
+            Err(CallError::InvalidParameter(invalid)) if invalid == parameter
+        ));
+    }
+
+    #[test]
+    fn cancelled_call() {
+        let trilogy = cancelling();
+        assert!(matches!(
+            trilogy.call_procedure("ask", vec![]),
+            Err(CallError::Cancelled(value)) if *value == Value::Array(vec![])
+        ));
+    }
+
+    #[test]
+    fn cancelled_query() {
+        let trilogy = cancelling();
+        assert!(matches!(
+            trilogy.query("asked", vec![None]),
+            Err(CallError::Cancelled(value)) if *value == Value::Array(vec![])
+        ));
+    }
+
+    #[test]
+    fn cancelled_run() {
+        let trilogy = cancelling();
+        assert!(matches!(
+            trilogy.call("ask", vec![]),
+            Ok(Termination::Cancelled {
+                exit_code: None,
+                ..
+            })
+        ));
+    }
 }
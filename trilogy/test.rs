@@ -46,6 +46,8 @@ struct Expectation {
     stderr: bool,
     #[serde(default = "const_true")]
     compile: bool,
+    #[serde(default)]
+    args: Vec<String>,
 }
 
 impl Default for Expectation {
@@ -58,6 +60,7 @@ impl Default for Expectation {
             output: String::new(),
             stderr: false,
             compile: true,
+            args: vec![],
         }
     }
 }
@@ -296,7 +299,9 @@ fn test_case(path: PathBuf, done: Sender<Report>) {
                 trilogy_command.args(["--prefix", prefix]);
             }
         } else if report.expected.run {
-            trilogy_command.args(["run", tri.to_str().unwrap()]);
+            trilogy_command
+                .args(["run", tri.to_str().unwrap(), "--"])
+                .args(&report.expected.args);
             let start = Instant::now();
             report.program_output = Some(trilogy_command.output().unwrap());
             report.trilogy_compile_time = start.elapsed();
@@ -362,6 +367,7 @@ fn test_case(path: PathBuf, done: Sender<Report>) {
         } else {
             Command::new(program)
         };
+        program_command.args(&report.expected.args);
         let mut child = program_command
             .stdout(Stdio::piped())
             .stderr(Stdio::piped())